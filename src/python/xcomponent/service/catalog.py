@@ -1,18 +1,21 @@
 """Registry of XComponents."""
 
 import inspect
-from collections.abc import Mapping
+import os
+import pickle
+from collections.abc import Iterator, Mapping
 from functools import wraps
 from types import ModuleType
-from typing import Any, Callable, overload
+from typing import Any, Callable, Literal, NamedTuple, overload
 
 from xcomponent.xcore import (
+    Markup,
     RenderContext,
     XCatalog,
     XNode,
 )
 
-__all__ = ["Component", "Function", "Catalog"]
+__all__ = ["CacheInfo", "Component", "Function", "Catalog", "PropValidation"]
 
 
 Component = Callable[..., str]
@@ -32,14 +35,58 @@ Then the callable has to be decorated with the [@catalog.component](#xcomponent.
 """
 
 
+PropValidation = Literal["strict", "warn", "off"]
+"""How the props given to the components are validated against their parameters."""
+
+
+class CacheInfo(NamedTuple):
+    """Statistics of the cache of the templates parsed by the render method."""
+
+    hits: int
+    misses: int
+    maxsize: int
+    currsize: int
+
+
 class Catalog:
     """
     Store all the components and functions to render templates.
+
+    :param cache_size: number of templates, parsed by the render method,
+        kept in cache. Use 0 to disable the cache.
+    :param validation: how the props given to the components are validated
+        against their parameters, "strict" raises a TypeError, "warn" emits
+        warnings and "off" disables the validation.
+    :param reload: parse the components loaded from template files again
+        when their file has been modified, for the development.
     """
 
-    def __init__(self) -> None:
+    def __init__(
+        self,
+        cache_size: int = 128,
+        validation: PropValidation = "off",
+        reload: bool = False,
+    ) -> None:
         self.scanned: set[ModuleType] = set()
-        self._catalog = XCatalog()
+        self._catalog = XCatalog(cache_size, validation, reload)
+
+    @property
+    def validation(self) -> PropValidation:
+        """How the props given to the components are validated."""
+        return self._catalog.validation  # type: ignore
+
+    @validation.setter
+    def validation(self, validation: PropValidation) -> None:
+        self._catalog.validation = validation
+
+    @property
+    def reload(self) -> bool:
+        """Whether the modified template files are parsed again."""
+        return self._catalog.reload
+
+    @reload.setter
+    def reload(self, reload: bool) -> None:
+        self._catalog.reload = reload
 
     def render(self, content: str, **params: Any) -> str:
         """
@@ -54,6 +101,73 @@ class Catalog:
         """
         return self._catalog.render(content, **params)
 
+    def render_iter(self, content: str, **params: Any) -> Iterator[str]:
+        """
+        Render the given markup by chunks.
+
+        The rendering is done while the chunks are consumed, in order to stream
+        the response, the concatenation of the chunks is the rendered template.
+
+        :param content: The markup to render
+        :param params: rendering context, see the render method.
+        :return: an iterator over the chunks of the rendered template.
+        """
+        return self._catalog.render_iter(content, **params)
+
+    def cache_info(self) -> CacheInfo:
+        """Statistics of the cache of the templates parsed by the render method."""
+        return CacheInfo(**self._catalog.cache_info())
+
+    def cache_clear(self) -> None:
+        """Clear the cache of the templates parsed by the render method."""
+        self._catalog.cache_clear()
+
+    def load_directory(self, path: str | os.PathLike[str]) -> None:
+        """
+        Register the components of the template files of a directory.
+
+        The files with the extension `.xcomponent` or `.xhtml` are registered
+        by their name without extension. Their parameters are declared in a
+        header comment, such as `<!-- props: title: str, count: int = 0 -->`.
+        The subdirectories are registered as namespaces, the file
+        `ui/Button.xhtml` is rendered by `<ui.Button />`.
+
+        :param path: the directory of the template files.
+        """
+        self._catalog.load_directory(path)
+
+    def save(self, path: str | os.PathLike[str]) -> None:
+        """
+        Save the catalog to a file, with its parsed components.
+
+        The components, their compiled expressions, and the functions are saved,
+        a worker process can load the catalog without parsing the templates.
+        The file is a pickle, the functions are saved by reference.
+
+        :param path: the file to write.
+        """
+        with open(path, "wb") as file:
+            pickle.dump(self._catalog, file, protocol=pickle.HIGHEST_PROTOCOL)
+
+    @classmethod
+    def load(cls, path: str | os.PathLike[str]) -> "Catalog":
+        """
+        Load a catalog saved by the save method.
+
+        The file is a pickle, it must be trusted, and saved by the same version
+        of xcomponent.
+
+        :param path: the file to read.
+        :return: the loaded catalog.
+        """
+        with open(path, "rb") as file:
+            xcatalog = pickle.load(file)
+        if not isinstance(xcatalog, XCatalog):
+            raise TypeError(f"{path} does not contain a catalog")
+        catalog = cls()
+        catalog._catalog = xcatalog
+        return catalog
+
     def register_component(
         self,
         component_name: str,
@@ -126,7 +240,7 @@ class Catalog:
                             break
                 for key, typ in template.params.items():
                     if typ is XNode:
-                        kwargs[key] = self._catalog.render(kwargs[key])
+                        kwargs[key] = Markup(self._catalog.render(kwargs[key]))
 
                 context.push(kwargs)
                 return self._catalog.render_node(template.node, context)
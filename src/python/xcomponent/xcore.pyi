@@ -1,9 +1,33 @@
 """Typing for the rust code."""
 
+import os
 from collections.abc import Callable, Mapping
 from enum import Enum
 from typing import Any
 
+class Markup(str):
+    """
+    A string that is safe to render as HTML.
+
+    Expressions are escaped while rendered, except the markup,
+    and objects that implement the `__html__` protocol, such as markupsafe.
+    """
+
+    def __html__(self) -> Markup: ...
+
+class XComponentRenderError(Exception):
+    """
+    Error raised while rendering components.
+
+    The raised error is also an instance of the original exception type.
+    """
+
+    component_stack: list[str]
+    """
+    The components, attributes and functions that led to the error,
+    from the outermost to the innermost.
+    """
+
 class NodeType(Enum):
     Element = "Element"
     ScriptElement = "ScriptElement"
@@ -93,6 +117,11 @@ class XNode:
     def unwrap(
         self,
     ) -> XFragment | XElement | XNSElement | XComment | XText | XExpression: ...
+    def __getstate__(self) -> bytes: ...
+    def __reduce__(self) -> tuple[Callable[[bytes], XNode], tuple[bytes]]: ...
+    @staticmethod
+    def from_state(state: bytes) -> XNode:
+        """The node of a state returned by `__getstate__`."""
 
 def parse_markup(raw: str) -> XNode:
     """
@@ -113,11 +142,20 @@ class XTemplate:
     node: XNode
     params: Mapping[str, type | Any]
     defaults: Mapping[str, Any]
+    origin: os.PathLike[str] | None
+    def __getstate__(self) -> tuple[os.PathLike[str] | None, int | None]: ...
+    def __setstate__(
+        self, state: tuple[os.PathLike[str] | None, int | None]
+    ) -> None: ...
 
 class XCatalog:
     """Catalog of templates en functions."""
 
-    def __init__(self) -> None: ...
+    validation: str
+    reload: bool
+    def __init__(
+        self, cache_size: int = 128, validation: str = "off", reload: bool = False
+    ) -> None: ...
     def add_component(
         self,
         name: str,
@@ -127,9 +165,31 @@ class XCatalog:
         namespaces: "Mapping[str, XCatalog]",
     ) -> None: ...
     def add_function(self, name: str, fn: Callable[..., Any]) -> None: ...
+    def load_directory(self, path: str | os.PathLike[str]) -> None: ...
     def get(self, name: str) -> XTemplate: ...
     def render_node(self, node: XNode, params: RenderContext) -> str: ...
     def render(self, template: str, **params: dict[str, Any]) -> str: ...
+    def render_iter(self, template: str, **params: dict[str, Any]) -> XRenderIter: ...
+    def cache_info(self) -> dict[str, int]: ...
+    def cache_clear(self) -> None: ...
+    def __getstate__(self) -> dict[str, Any]: ...
+    def __setstate__(self, state: Mapping[str, Any]) -> None: ...
+
+class XRenderIter:
+    """Iterator over the chunks of a rendered template."""
+
+    def __iter__(self) -> XRenderIter: ...
+    def __next__(self) -> str: ...
+
+class XLambda:
+    """An arrow function of an expression, with its captured context."""
+
+    def __call__(self, *args: Any, **kwargs: Any) -> Any: ...
+
+class XSlot:
+    """The markup given to a slot of a component, rendered when it is used."""
+
+    def __html__(self) -> Markup: ...
 
 class RenderContext:
     def __init__(self) -> None: ...
@@ -1,16 +1,31 @@
 use std::collections::HashMap;
+use std::fmt;
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use lru::LruCache;
 
 use pyo3::{
+    exceptions::PyValueError,
     prelude::*,
     types::{PyAny, PyDict, PyTuple},
 };
 
 use crate::{
+    catalog::{
+        loader::{load_directory, reload_template},
+        validation::Validation,
+        XRenderIter,
+    },
     context::RenderContext,
+    error::render_error,
     markup::{
-        parser::parse_markup,
+        parser::{parse_markup, parse_template},
         tokens::{ToHtml, XNode},
     },
+    span::Source,
 };
 
 #[pyclass]
@@ -35,13 +50,15 @@ impl PyCallable {
     }
 }
 
-#[pyclass]
+#[pyclass(module = "xcomponent.xcore")]
 #[derive(Debug)]
 pub struct XTemplate {
     node: Py<XNode>,
     params: Py<PyDict>,
     defaults: Py<PyDict>,
     namespaces: Py<PyDict>,
+    origin: Option<PathBuf>,
+    mtime: Option<SystemTime>,
 }
 
 #[pymethods]
@@ -58,6 +75,8 @@ impl XTemplate {
             params,
             defaults,
             namespaces,
+            origin: None,
+            mtime: None,
         }
     }
 
@@ -81,6 +100,48 @@ impl XTemplate {
         self.namespaces.bind(py)
     }
 
+    /// The template file of the component, if it has been loaded from a file.
+    #[getter]
+    pub fn origin(&self) -> Option<&PathBuf> {
+        self.origin.as_ref()
+    }
+
+    fn __getnewargs__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> (Py<XNode>, Py<PyDict>, Py<PyDict>, Py<PyDict>) {
+        (
+            self.node.clone_ref(py),
+            self.params.clone_ref(py),
+            self.defaults.clone_ref(py),
+            self.namespaces.clone_ref(py),
+        )
+    }
+
+    /// The template file of the component, and its modification time
+    /// in nanoseconds.
+    fn __getstate__(&self) -> (Option<PathBuf>, Option<u128>) {
+        let mtime = self
+            .mtime
+            .and_then(|mtime| mtime.duration_since(UNIX_EPOCH).ok())
+            .map(|mtime| mtime.as_nanos());
+        (self.origin.clone(), mtime)
+    }
+
+    fn __setstate__(&mut self, state: (Option<PathBuf>, Option<u128>)) -> PyResult<()> {
+        let (origin, mtime) = state;
+        self.origin = origin;
+        self.mtime = match mtime {
+            Some(mtime) => {
+                let mtime = u64::try_from(mtime)
+                    .map_err(|_| PyValueError::new_err("Invalid modification time"))?;
+                Some(UNIX_EPOCH + Duration::from_nanos(mtime))
+            }
+            None => None,
+        };
+        Ok(())
+    }
+
     pub fn __str__<'py>(&self, py: Python<'py>) -> Result<String, PyErr> {
         let r = self.node.getattr(py, "__repr__")?.call0(py)?;
         let res: String = r.extract(py)?;
@@ -90,20 +151,141 @@ impl XTemplate {
     }
 }
 
-#[pyclass]
-pub struct XCatalog {
+impl XTemplate {
+    /// A component loaded from the template file `path`, modified at `mtime`.
+    pub(crate) fn with_origin(mut self, path: PathBuf, mtime: Option<SystemTime>) -> Self {
+        self.origin = Some(path);
+        self.mtime = mtime;
+        self
+    }
+
+    pub(crate) fn mtime(&self) -> Option<SystemTime> {
+        self.mtime
+    }
+
+    pub(crate) fn set_mtime(&mut self, mtime: Option<SystemTime>) {
+        self.mtime = mtime;
+    }
+
+    /// Replace the parsed template and the parameters of the component.
+    pub(crate) fn update(&mut self, node: Py<XNode>, params: Py<PyDict>, defaults: Py<PyDict>) {
+        self.node = node;
+        self.params = params;
+        self.defaults = defaults;
+    }
+}
+
+/// Documents parsed by [`XCatalog::render`], by template source.
+struct DocumentCache {
+    documents: Option<LruCache<String, Arc<XNode>>>,
+    hits: usize,
+    misses: usize,
+}
+
+impl DocumentCache {
+    fn new(maxsize: usize) -> Self {
+        DocumentCache {
+            documents: NonZeroUsize::new(maxsize).map(LruCache::new),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    fn get(&mut self, template: &str) -> Option<Arc<XNode>> {
+        let node = self
+            .documents
+            .as_mut()
+            .and_then(|documents| documents.get(template).cloned());
+        if node.is_some() {
+            self.hits += 1;
+        } else {
+            self.misses += 1;
+        }
+        node
+    }
+
+    fn put(&mut self, template: &str, node: Arc<XNode>) {
+        if let Some(documents) = self.documents.as_mut() {
+            documents.put(template.to_string(), node);
+        }
+    }
+}
+
+/// The components, the functions and the namespaces of a catalog, shared with
+/// the arrow functions created while rendering, they are copied if they are
+/// updated while being shared.
+#[derive(Default)]
+struct Registry {
     components: HashMap<String, Py<XTemplate>>,
     functions: HashMap<String, Py<PyCallable>>,
+    namespaces: HashMap<String, Py<XCatalog>>,
+}
+
+impl Clone for Registry {
+    fn clone(&self) -> Self {
+        Python::with_gil(|py| Registry {
+            components: self
+                .components
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone_ref(py)))
+                .collect(),
+            functions: self
+                .functions
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone_ref(py)))
+                .collect(),
+            namespaces: self
+                .namespaces
+                .iter()
+                .map(|(k, v)| (k.clone(), v.clone_ref(py)))
+                .collect(),
+        })
+    }
+}
+
+#[pyclass(module = "xcomponent.xcore")]
+pub struct XCatalog {
+    registry: Arc<Registry>,
+    documents: Mutex<DocumentCache>,
+    validation: Validation,
+    reload: bool,
 }
 
 #[pymethods]
 impl XCatalog {
     #[new]
-    pub fn new() -> Self {
-        XCatalog {
-            components: HashMap::new(),
-            functions: HashMap::new(),
-        }
+    #[pyo3(signature = (cache_size=128, validation="off", reload=false))]
+    pub fn new(cache_size: usize, validation: &str, reload: bool) -> PyResult<Self> {
+        Ok(XCatalog {
+            registry: Arc::new(Registry::default()),
+            documents: Mutex::new(DocumentCache::new(cache_size)),
+            validation: Validation::parse(validation)?,
+            reload,
+        })
+    }
+
+    /// How the props of the components are validated: "strict", "warn" or "off".
+    #[getter(validation)]
+    fn get_validation(&self) -> &'static str {
+        self.validation.as_str()
+    }
+
+    #[setter(validation)]
+    fn set_validation(&mut self, validation: &str) -> PyResult<()> {
+        self.validation = Validation::parse(validation)?;
+        Ok(())
+    }
+
+    /// Whether the components loaded from template files are parsed again
+    /// when their file has been modified.
+    #[getter(reload)]
+    fn get_reload(&self) -> bool {
+        self.reload
+    }
+
+    #[setter(reload)]
+    fn set_reload(&mut self, reload: bool) {
+        self.reload = reload;
     }
 
     pub fn add_component<'py>(
@@ -115,19 +297,15 @@ impl XCatalog {
         defaults: Py<PyDict>,
         namespaces: Py<PyDict>,
     ) -> PyResult<()> {
-        let node = parse_markup(template).map_err(|e| {
-            pyo3::exceptions::PyValueError::new_err(format!(
-                "Cannot parse component <{}/>:\n    {}",
-                name, e
-            ))
-        })?;
-        let py_node = Py::new(py, node)?;
-        let template = XTemplate::new(py_node, params, defaults, namespaces);
-        info!("Registering node {}", name);
-        debug!("{:?}", template);
-        let py_template = Py::new(py, template)?;
-        self.components.insert(name.to_owned(), py_template);
-        Ok(())
+        let node = parse_component(name, template, &Source::new(Some(name), template), 0)?;
+        let template = XTemplate::new(Py::new(py, node)?, params, defaults, namespaces);
+        self.register(py, name, template)
+    }
+
+    /// Register the components of the template files of the directory `path`,
+    /// the subdirectories are registered as namespaces.
+    pub fn load_directory<'py>(&mut self, py: Python<'py>, path: PathBuf) -> PyResult<()> {
+        load_directory(py, self, &path)
     }
 
     fn add_function<'py>(
@@ -140,7 +318,9 @@ impl XCatalog {
         debug!("{:?}", function);
         let func = PyCallable::new(function);
         let py_func = Py::new(py, func)?;
-        self.functions.insert(name, py_func);
+        Arc::make_mut(&mut self.registry)
+            .functions
+            .insert(name, py_func);
         Ok(())
     }
 
@@ -148,12 +328,19 @@ impl XCatalog {
         &'py self,
         py: Python<'py>,
         name: &'py str,
-    ) -> Option<&'py Bound<'py, XTemplate>> {
-        self.components.get(name).map(|node| node.bind(py))
+    ) -> PyResult<Option<&'py Bound<'py, XTemplate>>> {
+        let Some(template) = self.registry.components.get(name) else {
+            return Ok(None);
+        };
+        let template = template.bind(py);
+        if self.reload {
+            reload_template(py, name, template)?;
+        }
+        Ok(Some(template))
     }
 
     pub fn functions(&self) -> &HashMap<String, Py<PyCallable>> {
-        &self.functions
+        &self.registry.functions
     }
     pub fn call<'py>(
         &self,
@@ -162,12 +349,13 @@ impl XCatalog {
         args: &Bound<'py, PyTuple>,
         kwargs: &Bound<'py, PyDict>,
     ) -> PyResult<Bound<'py, PyAny>> {
-        let func = self
-            .functions
-            .get(name)
-            .ok_or_else(|| PyErr::new::<pyo3::exceptions::PyKeyError, _>("Function not found"))?;
-        let res = func.bind(py).call_method("call", (args, kwargs), None);
-        res
+        let func =
+            self.registry.functions.get(name).ok_or_else(|| {
+                PyErr::new::<pyo3::exceptions::PyKeyError, _>("Function not found")
+            })?;
+        func.bind(py)
+            .call_method("call", (args, kwargs), None)
+            .map_err(|err| render_error(py, err, || format!("{}()", name)))
     }
 
     pub fn render_node<'py>(
@@ -186,7 +374,7 @@ impl XCatalog {
         template: &str,
         kwds: Option<Bound<'py, PyDict>>,
     ) -> PyResult<String> {
-        let node = parse_markup(template)?;
+        let node = self.parse_document(template)?;
         let params = if let Some(params) = kwds {
             params
         } else {
@@ -197,4 +385,190 @@ impl XCatalog {
 
         self.render_node(py, &node, &mut context)
     }
+
+    /// Render the template by chunks, the rendering starts while
+    /// the chunks are consumed.
+    #[pyo3(signature = (template, **kwds))]
+    pub fn render_iter<'py>(
+        &self,
+        py: Python<'py>,
+        template: &str,
+        kwds: Option<Bound<'py, PyDict>>,
+    ) -> PyResult<XRenderIter> {
+        let node = self.parse_document(template)?;
+        let params = kwds.unwrap_or_else(|| PyDict::new(py));
+        // the catalog is shared, it can be updated while the rendering is
+        // suspended
+        XRenderIter::spawn(py, self.share(), node, params.unbind())
+    }
+
+    /// The settings, the components, the functions and the namespaces of the
+    /// catalog, the cache of documents is not part of the state.
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let state = PyDict::new(py);
+        let cache_size = self
+            .documents
+            .lock()
+            .unwrap()
+            .documents
+            .as_ref()
+            .map_or(0, |d| d.cap().get());
+        state.set_item("cache_size", cache_size)?;
+        state.set_item("validation", self.validation.as_str())?;
+        state.set_item("reload", self.reload)?;
+        let registry = &self.registry;
+        state.set_item("components", &registry.components)?;
+        let functions = PyDict::new(py);
+        for (name, function) in registry.functions.iter() {
+            functions.set_item(name, &function.borrow(py).callable)?;
+        }
+        state.set_item("functions", functions)?;
+        state.set_item("namespaces", &registry.namespaces)?;
+        Ok(state)
+    }
+
+    fn __setstate__<'py>(&mut self, py: Python<'py>, state: &Bound<'py, PyDict>) -> PyResult<()> {
+        let item = |name: &str| {
+            state
+                .get_item(name)?
+                .ok_or_else(|| PyValueError::new_err(format!("Missing state {:?}", name)))
+        };
+        let functions = item("functions")?
+            .extract::<HashMap<String, Py<PyAny>>>()?
+            .into_iter()
+            .map(|(name, function)| Ok((name, Py::new(py, PyCallable::new(function))?)))
+            .collect::<PyResult<_>>()?;
+        self.registry = Arc::new(Registry {
+            components: item("components")?.extract()?,
+            functions,
+            namespaces: item("namespaces")?.extract()?,
+        });
+        self.documents = Mutex::new(DocumentCache::new(item("cache_size")?.extract()?));
+        self.validation = Validation::parse(item("validation")?.extract()?)?;
+        self.reload = item("reload")?.extract()?;
+        Ok(())
+    }
+
+    /// Statistics of the cache of the documents parsed by the render method.
+    pub fn cache_info<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyDict>> {
+        let cache = self.documents.lock().unwrap();
+        let info = PyDict::new(py);
+        info.set_item("hits", cache.hits)?;
+        info.set_item("misses", cache.misses)?;
+        info.set_item(
+            "maxsize",
+            cache.documents.as_ref().map_or(0, |d| d.cap().get()),
+        )?;
+        info.set_item("currsize", cache.documents.as_ref().map_or(0, |d| d.len()))?;
+        Ok(info)
+    }
+
+    /// Clear the cache of the documents parsed by the render method,
+    /// and its statistics.
+    pub fn cache_clear(&self) {
+        let mut cache = self.documents.lock().unwrap();
+        if let Some(documents) = cache.documents.as_mut() {
+            documents.clear();
+        }
+        cache.hits = 0;
+        cache.misses = 0;
+    }
+}
+
+/// Parse the template of the component `name`, located at `offset`
+/// in the source.
+pub(crate) fn parse_component(
+    name: &str,
+    template: &str,
+    source: &Arc<Source>,
+    offset: usize,
+) -> PyResult<XNode> {
+    parse_template(template, source, offset).map_err(|e| {
+        pyo3::exceptions::PyValueError::new_err(format!(
+            "Cannot parse component <{}/>:\n    {}",
+            name, e
+        ))
+    })
+}
+
+impl XCatalog {
+    /// A catalog sharing the components and the functions of this one,
+    /// without cache of documents.
+    pub fn share(&self) -> XCatalog {
+        XCatalog {
+            registry: self.registry.clone(),
+            documents: Mutex::new(DocumentCache::new(0)),
+            validation: self.validation,
+            reload: self.reload,
+        }
+    }
+
+    pub fn validation(&self) -> Validation {
+        self.validation
+    }
+
+    /// An empty catalog, with the settings of this one, for a namespace.
+    pub(crate) fn new_namespace(&self) -> XCatalog {
+        XCatalog {
+            registry: Arc::new(Registry::default()),
+            documents: Mutex::new(DocumentCache::new(0)),
+            validation: self.validation,
+            reload: self.reload,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.registry.components.is_empty() && self.registry.namespaces.is_empty()
+    }
+
+    /// The catalog of a directory loaded by [`XCatalog::load_directory`].
+    pub fn namespace<'py>(&self, py: Python<'py>, name: &str) -> Option<Bound<'py, XCatalog>> {
+        self.registry
+            .namespaces
+            .get(name)
+            .map(|catalog| catalog.bind(py).clone())
+    }
+
+    pub(crate) fn add_namespace(&mut self, name: &str, catalog: Py<XCatalog>) {
+        info!("Registering namespace {}", name);
+        Arc::make_mut(&mut self.registry)
+            .namespaces
+            .insert(name.to_owned(), catalog);
+    }
+
+    pub(crate) fn register<'py>(
+        &mut self,
+        py: Python<'py>,
+        name: &str,
+        template: XTemplate,
+    ) -> PyResult<()> {
+        info!("Registering node {}", name);
+        debug!("{:?}", template);
+        let py_template = Py::new(py, template)?;
+        Arc::make_mut(&mut self.registry)
+            .components
+            .insert(name.to_owned(), py_template);
+        Ok(())
+    }
+
+    /// Render the node into the output.
+    pub fn write_node<'py>(
+        &self,
+        py: Python<'py>,
+        node: &XNode,
+        context: &mut RenderContext,
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()> {
+        node.write_html(py, self, context, out)
+    }
+
+    /// Parse the template, or get it from the cache of parsed documents.
+    fn parse_document(&self, template: &str) -> PyResult<Arc<XNode>> {
+        if let Some(node) = self.documents.lock().unwrap().get(template) {
+            return Ok(node);
+        }
+        let node = Arc::new(parse_markup(template)?);
+        self.documents.lock().unwrap().put(template, node.clone());
+        Ok(node)
+    }
 }
@@ -0,0 +1,458 @@
+use std::ffi::CString;
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+
+use pyo3::{
+    exceptions::{PyUserWarning, PyValueError},
+    prelude::*,
+    types::{PyDict, PyModule, PyTuple},
+};
+
+use crate::{
+    catalog::{
+        catalog::{parse_component, XTemplate},
+        XCatalog,
+    },
+    markup::tokens::XNode,
+    span::Source,
+};
+
+/// The extensions of the template files.
+const EXTENSIONS: [&str; 2] = ["xcomponent", "xhtml"];
+
+/// The header declaring the parameters of the component of a template file,
+/// `<!-- props: title: str, count: int = 0 -->`.
+const PROPS_HEADER: &str = "props:";
+
+/// Register the components of the template files of the directory `path`,
+/// named by their file name without extension.
+///
+/// The subdirectories containing template files are loaded in their own
+/// catalog, registered as a namespace named by the directory, so the file
+/// `ui/Button.xhtml` is rendered by `<ui.Button />`.
+pub fn load_directory<'py>(py: Python<'py>, catalog: &mut XCatalog, path: &Path) -> PyResult<()> {
+    let mut entries = fs::read_dir(path)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<_>, _>>()?;
+    entries.sort();
+
+    for entry in entries {
+        let (Some(file_name), Some(stem)) = (
+            entry.file_name().and_then(|name| name.to_str()),
+            entry.file_stem().and_then(|stem| stem.to_str()),
+        ) else {
+            continue;
+        };
+        if file_name.starts_with('.') {
+            continue;
+        }
+        if entry.is_dir() {
+            let mut namespace = catalog.new_namespace();
+            load_directory(py, &mut namespace, &entry)?;
+            // directories without templates, such as __pycache__, are ignored
+            if namespace.is_empty() {
+                continue;
+            }
+            check_name(file_name, &entry)?;
+            catalog.add_namespace(file_name, Py::new(py, namespace)?);
+        } else if entry
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| EXTENSIONS.contains(&ext))
+        {
+            check_name(stem, &entry)?;
+            load_file(py, catalog, stem, &entry)?;
+        }
+    }
+    Ok(())
+}
+
+/// The components and the namespaces are rendered as tags.
+fn check_name(name: &str, path: &Path) -> PyResult<()> {
+    let mut chars = name.chars();
+    let is_tag = chars.next().is_some_and(|c| c.is_ascii_alphanumeric())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !is_tag {
+        return Err(PyValueError::new_err(format!(
+            "Invalid component name {:?} for {}",
+            name,
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+fn load_file<'py>(
+    py: Python<'py>,
+    catalog: &mut XCatalog,
+    name: &str,
+    path: &Path,
+) -> PyResult<()> {
+    let mtime = modified(path);
+    let (node, params, defaults) = read_template(py, name, path)?;
+    let template = XTemplate::new(
+        Py::new(py, node)?,
+        params.unbind(),
+        defaults.unbind(),
+        PyDict::new(py).unbind(),
+    )
+    .with_origin(path.to_path_buf(), mtime);
+    catalog.register(py, name, template)
+}
+
+/// Parse the template file again if it has been modified since it has been
+/// parsed, when it cannot be parsed, a warning is emitted and the previous
+/// version of the component is kept.
+pub fn reload_template<'py>(
+    py: Python<'py>,
+    name: &str,
+    template: &Bound<'py, XTemplate>,
+) -> PyResult<()> {
+    let Some(path) = template.borrow().origin().cloned() else {
+        return Ok(());
+    };
+    let mtime = modified(&path);
+    if mtime == template.borrow().mtime() {
+        return Ok(());
+    }
+    // the component is being rendered, it is reloaded on its next use
+    let Ok(mut template) = template.try_borrow_mut() else {
+        return Ok(());
+    };
+    // the error of a modification is reported once
+    template.set_mtime(mtime);
+    match read_template(py, name, &path) {
+        Ok((node, params, defaults)) => {
+            info!("Reloading node {} from {}", name, path.display());
+            template.update(Py::new(py, node)?, params.unbind(), defaults.unbind());
+            Ok(())
+        }
+        Err(err) => {
+            let category = py.get_type::<PyUserWarning>();
+            let message = CString::new(format!(
+                "Cannot reload component <{}/> from {}, the previous version is kept:\n{}",
+                name,
+                path.display(),
+                err.value(py)
+            ))?;
+            PyErr::warn(py, &category, &message, 1)
+        }
+    }
+}
+
+/// The modification time of the file, `None` if it does not exist anymore.
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+/// Parse the template file of the component `name`, and its props.
+fn read_template<'py>(
+    py: Python<'py>,
+    name: &str,
+    path: &Path,
+) -> PyResult<(XNode, Bound<'py, PyDict>, Bound<'py, PyDict>)> {
+    let text = fs::read_to_string(path)?;
+    let (props, offset) = split_header(&text);
+    let params = PyDict::new(py);
+    let defaults = PyDict::new(py);
+    if let Some(props) = props {
+        parse_props(py, &text, props, &params, &defaults).map_err(|err| {
+            PyValueError::new_err(format!(
+                "Invalid props of component <{}/> in {}, {}",
+                name,
+                path.display(),
+                err.value(py)
+            ))
+        })?;
+    }
+    let source = Source::new(Some(&path.display().to_string()), &text);
+    let node = parse_component(name, &text[offset..], &source, offset)?;
+    Ok((node, params, defaults))
+}
+
+/// The declaration of the props of the header of the template, and the
+/// offset of the template after the header.
+fn split_header(text: &str) -> (Option<&str>, usize) {
+    let start = text.len() - text.trim_start().len();
+    let header = text[start..]
+        .strip_prefix("<!--")
+        .and_then(|comment| comment.split_once("-->"))
+        .and_then(|(comment, _)| comment.trim_start().strip_prefix(PROPS_HEADER));
+    match header {
+        Some(props) => {
+            let end = text[start..].find("-->").unwrap() + start + "-->".len();
+            (Some(props.trim()), end)
+        }
+        None => (None, 0),
+    }
+}
+
+/// Parse the props `name: annotation = default`, separated by commas.
+///
+/// The annotations are built from the basic types, and the default values
+/// must be python literals. The errors start with the line of the prop in
+/// the text of the template.
+fn parse_props<'py>(
+    py: Python<'py>,
+    text: &str,
+    props: &str,
+    params: &Bound<'py, PyDict>,
+    defaults: &Bound<'py, PyDict>,
+) -> PyResult<()> {
+    let typing = PyModule::import(py, "typing")?;
+    let ast = PyModule::import(py, "ast")?;
+    let builtins = PyModule::import(py, "builtins")?;
+
+    let types = PyDict::new(py);
+    for name in [
+        "str", "int", "float", "bool", "list", "dict", "tuple", "set",
+    ] {
+        types.set_item(name, builtins.getattr(name)?)?;
+    }
+    for name in ["Any", "Optional", "Union", "Literal"] {
+        types.set_item(name, typing.getattr(name)?)?;
+    }
+    types.set_item("XNode", py.get_type::<XNode>())?;
+
+    for prop in split_top_level(props, ',') {
+        let prop = prop.trim();
+        if prop.is_empty() {
+            continue;
+        }
+        let error = |message: String| {
+            PyValueError::new_err(format!("line {}, {}", line_of(text, prop), message))
+        };
+        let (declaration, default) = match find_top_level(prop, '=') {
+            Some(idx) => (&prop[..idx], Some(prop[idx + 1..].trim())),
+            None => (prop, None),
+        };
+        let (name, annotation) = match declaration.split_once(':') {
+            Some((name, annotation)) => (name.trim(), Some(annotation.trim())),
+            None => (declaration.trim(), None),
+        };
+        if !is_identifier(name) {
+            return Err(error(format!("invalid name {:?}", name)));
+        }
+        if params.contains(name)? {
+            return Err(error(format!("duplicate name {:?}", name)));
+        }
+        let annotation = match annotation {
+            Some(annotation) => {
+                let mut parser = AnnotationParser {
+                    py,
+                    types: &types,
+                    ast: &ast,
+                    text: annotation,
+                    pos: 0,
+                };
+                parser.parse().map_err(|err| {
+                    error(format!(
+                        "invalid annotation {:?} of {}, {}",
+                        annotation, name, err
+                    ))
+                })?
+            }
+            None => typing.getattr("Any")?,
+        };
+        params.set_item(name, annotation)?;
+        if let Some(default) = default {
+            let value = ast.call_method1("literal_eval", (default,)).map_err(|_| {
+                error(format!(
+                    "invalid default value {:?} of {}, a literal is expected",
+                    default, name
+                ))
+            })?;
+            defaults.set_item(name, value)?;
+        }
+    }
+    Ok(())
+}
+
+/// The line number of `part`, a slice of `text`.
+fn line_of(text: &str, part: &str) -> usize {
+    let offset = part.as_ptr() as usize - text.as_ptr() as usize;
+    text[..offset].matches('\n').count() + 1
+}
+
+/// The annotations are not evaluated, only the known types, their
+/// subscriptions `list[str]`, the unions `str | None` and the literals
+/// of `Literal["a", 1]` are accepted.
+struct AnnotationParser<'a, 'py> {
+    py: Python<'py>,
+    types: &'a Bound<'py, PyDict>,
+    ast: &'a Bound<'py, PyModule>,
+    text: &'a str,
+    pos: usize,
+}
+
+impl<'py> AnnotationParser<'_, 'py> {
+    fn parse(&mut self) -> Result<Bound<'py, PyAny>, String> {
+        let value = self.parse_union()?;
+        self.skip_spaces();
+        match self.peek() {
+            None => Ok(value),
+            Some(c) => Err(format!("unexpected {:?}", c)),
+        }
+    }
+
+    fn parse_union(&mut self) -> Result<Bound<'py, PyAny>, String> {
+        let mut value = self.parse_type()?;
+        while self.eat('|') {
+            let right = self.parse_type()?;
+            value = value.bitor(right).map_err(|err| self.py_error(err))?;
+        }
+        Ok(value)
+    }
+
+    fn parse_type(&mut self) -> Result<Bound<'py, PyAny>, String> {
+        self.skip_spaces();
+        let start = self.pos;
+        match self.peek() {
+            Some('"' | '\'') => {
+                let quote = self.bump();
+                let mut escaped = false;
+                loop {
+                    match self.bump() {
+                        None => return Err("unterminated string".to_string()),
+                        Some('\\') if !escaped => escaped = true,
+                        Some(c) if c == quote.unwrap() && !escaped => break,
+                        Some(_) => escaped = false,
+                    }
+                }
+                self.literal(start)
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' => {
+                self.bump();
+                while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                    self.bump();
+                }
+                self.literal(start)
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+                    self.bump();
+                }
+                let name = &self.text[start..self.pos];
+                let value = match name {
+                    "None" | "True" | "False" => self.literal(start)?,
+                    _ => self
+                        .types
+                        .get_item(name)
+                        .map_err(|err| self.py_error(err))?
+                        .ok_or_else(|| format!("unknown type {:?}", name))?,
+                };
+                if self.eat('[') {
+                    let args = self.parse_args()?;
+                    return value.get_item(args).map_err(|err| self.py_error(err));
+                }
+                Ok(value)
+            }
+            Some(c) => Err(format!("unexpected {:?}", c)),
+            None => Err("a type is expected".to_string()),
+        }
+    }
+
+    /// The arguments of a subscription, after the opening bracket.
+    fn parse_args(&mut self) -> Result<Bound<'py, PyAny>, String> {
+        let mut args = Vec::new();
+        loop {
+            args.push(self.parse_union()?);
+            if self.eat(']') {
+                break;
+            }
+            if !self.eat(',') {
+                return Err("\",\" or \"]\" is expected".to_string());
+            }
+            if self.eat(']') {
+                break;
+            }
+        }
+        if args.len() == 1 {
+            return Ok(args.pop().unwrap());
+        }
+        PyTuple::new(self.py, args)
+            .map(|args| args.into_any())
+            .map_err(|err| self.py_error(err))
+    }
+
+    fn literal(&self, start: usize) -> Result<Bound<'py, PyAny>, String> {
+        self.ast
+            .call_method1("literal_eval", (&self.text[start..self.pos],))
+            .map_err(|err| self.py_error(err))
+    }
+
+    fn py_error(&self, err: PyErr) -> String {
+        err.value(self.py).to_string()
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.text[self.pos..].chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.bump();
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        self.skip_spaces();
+        if self.peek() == Some(expected) {
+            self.bump();
+            return true;
+        }
+        false
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// The position of the first `sep` outside of brackets and quoted strings.
+fn find_top_level(text: &str, sep: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote = None;
+    let mut escaped = false;
+    for (idx, c) in text.char_indices() {
+        match quote {
+            Some(q) => {
+                if escaped {
+                    escaped = false;
+                } else if c == '\\' {
+                    escaped = true;
+                } else if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '(' | '[' | '{' => depth += 1,
+                ')' | ']' | '}' => depth -= 1,
+                _ if c == sep && depth == 0 => return Some(idx),
+                _ => {}
+            },
+        }
+    }
+    None
+}
+
+fn split_top_level(text: &str, sep: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(idx) = find_top_level(rest, sep) {
+        parts.push(&rest[..idx]);
+        rest = &rest[idx + sep.len_utf8()..];
+    }
+    parts.push(rest);
+    parts
+}
@@ -1,3 +1,7 @@
 mod catalog;
+mod loader;
+mod stream;
+pub(crate) mod validation;
 
-pub use catalog::XCatalog;
+pub use catalog::{XCatalog, XTemplate};
+pub use stream::{stop_renderings, XRenderIter};
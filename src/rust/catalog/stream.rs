@@ -0,0 +1,185 @@
+use std::fmt;
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, Weak};
+use std::thread::{self, JoinHandle};
+
+use pyo3::{
+    prelude::*,
+    types::{PyCFunction, PyDict, PyTuple},
+};
+
+use crate::{catalog::XCatalog, context::RenderContext, markup::tokens::XNode};
+
+/// Size of the chunks of html sent to the iterator.
+const CHUNK_SIZE: usize = 8192;
+
+/// Chunks rendered and not consumed by the iterator yet.
+const PENDING_CHUNKS: usize = 1;
+
+/// Output of the streamed rendering, the html is buffered, and sent by chunks
+/// to the iterator.
+struct ChunkWriter<'py> {
+    py: Python<'py>,
+    sender: SyncSender<PyResult<String>>,
+    buffer: String,
+}
+
+impl<'py> ChunkWriter<'py> {
+    fn new(py: Python<'py>, sender: SyncSender<PyResult<String>>) -> Self {
+        ChunkWriter {
+            py,
+            sender,
+            buffer: String::with_capacity(CHUNK_SIZE),
+        }
+    }
+
+    fn send(&mut self, chunk: PyResult<String>) -> fmt::Result {
+        // the GIL is released while the iterator is consuming the chunks
+        let sender = &self.sender;
+        self.py
+            .allow_threads(move || sender.send(chunk))
+            .map_err(|_| fmt::Error)
+    }
+
+    fn flush(&mut self) -> fmt::Result {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let chunk = std::mem::replace(&mut self.buffer, String::with_capacity(CHUNK_SIZE));
+        self.send(Ok(chunk))
+    }
+}
+
+impl fmt::Write for ChunkWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.buffer.push_str(s);
+        if self.buffer.len() >= CHUNK_SIZE {
+            self.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// The renderings in progress, they are stopped when the interpreter exits.
+static RENDERINGS: Mutex<Vec<Weak<Rendering>>> = Mutex::new(Vec::new());
+
+/// A rendering running in its own thread.
+struct Rendering {
+    receiver: Mutex<Option<Receiver<PyResult<String>>>>,
+    thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Rendering {
+    /// Close the channel, the rendering stops at the next written chunk,
+    /// and wait for the thread to finish.
+    fn stop(&self, py: Python<'_>) {
+        // the receiver is locked while a chunk is awaited by another thread,
+        // then the rendering can't be stopped here.
+        match self.receiver.try_lock() {
+            Ok(mut receiver) => receiver.take(),
+            Err(_) => return,
+        };
+        if let Some(thread) = self.thread.lock().unwrap().take() {
+            if py.allow_threads(move || thread.join()).is_err() {
+                error!("The rendering thread panicked");
+            }
+        }
+    }
+}
+
+/// Stop the renderings in progress, they must not outlive the interpreter.
+#[pyfunction]
+pub fn stop_renderings(py: Python<'_>) {
+    let renderings = std::mem::take(&mut *RENDERINGS.lock().unwrap());
+    for rendering in renderings.iter().filter_map(Weak::upgrade) {
+        rendering.stop(py);
+    }
+}
+
+/// Iterator over the chunks of html of a rendering.
+///
+/// The rendering runs in its own thread, and is suspended until the
+/// chunks are consumed, it is stopped if the iterator is dropped.
+#[pyclass]
+pub struct XRenderIter {
+    rendering: Arc<Rendering>,
+}
+
+impl XRenderIter {
+    /// Start the rendering, it runs in a copy of the context of the caller,
+    /// so the context variables are visible to the functions of the catalog.
+    pub fn spawn(
+        py: Python<'_>,
+        catalog: XCatalog,
+        node: Arc<XNode>,
+        params: Py<PyDict>,
+    ) -> PyResult<Self> {
+        let pycontext = py
+            .import("contextvars")?
+            .call_method0("copy_context")?
+            .unbind();
+        let (sender, receiver) = sync_channel(PENDING_CHUNKS);
+        // the render function is called once, by the rendering thread
+        let job = Mutex::new(Some((catalog, node, params, sender)));
+        let render =
+            move |args: &Bound<'_, PyTuple>, _kwargs: Option<&Bound<'_, PyDict>>| -> PyResult<()> {
+                let py = args.py();
+                let Some((catalog, node, params, sender)) = job.lock().unwrap().take() else {
+                    return Ok(());
+                };
+                let mut writer = ChunkWriter::new(py, sender);
+                let mut context = RenderContext::new();
+                let res = context
+                    .push(py, params.into_bound(py))
+                    .and_then(|_| catalog.write_node(py, &node, &mut context, &mut writer));
+                match res {
+                    Ok(()) => {
+                        let _ = writer.flush();
+                    }
+                    Err(err) => {
+                        let _ = writer.send(Err(err));
+                    }
+                }
+                Ok(())
+            };
+        let render = PyCFunction::new_closure(py, None, None, render)?.unbind();
+        let thread = thread::spawn(move || {
+            Python::with_gil(|py| {
+                if let Err(err) = pycontext.call_method1(py, "run", (render,)) {
+                    err.write_unraisable(py, None);
+                }
+            })
+        });
+        let rendering = Arc::new(Rendering {
+            receiver: Mutex::new(Some(receiver)),
+            thread: Mutex::new(Some(thread)),
+        });
+        let mut renderings = RENDERINGS.lock().unwrap();
+        renderings.retain(|r| r.strong_count() > 0);
+        renderings.push(Arc::downgrade(&rendering));
+        Ok(XRenderIter { rendering })
+    }
+}
+
+#[pymethods]
+impl XRenderIter {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__(&self, py: Python<'_>) -> PyResult<Option<String>> {
+        let receiver = &self.rendering.receiver;
+        let chunk = py.allow_threads(move || receiver.lock().unwrap().as_ref().map(|r| r.recv()));
+        match chunk {
+            Some(Ok(chunk)) => chunk.map(Some),
+            // the rendering is done
+            _ => Ok(None),
+        }
+    }
+}
+
+impl Drop for XRenderIter {
+    fn drop(&mut self) {
+        Python::with_gil(|py| self.rendering.stop(py));
+    }
+}
@@ -0,0 +1,180 @@
+use std::ffi::CString;
+
+use pyo3::{
+    exceptions::{PyTypeError, PyUserWarning, PyValueError},
+    prelude::*,
+    types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyNone, PyString, PyType},
+};
+
+use crate::{
+    context::markup_type, error::render_error, markup::slot::XSlot, markup::tokens::XNode,
+};
+
+/// How the props given to the components are validated against
+/// the parameters of the components.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Validation {
+    #[default]
+    Off,
+    Warn,
+    Strict,
+}
+
+impl Validation {
+    pub fn parse(mode: &str) -> PyResult<Self> {
+        match mode {
+            "off" => Ok(Validation::Off),
+            "warn" => Ok(Validation::Warn),
+            "strict" => Ok(Validation::Strict),
+            _ => Err(PyValueError::new_err(format!(
+                "Invalid validation {:?}, expected \"strict\", \"warn\" or \"off\"",
+                mode
+            ))),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Validation::Off => "off",
+            Validation::Warn => "warn",
+            Validation::Strict => "strict",
+        }
+    }
+}
+
+/// A component used without children, such as `<Card />`, is given empty
+/// children, unless its `children` parameter has a default value.
+pub fn default_children<'py>(
+    py: Python<'py>,
+    params: &Bound<'py, PyDict>,
+    defaults: &Bound<'py, PyDict>,
+    props: &Bound<'py, PyDict>,
+) -> PyResult<()> {
+    if params.contains("children")?
+        && !defaults.contains("children")?
+        && !props.contains("children")?
+    {
+        props.set_item("children", markup_type(py)?.call1(("",))?)?;
+    }
+    Ok(())
+}
+
+/// Validate the props given to the component `<tag>`.
+///
+/// The props must be parameters of the component, the parameters without
+/// default value are required, and the values must match the basic type
+/// annotations. In strict mode, the problems raise a `TypeError`,
+/// in warn mode, they are reported as warnings.
+pub fn validate_props<'py>(
+    py: Python<'py>,
+    validation: Validation,
+    tag: &str,
+    params: &Bound<'py, PyDict>,
+    defaults: &Bound<'py, PyDict>,
+    props: &Bound<'py, PyDict>,
+) -> PyResult<()> {
+    if validation == Validation::Off {
+        return Ok(());
+    }
+    let mut problems = Vec::new();
+    for (name, value) in props.iter() {
+        match params.get_item(&name)? {
+            None => problems.push(format!("unknown attribute {}", name.repr()?)),
+            Some(annotation) => {
+                if !is_instance(py, &value, &annotation)? {
+                    problems.push(format!(
+                        "attribute {} expects {}, got {}",
+                        name.repr()?,
+                        type_name(&annotation)?,
+                        value.get_type().name()?
+                    ));
+                }
+            }
+        }
+    }
+    for name in params.keys() {
+        // the globals are not given as attributes
+        if name.extract::<&str>()? == "globals" {
+            continue;
+        }
+        if !props.contains(&name)? && !defaults.contains(&name)? {
+            problems.push(format!("missing attribute {}", name.repr()?));
+        }
+    }
+
+    match validation {
+        Validation::Strict if !problems.is_empty() => {
+            let err = PyTypeError::new_err(format!("<{}> {}", tag, problems.join(", ")));
+            Err(render_error(py, err, || format!("<{}>", tag)))
+        }
+        _ => {
+            let category = py.get_type::<PyUserWarning>();
+            for problem in problems {
+                let message = CString::new(format!("<{}> {}", tag, problem))?;
+                PyErr::warn(py, &category, &message, 1)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Check the basic type annotations, `str`, `int`, `float`, `bool`, `list`,
+/// `dict`, `XNode` and their unions, other annotations accept any value.
+fn is_instance<'py>(
+    py: Python<'py>,
+    value: &Bound<'py, PyAny>,
+    annotation: &Bound<'py, PyAny>,
+) -> PyResult<bool> {
+    if annotation.is_none() || annotation.is(&py.get_type::<PyNone>()) {
+        return Ok(value.is_none());
+    }
+    let typing = py.import("typing")?;
+    let origin = typing.call_method1("get_origin", (annotation,))?;
+    if origin.is(&typing.getattr("Union")?)
+        || origin.is(&py.import("types")?.getattr("UnionType")?)
+    {
+        for arg in typing.call_method1("get_args", (annotation,))?.try_iter()? {
+            if is_instance(py, value, &arg?)? {
+                return Ok(true);
+            }
+        }
+        return Ok(false);
+    }
+    // list[str] is checked as a list
+    let annotation = if origin.is_none() {
+        annotation
+    } else {
+        &origin
+    };
+    let Ok(annotation) = annotation.downcast::<PyType>() else {
+        return Ok(true);
+    };
+    let is_instance = if annotation.is(&py.get_type::<PyString>()) {
+        value.is_instance_of::<PyString>()
+    } else if annotation.is(&py.get_type::<PyBool>()) {
+        value.is_instance_of::<PyBool>()
+    } else if annotation.is(&py.get_type::<PyInt>()) {
+        value.is_instance_of::<PyInt>()
+    } else if annotation.is(&py.get_type::<PyFloat>()) {
+        value.is_instance_of::<PyFloat>() || value.is_instance_of::<PyInt>()
+    } else if annotation.is(&py.get_type::<PyList>()) {
+        value.is_instance_of::<PyList>()
+    } else if annotation.is(&py.get_type::<PyDict>()) {
+        value.is_instance_of::<PyDict>()
+    } else if annotation.is(&py.get_type::<XNode>()) {
+        // the children are rendered and the markup of attributes are slots
+        value.is_instance_of::<XNode>()
+            || value.is_instance_of::<XSlot>()
+            || value.is_instance_of::<PyString>()
+    } else {
+        true
+    };
+    Ok(is_instance)
+}
+
+fn type_name(annotation: &Bound<'_, PyAny>) -> PyResult<String> {
+    match annotation.downcast::<PyType>() {
+        Ok(t) => Ok(t.name()?.to_string()),
+        Err(_) => Ok(annotation.str()?.to_string()),
+    }
+}
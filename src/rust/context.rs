@@ -1,13 +1,57 @@
 use std::collections::HashMap;
 use std::fmt;
+use std::sync::Arc;
 
+use indexmap::IndexMap;
 use pyo3::exceptions::PyTypeError;
+use pyo3::ffi::c_str;
 use pyo3::marker::Python;
-use pyo3::types::{PyBool, PyDict, PyInt, PyList, PyNone, PyString};
+use pyo3::sync::GILOnceCell;
+use pyo3::types::{PyBool, PyDict, PyFloat, PyInt, PyList, PyNone, PyString, PyType};
 use pyo3::{prelude::*, BoundObject, IntoPyObjectExt};
 
 use crate::catalog::XCatalog;
-use crate::markup::tokens::{ToHtml, XNode};
+use crate::markup::escape::Escape;
+use crate::markup::slot::XSlot;
+use crate::markup::tokens::{write_html, ToHtml, XNode};
+use crate::serialize::{Decode, Decoder, Encode, Encoder};
+
+/// Format the float like the python repr, the shortest representation that
+/// round trips, using the scientific notation for large and small exponents.
+pub fn format_float(value: f64) -> String {
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    let sci = format!("{:e}", value);
+    let (mantissa, exp) = sci.split_once('e').unwrap();
+    let exp: i32 = exp.parse().unwrap();
+    if (-4..16).contains(&exp) {
+        let res = format!("{}", value);
+        if res.contains('.') {
+            res
+        } else {
+            format!("{}.0", res)
+        }
+    } else {
+        let sign = if exp < 0 { '-' } else { '+' };
+        format!("{}e{}{:02}", mantissa, sign, exp.abs())
+    }
+}
+
+/// Format the uuid hex representation with hyphens.
+pub fn format_uuid(uuid: &str) -> String {
+    format!(
+        "{}-{}-{}-{}-{}",
+        &uuid[0..8],
+        &uuid[8..12],
+        &uuid[12..16],
+        &uuid[16..20],
+        &uuid[20..32]
+    )
+}
 
 pub trait Truthy {
     fn is_truthy(&self) -> bool;
@@ -63,6 +107,7 @@ impl TryFrom<Literal> for LiteralKey {
         match lit {
             Literal::Int(i) => Ok(LiteralKey::Int(i)),
             Literal::Str(s) => Ok(LiteralKey::Str(s)),
+            Literal::Markup(s) => Ok(LiteralKey::Str(s)),
             Literal::Uuid(u) => Ok(LiteralKey::Uuid(u)),
             _ => Err(PyTypeError::new_err(format!(
                 "Unsupported literal type for key {:?}",
@@ -72,6 +117,55 @@ impl TryFrom<Literal> for LiteralKey {
     }
 }
 
+impl From<LiteralKey> for Literal {
+    fn from(key: LiteralKey) -> Self {
+        match key {
+            LiteralKey::Int(i) => Literal::Int(i),
+            LiteralKey::Str(s) => Literal::Str(s),
+            LiteralKey::Uuid(u) => Literal::Uuid(u),
+        }
+    }
+}
+
+static MARKUP_TYPE: GILOnceCell<Py<PyType>> = GILOnceCell::new();
+static NUMBER_TYPE: GILOnceCell<Py<PyType>> = GILOnceCell::new();
+
+/// The abstract base class of python numbers, such as `Decimal` or `Fraction`.
+fn number_type(py: Python<'_>) -> PyResult<&Bound<'_, PyType>> {
+    NUMBER_TYPE.import(py, "numbers", "Number")
+}
+
+/// The python type of the safe markup, a str subclass implementing the
+/// `__html__` protocol, in order to be compatible with markupsafe.
+pub fn markup_type(py: Python<'_>) -> PyResult<&Bound<'_, PyType>> {
+    MARKUP_TYPE
+        .get_or_try_init(py, || {
+            let module = PyModule::from_code(
+                py,
+                c_str!(
+                    r#"
+class Markup(str):
+    """A string that is safe to render as HTML, it will not be escaped."""
+
+    __slots__ = ()
+
+    def __html__(self):
+        return self
+
+    def __repr__(self):
+        return f"Markup({str.__repr__(self)})"
+"#
+                ),
+                c_str!("markup.py"),
+                c_str!("xcomponent.xcore.markup"),
+            )?;
+            let markup = module.getattr("Markup")?.downcast_into::<PyType>()?;
+            markup.setattr("__module__", "xcomponent.xcore")?;
+            Ok(markup.unbind())
+        })
+        .map(|t| t.bind(py))
+}
+
 #[derive(Debug, IntoPyObject)]
 pub struct PyObj {
     obj: Py<PyAny>,
@@ -99,23 +193,39 @@ pub enum Literal {
     None(()),
     Bool(bool),
     Int(isize),
+    Float(f64),
+    Number(PyObj), // Python number without native type, Decimal, int wider than 64 bits...
     Str(String),
-    Uuid(String), // Uuid type does not support IntoPyObject
+    Markup(String), // Safe markup, rendered without being escaped
+    Uuid(String),   // Uuid type does not support IntoPyObject
     XNode(XNode),
     List(Vec<Literal>),
-    Dict(HashMap<LiteralKey, Literal>),
+    Dict(IndexMap<LiteralKey, Literal>),
     Callable(String), // the name of the callable
     Object(PyObj),
 }
 
 impl Literal {
     pub fn downcast<'py>(py: Python<'py>, value: Bound<'py, PyAny>) -> Result<Self, PyErr> {
-        if let Ok(v) = value.downcast::<PyString>() {
+        if let Ok(v) = value.downcast_exact::<PyString>() {
+            return Ok(Literal::Str(v.to_string()));
+        } else if value.downcast::<XSlot>().is_ok() {
+            // the slots are rendered when they are used
+            return Ok(Literal::Object(PyObj::new(value.unbind())));
+        } else if value.hasattr("__html__")? {
+            let markup = value.call_method0("__html__")?;
+            return Ok(Literal::Markup(markup.extract::<String>()?));
+        } else if let Ok(v) = value.downcast::<PyString>() {
             return Ok(Literal::Str(v.to_string()));
         } else if let Ok(v) = value.downcast::<PyBool>() {
             return Ok(Literal::Bool(v.extract::<bool>()?));
         } else if let Ok(v) = value.downcast::<PyInt>() {
-            return Ok(Literal::Int(v.extract::<isize>()?));
+            return Ok(match v.extract::<isize>() {
+                Ok(i) => Literal::Int(i),
+                Err(_) => Literal::Number(PyObj::new(value.unbind())),
+            });
+        } else if let Ok(v) = value.downcast::<PyFloat>() {
+            return Ok(Literal::Float(v.value()));
         } else if let Ok(_) = value.downcast::<PyNone>() {
             return Ok(Literal::None(()));
         } else if let Ok(v) = value.extract::<XNode>() {
@@ -127,7 +237,7 @@ impl Literal {
             }
             Ok(Literal::List(items))
         } else if let Ok(dict) = value.downcast::<PyDict>() {
-            let mut map = HashMap::new();
+            let mut map = IndexMap::with_capacity(dict.len());
             for (k, v) in dict {
                 let key: LiteralKey = LiteralKey::downcast(py, k)?;
                 let val: Literal = Literal::downcast(py, v)?;
@@ -137,6 +247,8 @@ impl Literal {
         } else if value.downcast::<PyAny>()?.get_type().name()? == "UUID" {
             let uuid_str = value.getattr("hex")?;
             Ok(Literal::Uuid(uuid_str.to_string()))
+        } else if value.is_instance(number_type(py)?)? {
+            Ok(Literal::Number(PyObj::new(value.unbind())))
         } else {
             let o: Py<PyAny> = value.extract()?;
             Ok(Literal::Object(PyObj::new(o)))
@@ -158,7 +270,10 @@ impl Literal {
                 uuid_class.call1(args).unwrap()
             }
             Literal::Int(v) => v.clone().into_pyobject(py).unwrap().into_any(),
+            Literal::Float(v) => v.into_pyobject(py).unwrap().into_any(),
+            Literal::Number(v) => v.obj().bind(py).clone(),
             Literal::Str(v) => v.clone().into_pyobject(py).unwrap().into_any(),
+            Literal::Markup(v) => markup_type(py).unwrap().call1((v.as_str(),)).unwrap(),
             Literal::XNode(v) => v.clone().into_pyobject(py).unwrap().into_any(),
             Literal::List(v) => {
                 let vals = v
@@ -175,11 +290,7 @@ impl Literal {
             Literal::Dict(map) => {
                 let dict = PyDict::new(py);
                 for (k, v) in map {
-                    dict.set_item(
-                        k.clone().into_pyobject(py).unwrap().into_any(),
-                        v.into_py(py),
-                    )
-                    .unwrap();
+                    dict.set_item(k.into_py(py), v.into_py(py)).unwrap();
                 }
                 dict.into_any()
             }
@@ -194,11 +305,13 @@ impl Truthy for Literal {
             Literal::None(_s) => false,
             Literal::Bool(bool) => bool.clone(),
             Literal::Int(i) => *i != 0,
+            Literal::Float(f) => *f != 0.0,
             Literal::Str(s) => !s.is_empty(),
+            Literal::Markup(s) => !s.is_empty(),
             Literal::Uuid(_) => true,
             Literal::XNode(_) => true,
             Literal::Callable(_) => true,
-            Literal::Object(o) => Python::with_gil(|py| {
+            Literal::Number(o) | Literal::Object(o) => Python::with_gil(|py| {
                 let builtins = PyModule::import(py, "builtins").unwrap();
                 let boolcls = builtins.getattr("bool").unwrap();
                 let v = o.obj().into_pyobject(py).unwrap();
@@ -212,51 +325,52 @@ impl Truthy for Literal {
 }
 
 impl ToHtml for Literal {
-    fn to_html<'py>(
+    fn write_html<'py>(
         &self,
         py: Python<'py>,
         catalog: &XCatalog,
         context: &mut RenderContext,
-    ) -> PyResult<String> {
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()> {
         debug!("Rendering {:?}", self);
         match self {
-            Literal::None(_) => Ok("".to_string()),
-            Literal::Bool(b) => Ok(format!("{}", b)),
-            Literal::Int(i) => Ok(format!("{}", i)),
-            Literal::Str(s) => Ok(format!("{}", s)),
-            Literal::Callable(s) => Ok(format!("{}()", s)),
-            Literal::Uuid(uuid) => Ok(format!(
-                "{}-{}-{}-{}-{}",
-                &uuid[0..8],
-                &uuid[8..12],
-                &uuid[12..16],
-                &uuid[16..20],
-                &uuid[20..32]
-            )),
+            Literal::None(_) => Ok(()),
+            Literal::Bool(b) => write_html(out, format!("{}", b).as_str()),
+            Literal::Int(i) => write_html(out, format!("{}", i).as_str()),
+            Literal::Float(f) => write_html(out, format_float(*f).as_str()),
+            Literal::Number(n) => write_html(
+                out,
+                &Escape::Text.escape(&n.obj().bind(py).str()?.to_cow()?),
+            ),
+            Literal::Str(s) => write_html(out, Escape::Text.escape(s).as_ref()),
+            Literal::Markup(s) => write_html(out, s),
+            Literal::Callable(s) => write_html(out, format!("{}()", s).as_str()),
+            Literal::Uuid(uuid) => write_html(out, format_uuid(uuid).as_str()),
             Literal::List(l) => {
-                let mut out = String::new();
                 for item in l {
-                    out.push_str(item.to_html(py, catalog, context)?.as_str());
+                    item.write_html(py, catalog, context, out)?;
                 }
-                Ok(out)
+                Ok(())
             }
             Literal::Dict(d) => {
-                let mut out = String::new();
-                out.push_str("<dl>");
+                write_html(out, "<dl>")?;
                 for (k, item) in d {
-                    out.push_str("<dt>");
-                    out.push_str(format!("{}", k).as_str());
-                    out.push_str("</dt>");
-                    out.push_str("<dt>");
-                    out.push_str(item.to_html(py, catalog, context)?.as_str());
-                    out.push_str("</dt>");
+                    write_html(out, "<dt>")?;
+                    write_html(out, &Escape::Text.escape(&format!("{}", k)))?;
+                    write_html(out, "</dt>")?;
+                    write_html(out, "<dt>")?;
+                    item.write_html(py, catalog, context, out)?;
+                    write_html(out, "</dt>")?;
                 }
-                out.push_str("</dl>");
-                Ok(out)
+                write_html(out, "</dl>")
             }
-            Literal::Object(o) => Ok(format!(
-                "{}",
-                Python::with_gil(|py| {
+            Literal::Object(o) if o.obj().bind(py).downcast::<XSlot>().is_ok() => {
+                let slot = o.obj().bind(py).downcast::<XSlot>()?.clone();
+                slot.get().write_html(py, context.provided(), out)
+            }
+            Literal::Object(o) => write_html(
+                out,
+                &Escape::Text.escape(&Python::with_gil(|py| {
                     match o
                         .obj()
                         .into_pyobject(py)
@@ -266,18 +380,24 @@ impl ToHtml for Literal {
                         Ok(b) => b.extract::<String>().unwrap(),
                         Err(_) => "<PyObject>".to_string(),
                     }
-                })
-            )),
-            Literal::XNode(n) => catalog.render_node(py, &n, context),
+                })),
+            ),
+            Literal::XNode(n) => catalog.write_node(py, n, context, out),
         }
     }
 }
 
+/// The scopes of the variables, the frames are shared with the copies of
+/// the context, such as the ones captured by the arrow functions.
+///
+/// The provided values are visible to all the descendant components,
+/// they are kept by the shadow contexts.
 #[pyclass]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct RenderContext {
-    stack: Vec<HashMap<LiteralKey, Literal>>,
-    ns_stack: Vec<HashMap<LiteralKey, Literal>>,
+    stack: Vec<Arc<HashMap<LiteralKey, Literal>>>,
+    ns_stack: Vec<Arc<HashMap<LiteralKey, Literal>>>,
+    provided: Arc<HashMap<String, Literal>>,
 }
 
 #[pymethods]
@@ -287,6 +407,7 @@ impl RenderContext {
         Self {
             stack: vec![],
             ns_stack: vec![],
+            provided: Arc::new(HashMap::new()),
         }
     }
 
@@ -294,6 +415,7 @@ impl RenderContext {
         let mut shadow_context = Self {
             stack: self.ns_stack.clone(),
             ns_stack: self.ns_stack.clone(),
+            provided: self.provided.clone(),
         };
         let gblk = LiteralKey::Str("globals".to_string());
         if let Some(glb) = self.get(&gblk) {
@@ -305,7 +427,7 @@ impl RenderContext {
     pub fn push_ns<'py>(&mut self, py: Python<'py>, params: Bound<'py, PyDict>) -> PyResult<()> {
         let anyparams: Bound<'py, PyAny> = params.extract()?;
         if let Literal::Dict(d) = Literal::downcast(py, anyparams)? {
-            self.ns_stack.push(d);
+            self.ns_stack.push(Arc::new(d.into_iter().collect()));
             self.push(py, params)?;
             debug!("ns stack updated {:?}", self);
             Ok(())
@@ -318,7 +440,7 @@ impl RenderContext {
     pub fn push<'py>(&mut self, py: Python<'py>, params: Bound<'py, PyDict>) -> PyResult<()> {
         let anyparams: Bound<'py, PyAny> = params.extract()?;
         if let Literal::Dict(d) = Literal::downcast(py, anyparams)? {
-            self.stack.push(d);
+            self.stack.push(Arc::new(d.into_iter().collect()));
             debug!("stack updated {:?}", self.stack);
             Ok(())
         } else {
@@ -343,12 +465,150 @@ impl RenderContext {
     pub fn insert(&mut self, key: LiteralKey, value: Literal) {
         let mut d = HashMap::new();
         d.insert(key, value);
-        self.stack.push(d);
+        self.stack.push(Arc::new(d));
     }
     pub fn insert_current(&mut self, key: LiteralKey, value: Literal) {
-        self.stack.last_mut().unwrap().insert(key, value);
+        Arc::make_mut(self.stack.last_mut().unwrap()).insert(key, value);
     }
     pub fn get(&self, key: &LiteralKey) -> Option<&Literal> {
         self.stack.iter().rev().find_map(|scope| scope.get(key))
     }
+
+    /// Provide a value to the rest of the rendering of the context.
+    pub fn provide(&mut self, name: String, value: Literal) {
+        Arc::make_mut(&mut self.provided).insert(name, value);
+    }
+    pub fn inject(&self, name: &str) -> Option<&Literal> {
+        self.provided.get(name)
+    }
+
+    /// The provided values, to restore them at the end of a provider.
+    pub fn provided(&self) -> Arc<HashMap<String, Literal>> {
+        self.provided.clone()
+    }
+    pub fn set_provided(&mut self, provided: Arc<HashMap<String, Literal>>) {
+        self.provided = provided;
+    }
+}
+
+impl Encode for LiteralKey {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        match self {
+            LiteralKey::Int(i) => {
+                enc.write_u8(0);
+                enc.write_isize(*i);
+            }
+            LiteralKey::Str(s) => {
+                enc.write_u8(1);
+                enc.write_str(s);
+            }
+            LiteralKey::Uuid(u) => {
+                enc.write_u8(2);
+                enc.write_str(u);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Decode for LiteralKey {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        match dec.read_u8()? {
+            0 => Ok(LiteralKey::Int(dec.read_isize()?)),
+            1 => Ok(LiteralKey::Str(dec.read_str()?)),
+            2 => Ok(LiteralKey::Uuid(dec.read_str()?)),
+            tag => Err(dec.invalid("key", tag)),
+        }
+    }
+}
+
+/// The integer literals too large for an isize are python ints.
+fn is_int(n: &PyObj) -> bool {
+    Python::with_gil(|py| n.obj().bind(py).is_exact_instance_of::<PyInt>())
+}
+
+/// The literals of the compiled expressions are encoded, the python
+/// objects are not, except the integers too large for an isize.
+impl Encode for Literal {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        match self {
+            Literal::None(()) => enc.write_u8(0),
+            Literal::Bool(b) => {
+                enc.write_u8(1);
+                b.encode(enc)?;
+            }
+            Literal::Int(i) => {
+                enc.write_u8(2);
+                enc.write_isize(*i);
+            }
+            Literal::Float(f) => {
+                enc.write_u8(3);
+                f.encode(enc)?;
+            }
+            Literal::Str(s) => {
+                enc.write_u8(4);
+                enc.write_str(s);
+            }
+            Literal::Markup(s) => {
+                enc.write_u8(5);
+                enc.write_str(s);
+            }
+            Literal::Uuid(u) => {
+                enc.write_u8(6);
+                enc.write_str(u);
+            }
+            Literal::XNode(node) => {
+                enc.write_u8(7);
+                node.encode(enc)?;
+            }
+            Literal::List(items) => {
+                enc.write_u8(8);
+                items.encode(enc)?;
+            }
+            Literal::Dict(items) => {
+                enc.write_u8(9);
+                items.encode(enc)?;
+            }
+            Literal::Callable(name) => {
+                enc.write_u8(10);
+                enc.write_str(name);
+            }
+            Literal::Number(n) if is_int(n) => {
+                enc.write_u8(11);
+                let digits = Python::with_gil(|py| n.obj().bind(py).str()?.extract::<String>())?;
+                enc.write_str(&digits);
+            }
+            Literal::Number(_) | Literal::Object(_) => {
+                return Err(PyTypeError::new_err(format!("Cannot serialize {:?}", self)))
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Decode for Literal {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        let value = match dec.read_u8()? {
+            0 => Literal::None(()),
+            1 => Literal::Bool(Decode::decode(dec)?),
+            2 => Literal::Int(dec.read_isize()?),
+            3 => Literal::Float(Decode::decode(dec)?),
+            4 => Literal::Str(dec.read_str()?),
+            5 => Literal::Markup(dec.read_str()?),
+            6 => Literal::Uuid(dec.read_str()?),
+            7 => Literal::XNode(Decode::decode(dec)?),
+            8 => Literal::List(Decode::decode(dec)?),
+            9 => Literal::Dict(Decode::decode(dec)?),
+            10 => Literal::Callable(dec.read_str()?),
+            11 => {
+                let digits = dec.read_str()?;
+                Python::with_gil(|py| -> PyResult<_> {
+                    let value = py.get_type::<PyInt>().call1((digits,))?;
+                    Ok(Literal::Number(PyObj::new(value.unbind())))
+                })?
+            }
+            tag => return Err(dec.invalid("literal", tag)),
+        };
+        Ok(value)
+    }
 }
@@ -0,0 +1,100 @@
+use pyo3::ffi::c_str;
+use pyo3::prelude::*;
+use pyo3::sync::GILOnceCell;
+use pyo3::types::PyType;
+
+static RENDER_ERROR_MODULE: GILOnceCell<Py<PyModule>> = GILOnceCell::new();
+
+fn render_error_module(py: Python<'_>) -> PyResult<&Bound<'_, PyModule>> {
+    RENDER_ERROR_MODULE
+        .get_or_try_init(py, || {
+            let module = PyModule::from_code(
+                py,
+                c_str!(
+                    r#"
+class XComponentRenderError(Exception):
+    """
+    Error raised while rendering components.
+
+    The original exception type is kept, the raised error is a subclass of
+    both, this one and the original exception type.
+    """
+
+    component_stack = ()
+
+
+_error_types = {}
+
+
+def _error_type(typ):
+    try:
+        return _error_types[typ]
+    except KeyError:
+        try:
+            err_type = type(
+                typ.__name__,
+                (XComponentRenderError, typ),
+                {"__module__": typ.__module__},
+            )
+        except TypeError:
+            err_type = XComponentRenderError
+        _error_types[typ] = err_type
+        return err_type
+
+
+def wrap(exc, frame):
+    if not isinstance(exc, XComponentRenderError):
+        err_type = _error_type(type(exc))
+        try:
+            err = err_type(*exc.args)
+        except Exception:
+            # the exception has a custom constructor, attributes are copied
+            err = err_type.__new__(err_type, *exc.args)
+            err.args = exc.args
+        err.__dict__.update(exc.__dict__)
+        err.__cause__ = exc.__cause__
+        err.__context__ = exc.__context__
+        err.__suppress_context__ = exc.__suppress_context__
+        err.__traceback__ = exc.__traceback__
+        err.__notes__ = [*getattr(exc, "__notes__", ()), ""]
+        err.component_stack = []
+        err._stack_note = len(err.__notes__) - 1
+        exc = err
+    exc.component_stack.insert(0, frame)
+    exc.__notes__[exc._stack_note] = "component stack: " + " > ".join(
+        exc.component_stack
+    )
+    return exc
+"#
+                ),
+                c_str!("error.py"),
+                c_str!("xcomponent.xcore.error"),
+            )?;
+            module
+                .getattr("XComponentRenderError")?
+                .setattr("__module__", "xcomponent.xcore")?;
+            Ok(module.unbind())
+        })
+        .map(|m| m.bind(py))
+}
+
+/// The python type of the errors raised while rendering components.
+pub fn render_error_type(py: Python<'_>) -> PyResult<Bound<'_, PyType>> {
+    Ok(render_error_module(py)?
+        .getattr("XComponentRenderError")?
+        .downcast_into::<PyType>()?)
+}
+
+/// Wrap the error in a render error, and push the frame on its component
+/// stack, the frame describe the component being rendered.
+pub fn render_error(py: Python<'_>, err: PyErr, frame: impl FnOnce() -> String) -> PyErr {
+    let wrapped = render_error_module(py)
+        .and_then(|module| module.getattr("wrap")?.call1((err.value(py), frame())));
+    match wrapped {
+        Ok(value) => PyErr::from_value(value),
+        Err(e) => {
+            warn!("Cannot wrap render error: {}", e);
+            err
+        }
+    }
+}
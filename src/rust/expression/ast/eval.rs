@@ -1,27 +1,129 @@
 use std::cmp::min;
 use std::collections::HashMap;
+use std::fmt;
 
+use indexmap::IndexMap;
 use pyo3::exceptions::{
-    PyAttributeError, PyIndexError, PyKeyError, PyTypeError, PyZeroDivisionError,
+    PyAttributeError, PyIndexError, PyKeyError, PyTypeError, PyValueError, PyZeroDivisionError,
 };
 use pyo3::prelude::*;
-use pyo3::types::{PyDict, PyTuple};
+use pyo3::types::{PyDict, PySlice, PyTuple};
 
 use crate::catalog::XCatalog;
-use crate::context::{Literal, LiteralKey, RenderContext, Truthy};
-use crate::expression::ast::model::AST;
-use crate::expression::ast::parse::parse;
-use crate::expression::tokens::{ExpressionToken, UnaryOperator};
-use crate::expression::{parser::tokenize, tokens::Operator};
-use crate::markup::tokens::ToHtml;
+use crate::context::{
+    format_float, format_uuid, Literal, LiteralKey, PyObj, RenderContext, Truthy,
+};
+use crate::error::render_error;
+use crate::expression::ast::model::{MatchArm, MatchPattern, AST};
+use crate::expression::lambda::XLambda;
+use crate::expression::tokens::{Operator, UnaryOperator};
+use crate::expression::{filters, methods};
+use crate::markup::slot::render_slot;
+use crate::markup::tokens::{ToHtml, XExpression};
+
+/// Numeric value of a native literal, booleans are integers, like in Python.
+#[derive(Debug, Clone, Copy)]
+enum Num {
+    Int(isize),
+    Float(f64),
+}
+
+impl Num {
+    fn from_literal(lit: &Literal) -> Option<Num> {
+        match lit {
+            Literal::Bool(b) => Some(Num::Int(*b as isize)),
+            Literal::Int(i) => Some(Num::Int(*i)),
+            Literal::Float(f) => Some(Num::Float(*f)),
+            _ => None,
+        }
+    }
+
+    fn as_f64(self) -> f64 {
+        match self {
+            Num::Int(i) => i as f64,
+            Num::Float(f) => f,
+        }
+    }
+
+    fn is_zero(self) -> bool {
+        match self {
+            Num::Int(i) => i == 0,
+            Num::Float(f) => f == 0.0,
+        }
+    }
+}
+
+/// Integers that can be converted to float without loss of precision.
+const MAX_EXACT_INT: usize = 1 << f64::MANTISSA_DIGITS;
+
+fn is_number(lit: &Literal) -> bool {
+    matches!(
+        lit,
+        Literal::Bool(_) | Literal::Int(_) | Literal::Float(_) | Literal::Number(_)
+    )
+}
+
+/// Apply a function of the python operator module, used for the numbers that
+/// do not have a native type, and for the integers that overflow.
+fn eval_py_op(op: &str, l: &Literal, r: &Literal) -> PyResult<Literal> {
+    Python::with_gil(|py| {
+        let operator = PyModule::import(py, "operator")?;
+        let res = operator.call_method1(op, (l.into_py(py), r.into_py(py)))?;
+        Literal::downcast(py, res)
+    })
+}
+
+/// Evaluate an arithmetic operation on numbers, returns None if one of the
+/// operand is not a number.
+fn eval_num_op(
+    l: &Literal,
+    r: &Literal,
+    py_op: &str,
+    int_op: fn(isize, isize) -> Option<isize>,
+    float_op: fn(f64, f64) -> f64,
+) -> Option<PyResult<Literal>> {
+    match (Num::from_literal(l), Num::from_literal(r)) {
+        (Some(Num::Int(a)), Some(Num::Int(b))) => Some(match int_op(a, b) {
+            Some(i) => Ok(Literal::Int(i)),
+            None => eval_py_op(py_op, l, r),
+        }),
+        (Some(a), Some(b)) => Some(Ok(Literal::Float(float_op(a.as_f64(), b.as_f64())))),
+        _ if is_number(l) && is_number(r) => Some(eval_py_op(py_op, l, r)),
+        _ => None,
+    }
+}
+
+/// Evaluate a comparison of numbers, returns None if one of the operand is not
+/// a number.
+fn eval_num_cmp(
+    l: &Literal,
+    r: &Literal,
+    py_op: &str,
+    int_cmp: fn(&isize, &isize) -> bool,
+    float_cmp: fn(&f64, &f64) -> bool,
+) -> Option<PyResult<bool>> {
+    match (Num::from_literal(l), Num::from_literal(r)) {
+        (Some(Num::Int(a)), Some(Num::Int(b))) => Some(Ok(int_cmp(&a, &b))),
+        (Some(a), Some(b)) => Some(Ok(float_cmp(&a.as_f64(), &b.as_f64()))),
+        _ if is_number(l) && is_number(r) => Some(eval_py_op(py_op, l, r).map(|b| b.is_truthy())),
+        _ => None,
+    }
+}
 
 fn eval_add(l: Literal, r: Literal) -> PyResult<Literal> {
+    if let Some(res) = eval_num_op(&l, &r, "add", isize::checked_add, |a, b| a + b) {
+        return res;
+    }
     match (l, r) {
-        (Literal::Int(a), Literal::Int(b)) => Ok(Literal::Int(a + b)),
-        (Literal::Int(a), Literal::Bool(b)) => Ok(Literal::Int(a + b as isize)),
-        (Literal::Bool(a), Literal::Int(b)) => Ok(Literal::Int(a as isize + b)),
-        (Literal::Bool(a), Literal::Bool(b)) => Ok(Literal::Int(a as isize + b as isize)),
         (Literal::Str(a), Literal::Str(b)) => Ok(Literal::Str(a + &b)),
+        // like markupsafe, the unsafe string is escaped while concatenated to markup
+        (Literal::Markup(a), Literal::Markup(b)) => Ok(Literal::Markup(a + &b)),
+        (Literal::Markup(a), Literal::Str(b)) => {
+            Ok(Literal::Markup(a + &html_escape::encode_text(&b)))
+        }
+        (Literal::Str(a), Literal::Markup(b)) => Ok(Literal::Markup(
+            html_escape::encode_text(&a).to_string() + &b,
+        )),
         (a, b) => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
             "Cannot add {:?} + {:?}, type mismatch",
             a, b
@@ -30,30 +132,32 @@ fn eval_add(l: Literal, r: Literal) -> PyResult<Literal> {
 }
 
 fn eval_sub(l: Literal, r: Literal) -> PyResult<Literal> {
-    match (l, r) {
-        (Literal::Int(a), Literal::Int(b)) => Ok(Literal::Int(a - b)),
-        (Literal::Int(a), Literal::Bool(b)) => Ok(Literal::Int(a - b as isize)),
-        (Literal::Bool(a), Literal::Int(b)) => Ok(Literal::Int(a as isize - b)),
-        (Literal::Bool(a), Literal::Bool(b)) => Ok(Literal::Int(a as isize - b as isize)),
-        (a, b) => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
-            "Cannot substract {:?} - {:?}, type mismatch",
-            a, b
-        ))),
+    if let Some(res) = eval_num_op(&l, &r, "sub", isize::checked_sub, |a, b| a - b) {
+        return res;
     }
+    Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+        "Cannot substract {:?} - {:?}, type mismatch",
+        l, r
+    )))
 }
 
 fn eval_mul(l: Literal, r: Literal) -> PyResult<Literal> {
+    if let Some(res) = eval_num_op(&l, &r, "mul", isize::checked_mul, |a, b| a * b) {
+        return res;
+    }
     match (l, r) {
-        (Literal::Int(a), Literal::Int(b)) => Ok(Literal::Int(a * b)),
-        (Literal::Int(a), Literal::Bool(b)) => Ok(Literal::Int(a * b as isize)),
-        (Literal::Bool(a), Literal::Int(b)) => Ok(Literal::Int(a as isize * b)),
-        (Literal::Bool(a), Literal::Bool(b)) => Ok(Literal::Int(a as isize * b as isize)),
         (Literal::Str(a), Literal::Int(b)) => Ok(Literal::Str(if b > 0 {
             a.repeat(b as usize)
         } else {
             "".to_string()
         })),
         (Literal::Str(a), Literal::Bool(b)) => Ok(Literal::Str(a.repeat(b as usize))),
+        (Literal::Markup(a), Literal::Int(b)) => Ok(Literal::Markup(if b > 0 {
+            a.repeat(b as usize)
+        } else {
+            "".to_string()
+        })),
+        (Literal::Markup(a), Literal::Bool(b)) => Ok(Literal::Markup(a.repeat(b as usize))),
         (a, b) => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
             "Cannot multiply {:?} * {:?}, type mismatch",
             a, b
@@ -61,43 +165,180 @@ fn eval_mul(l: Literal, r: Literal) -> PyResult<Literal> {
     }
 }
 
+/// The true division, the result is always a float, like in Python.
 fn eval_div(l: Literal, r: Literal) -> PyResult<Literal> {
-    match (l, r) {
-        (Literal::Int(a), Literal::Int(b)) => {
-            if b == 0 {
-                Err(PyErr::new::<PyZeroDivisionError, _>("Division by zero"))
-            } else {
-                Ok(Literal::Int(a / b))
-            }
+    match (Num::from_literal(&l), Num::from_literal(&r)) {
+        (Some(_), Some(b)) if b.is_zero() => {
+            Err(PyErr::new::<PyZeroDivisionError, _>("Division by zero"))
         }
-        (Literal::Int(a), Literal::Bool(b)) => {
-            if b as isize == 0 {
-                Err(PyErr::new::<PyZeroDivisionError, _>("Division by zero"))
-            } else {
-                Ok(Literal::Int(a / b as isize))
+        (Some(Num::Int(a)), Some(Num::Int(b)))
+            if a.unsigned_abs() > MAX_EXACT_INT || b.unsigned_abs() > MAX_EXACT_INT =>
+        {
+            eval_py_op("truediv", &l, &r)
+        }
+        (Some(a), Some(b)) => Ok(Literal::Float(a.as_f64() / b.as_f64())),
+        _ if is_number(&l) && is_number(&r) => eval_py_op("truediv", &l, &r),
+        _ => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+            "Cannot divide {:?} / {:?}, type mismatch",
+            l, r
+        ))),
+    }
+}
+
+/// The floor division, rounded toward negative infinity, like in Python.
+fn eval_floordiv(l: Literal, r: Literal) -> PyResult<Literal> {
+    match (Num::from_literal(&l), Num::from_literal(&r)) {
+        (Some(_), Some(b)) if b.is_zero() => {
+            Err(PyErr::new::<PyZeroDivisionError, _>("Division by zero"))
+        }
+        (Some(Num::Int(a)), Some(Num::Int(b))) => match a.checked_div(b) {
+            Some(q) if a % b != 0 && ((a < 0) != (b < 0)) => Ok(Literal::Int(q - 1)),
+            Some(q) => Ok(Literal::Int(q)),
+            None => eval_py_op("floordiv", &l, &r),
+        },
+        (Some(a), Some(b)) => Ok(Literal::Float(float_floordiv(a.as_f64(), b.as_f64()))),
+        _ if is_number(&l) && is_number(&r) => eval_py_op("floordiv", &l, &r),
+        _ => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+            "Cannot divide {:?} // {:?}, type mismatch",
+            l, r
+        ))),
+    }
+}
+
+/// The modulo has the sign of the divisor, like in Python.
+fn eval_mod(l: Literal, r: Literal) -> PyResult<Literal> {
+    match (Num::from_literal(&l), Num::from_literal(&r)) {
+        (Some(_), Some(b)) if b.is_zero() => {
+            Err(PyErr::new::<PyZeroDivisionError, _>("Modulo by zero"))
+        }
+        (Some(Num::Int(a)), Some(Num::Int(b))) => match a.checked_rem(b) {
+            Some(m) if m != 0 && ((m < 0) != (b < 0)) => Ok(Literal::Int(m + b)),
+            Some(m) => Ok(Literal::Int(m)),
+            None => eval_py_op("mod", &l, &r),
+        },
+        (Some(a), Some(b)) => Ok(Literal::Float(float_mod(a.as_f64(), b.as_f64()))),
+        _ if is_number(&l) && is_number(&r) => eval_py_op("mod", &l, &r),
+        _ => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+            "Cannot compute modulo {:?} % {:?}, type mismatch",
+            l, r
+        ))),
+    }
+}
+
+/// The modulo of floats, ported from CPython.
+fn float_mod(a: f64, b: f64) -> f64 {
+    let m = a % b;
+    if m == 0.0 {
+        0.0_f64.copysign(b)
+    } else if (b < 0.0) != (m < 0.0) {
+        m + b
+    } else {
+        m
+    }
+}
+
+fn eval_pow(l: Literal, r: Literal) -> PyResult<Literal> {
+    match (Num::from_literal(&l), Num::from_literal(&r)) {
+        (Some(Num::Int(a)), Some(Num::Int(b))) => {
+            // a negative exponent produces a float, computed by python
+            match u32::try_from(b).ok().and_then(|b| a.checked_pow(b)) {
+                Some(i) => Ok(Literal::Int(i)),
+                None => eval_py_op("pow", &l, &r),
             }
         }
-        (Literal::Bool(a), Literal::Int(b)) => {
-            if b == 0 {
-                Err(PyErr::new::<PyZeroDivisionError, _>("Division by zero"))
+        (Some(a), Some(b)) => {
+            let (a, b) = (a.as_f64(), b.as_f64());
+            if (a == 0.0 && b < 0.0) || (a < 0.0 && b.fract() != 0.0) {
+                // python raises the division by zero, or returns a complex number
+                eval_py_op("pow", &l, &r)
             } else {
-                Ok(Literal::Int(a as isize / b))
+                Ok(Literal::Float(a.powf(b)))
             }
         }
-        (Literal::Bool(a), Literal::Bool(b)) => {
-            if b as isize == 0 {
-                Err(PyErr::new::<PyZeroDivisionError, _>("Division by zero"))
-            } else {
-                Ok(Literal::Int(a as isize / b as isize))
+        _ if is_number(&l) && is_number(&r) => eval_py_op("pow", &l, &r),
+        _ => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+            "Cannot raise {:?} ** {:?}, type mismatch",
+            l, r
+        ))),
+    }
+}
+
+fn eval_neg(value: Literal) -> PyResult<Literal> {
+    match Num::from_literal(&value) {
+        Some(Num::Int(i)) => match i.checked_neg() {
+            Some(i) => Ok(Literal::Int(i)),
+            None => Python::with_gil(|py| {
+                let operator = PyModule::import(py, "operator")?;
+                Literal::downcast(py, operator.call_method1("neg", (value.into_py(py),))?)
+            }),
+        },
+        Some(Num::Float(f)) => Ok(Literal::Float(-f)),
+        None => match value {
+            Literal::Number(n) => {
+                Python::with_gil(|py| Literal::downcast(py, n.obj().bind(py).neg()?))
             }
+            other => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+                "Cannot negate {:?}, type mismatch",
+                other
+            ))),
+        },
+    }
+}
+
+/// Membership test, `l in r`.
+fn eval_in(l: Literal, r: Literal) -> PyResult<bool> {
+    match (l, r) {
+        (Literal::Str(a) | Literal::Markup(a), Literal::Str(b) | Literal::Markup(b)) => {
+            Ok(b.contains(&a))
         }
+        // values of different types are not equal, like in Python
+        (a, Literal::List(items)) => Ok(items
+            .into_iter()
+            .any(|item| matches!(eval_raw_eq(a.clone(), item, "in".to_string()), Ok(true)))),
+        (Literal::Bool(b), Literal::Dict(map)) => {
+            Ok(map.contains_key(&LiteralKey::Int(b as isize)))
+        }
+        (a, Literal::Dict(map)) => Ok(map.contains_key(&LiteralKey::try_from(a)?)),
+        (a, b @ Literal::Object(_)) => eval_py_op("contains", &b, &a).map(|res| res.is_truthy()),
         (a, b) => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
-            "Cannot divide {:?} / {:?}, type mismatch",
+            "Cannot check membership {:?} in {:?}, type mismatch",
             a, b
         ))),
     }
 }
 
+/// Identity test, `l is r`, meant to check for None, None and booleans are
+/// compared by value, and the python objects by identity.
+fn eval_is(l: &Literal, r: &Literal) -> bool {
+    match (l, r) {
+        (Literal::None(()), Literal::None(())) => true,
+        (Literal::Bool(a), Literal::Bool(b)) => a == b,
+        (Literal::Number(a), Literal::Number(b)) | (Literal::Object(a), Literal::Object(b)) => {
+            a.obj().is(b.obj())
+        }
+        _ => false,
+    }
+}
+
+/// The floor division of floats, ported from CPython.
+fn float_floordiv(a: f64, b: f64) -> f64 {
+    let m = a % b;
+    let mut div = (a - m) / b;
+    if m != 0.0 && ((b < 0.0) != (m < 0.0)) {
+        div -= 1.0;
+    }
+    if div != 0.0 {
+        let floordiv = div.floor();
+        if div - floordiv > 0.5 {
+            floordiv + 1.0
+        } else {
+            floordiv
+        }
+    } else {
+        0.0_f64.copysign(a / b)
+    }
+}
+
 fn eval_and(l: Literal, r: Literal) -> PyResult<Literal> {
     match (l.is_truthy(), r.is_truthy()) {
         (true, false) => Ok(r),
@@ -116,13 +357,15 @@ fn eval_or(l: Literal, r: Literal) -> PyResult<Literal> {
     }
 }
 
-fn eval_raw_eq(l: Literal, r: Literal, op: String) -> PyResult<bool> {
+pub fn eval_raw_eq(l: Literal, r: Literal, op: String) -> PyResult<bool> {
+    if let Some(res) = eval_num_cmp(&l, &r, "eq", isize::eq, f64::eq) {
+        return res;
+    }
     match (l, r) {
-        (Literal::Int(a), Literal::Int(b)) => Ok(a == b),
-        (Literal::Int(a), Literal::Bool(b)) => Ok(a == b as isize),
-        (Literal::Bool(a), Literal::Int(b)) => Ok(a as isize == b),
-        (Literal::Bool(a), Literal::Bool(b)) => Ok(a == b),
         (Literal::Str(a), Literal::Str(b)) => Ok(a == b),
+        (Literal::Markup(a), Literal::Markup(b)) => Ok(a == b),
+        (Literal::Markup(a), Literal::Str(b)) => Ok(a == b),
+        (Literal::Str(a), Literal::Markup(b)) => Ok(a == b),
         (Literal::Uuid(a), Literal::Uuid(b)) => Ok(a == b),
         (Literal::None(()), Literal::None(())) => Ok(true),
         (Literal::None(()), _) => Ok(false),
@@ -143,11 +386,10 @@ fn eval_neq(l: Literal, r: Literal) -> PyResult<Literal> {
 }
 
 fn eval_raw_gt(l: Literal, r: Literal) -> PyResult<bool> {
+    if let Some(res) = eval_num_cmp(&l, &r, "gt", isize::gt, f64::gt) {
+        return res;
+    }
     match (l, r) {
-        (Literal::Int(a), Literal::Int(b)) => Ok(a > b),
-        (Literal::Int(a), Literal::Bool(b)) => Ok(a > b as isize),
-        (Literal::Bool(a), Literal::Int(b)) => Ok(a as isize > b),
-        (Literal::Bool(a), Literal::Bool(b)) => Ok(a > b),
         (Literal::Str(a), Literal::Str(b)) => Ok(a > b),
         (a, b) => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
             "Cannot compare {:?} > {:?}, type mismatch",
@@ -156,12 +398,11 @@ fn eval_raw_gt(l: Literal, r: Literal) -> PyResult<bool> {
     }
 }
 
-fn eval_raw_lt(l: Literal, r: Literal) -> PyResult<bool> {
+pub fn eval_raw_lt(l: Literal, r: Literal) -> PyResult<bool> {
+    if let Some(res) = eval_num_cmp(&l, &r, "lt", isize::lt, f64::lt) {
+        return res;
+    }
     match (l, r) {
-        (Literal::Int(a), Literal::Int(b)) => Ok(a < b),
-        (Literal::Int(a), Literal::Bool(b)) => Ok(a < b as isize),
-        (Literal::Bool(a), Literal::Int(b)) => Ok((a as isize) < b),
-        (Literal::Bool(a), Literal::Bool(b)) => Ok(a < b),
         (Literal::Str(a), Literal::Str(b)) => Ok(a < b),
         (a, b) => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
             "Cannot compare {:?} < {:?}, type mismatch",
@@ -170,6 +411,32 @@ fn eval_raw_lt(l: Literal, r: Literal) -> PyResult<bool> {
     }
 }
 
+fn eval_raw_gte(l: Literal, r: Literal) -> PyResult<bool> {
+    if let Some(res) = eval_num_cmp(&l, &r, "ge", isize::ge, f64::ge) {
+        return res;
+    }
+    match (l, r) {
+        (Literal::Str(a), Literal::Str(b)) => Ok(a >= b),
+        (a, b) => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+            "Cannot compare {:?} >= {:?}, type mismatch",
+            a, b
+        ))),
+    }
+}
+
+fn eval_raw_lte(l: Literal, r: Literal) -> PyResult<bool> {
+    if let Some(res) = eval_num_cmp(&l, &r, "le", isize::le, f64::le) {
+        return res;
+    }
+    match (l, r) {
+        (Literal::Str(a), Literal::Str(b)) => Ok(a <= b),
+        (a, b) => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(format!(
+            "Cannot compare {:?} <= {:?}, type mismatch",
+            a, b
+        ))),
+    }
+}
+
 fn eval_gt(l: Literal, r: Literal) -> PyResult<Literal> {
     return eval_raw_gt(l, r).map(|b| Literal::Bool(b));
 }
@@ -179,11 +446,54 @@ fn eval_lt(l: Literal, r: Literal) -> PyResult<Literal> {
 }
 
 fn eval_gte(l: Literal, r: Literal) -> PyResult<Literal> {
-    return eval_raw_lt(l, r).map(|b| Literal::Bool(!b));
+    return eval_raw_gte(l, r).map(Literal::Bool);
 }
 
 fn eval_lte(l: Literal, r: Literal) -> PyResult<Literal> {
-    return eval_raw_gt(l, r).map(|b| Literal::Bool(!b));
+    return eval_raw_lte(l, r).map(Literal::Bool);
+}
+
+/// The value as a string, as rendered but not escaped, used by the f-strings.
+///
+/// The rule is the one of the markup: the items of a list are joined,
+/// a dict gives its keys and values, and an object gives its repr.
+pub fn format_literal<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    value: &Literal,
+) -> PyResult<String> {
+    match value {
+        Literal::None(()) => Ok("".to_string()),
+        Literal::Bool(b) => Ok(b.to_string()),
+        Literal::Int(i) => Ok(i.to_string()),
+        Literal::Float(f) => Ok(format_float(*f)),
+        Literal::Number(n) => Ok(n.obj().bind(py).str()?.to_string()),
+        Literal::Str(s) | Literal::Markup(s) => Ok(s.clone()),
+        Literal::Uuid(u) => Ok(format_uuid(u)),
+        Literal::Callable(s) => Ok(format!("{}()", s)),
+        Literal::XNode(n) => catalog.render_node(py, n, context),
+        Literal::List(items) => items
+            .iter()
+            .map(|item| format_literal(py, catalog, context, item))
+            .collect(),
+        Literal::Dict(items) => {
+            let mut result = String::new();
+            for (key, item) in items {
+                result.push_str(&key.to_string());
+                result.push_str(&format_literal(py, catalog, context, item)?);
+            }
+            Ok(result)
+        }
+        Literal::Object(_) => match render_slot(py, value.clone())? {
+            Literal::Markup(s) => Ok(s),
+            _ => Ok(value
+                .into_py(py)
+                .repr()
+                .map(|repr| repr.to_string())
+                .unwrap_or_else(|_| "<PyObject>".to_string())),
+        },
+    }
 }
 
 pub fn eval_ast<'py>(
@@ -191,37 +501,69 @@ pub fn eval_ast<'py>(
     ast: &'py AST,
     catalog: &XCatalog,
     context: &mut RenderContext,
+) -> Result<Literal, PyErr> {
+    eval_ast_node(py, ast, catalog, context).map_err(|err| match ast.span() {
+        Some(span) => span.locate(py, err),
+        None => err,
+    })
+}
+
+fn eval_ast_node<'py>(
+    py: Python<'py>,
+    ast: &'py AST,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
 ) -> Result<Literal, PyErr> {
     // error!(":::::::");
     // error!("{:?}", ast);
     match ast {
         AST::Literal(lit) => Ok(lit.clone()),
 
-        AST::Unary { op, expr } => {
+        AST::Format(expr) => {
+            let value = eval_ast(py, expr, catalog, context)?;
+            format_literal(py, catalog, context, &value).map(Literal::Str)
+        }
+
+        AST::List(items) => Ok(Literal::List(
+            items
+                .iter()
+                .map(|item| eval_ast(py, item, catalog, context))
+                .collect::<Result<_, _>>()?,
+        )),
+
+        AST::Dict(items, _) => {
+            let mut map = IndexMap::with_capacity(items.len());
+            for (key, value) in items {
+                let key = LiteralKey::try_from(eval_ast(py, key, catalog, context)?)?;
+                map.insert(key, eval_ast(py, value, catalog, context)?);
+            }
+            Ok(Literal::Dict(map))
+        }
+
+        AST::Unary { op, expr, .. } => {
             let value = eval_ast(py, expr, catalog, context)?;
             match (op, value) {
                 (UnaryOperator::Not, Literal::Bool(b)) => Ok(Literal::Bool(!b)),
                 (UnaryOperator::Not, Literal::Int(i)) => Ok(Literal::Bool(i == 0)),
+                (UnaryOperator::Not, Literal::Float(f)) => Ok(Literal::Bool(f == 0.0)),
+                (UnaryOperator::Not, n @ Literal::Number(_)) => Ok(Literal::Bool(!n.is_truthy())),
                 (UnaryOperator::Not, Literal::Str(s)) => Ok(Literal::Bool(s.len() == 0)),
+                (UnaryOperator::Not, Literal::Markup(s)) => Ok(Literal::Bool(s.is_empty())),
                 (UnaryOperator::Not, other) => Err(PyErr::new::<pyo3::exceptions::PyTypeError, _>(
                     format!("Cannot apply 'not' to {:?}", other),
                 )),
+                (UnaryOperator::Neg, value) => eval_neg(value),
             }
         }
 
-        AST::Binary { left, op, right } => {
+        AST::Binary {
+            left, op, right, ..
+        } => {
             let l = eval_ast(py, left, catalog, context)?;
             match op {
-                Operator::And => {
-                    if !l.is_truthy() {
-                        return Ok(l);
-                    }
-                }
-                Operator::Or => {
-                    if l.is_truthy() {
-                        return Ok(l);
-                    }
-                }
+                Operator::And if !l.is_truthy() => return Ok(l),
+                Operator::Or if l.is_truthy() => return Ok(l),
+                Operator::Coalesce if !matches!(l, Literal::None(())) => return Ok(l),
                 _ => (),
             }
             let r = eval_ast(py, right, catalog, context)?;
@@ -231,24 +573,35 @@ pub fn eval_ast<'py>(
                 Operator::Sub => eval_sub(l, r),
                 Operator::Mul => eval_mul(l, r),
                 Operator::Div => eval_div(l, r),
+                Operator::FloorDiv => eval_floordiv(l, r),
+                Operator::Mod => eval_mod(l, r),
+                Operator::Pow => eval_pow(l, r),
                 Operator::And => eval_and(l, r),
                 Operator::Or => eval_or(l, r),
+                Operator::Coalesce => Ok(r),
                 Operator::Eq => eval_eq(l, r),
                 Operator::Neq => eval_neq(l, r),
                 Operator::Gt => eval_gt(l, r),
                 Operator::Lt => eval_lt(l, r),
                 Operator::Gte => eval_gte(l, r),
                 Operator::Lte => eval_lte(l, r),
+                Operator::In => eval_in(l, r).map(Literal::Bool),
+                Operator::NotIn => eval_in(l, r).map(|b| Literal::Bool(!b)),
+                Operator::Is => Ok(Literal::Bool(eval_is(&l, &r))),
+                Operator::IsNot => Ok(Literal::Bool(!eval_is(&l, &r))),
             }
         }
 
-        AST::Variable(name) => {
+        AST::Variable(name, _) => {
             let val = context.get(&LiteralKey::Str(name.clone())).cloned();
             match val {
                 Some(Literal::None(_)) => Ok(Literal::None(())),
                 Some(Literal::Bool(v)) => Ok(Literal::Bool(v.clone())),
                 Some(Literal::Int(v)) => Ok(Literal::Int(v.clone())),
+                Some(Literal::Float(v)) => Ok(Literal::Float(v)),
+                Some(Literal::Number(v)) => Ok(Literal::Number(v)),
                 Some(Literal::Str(v)) => Ok(Literal::Str(v.clone())),
+                Some(Literal::Markup(v)) => Ok(Literal::Markup(v.clone())),
                 Some(Literal::Callable(v)) => Ok(Literal::Callable(v.clone())),
                 Some(Literal::Uuid(v)) => Ok(Literal::Uuid(v.clone())),
                 Some(Literal::List(v)) => Ok(Literal::List(v.clone())),
@@ -257,11 +610,13 @@ pub fn eval_ast<'py>(
                 Some(Literal::XNode(ref node)) => {
                     debug!("Rendering node from expression with context {:?}", context);
                     let resp = catalog.render_node(py, node, context);
-                    resp.map(|markup| Literal::Str(markup))
+                    resp.map(|markup| Literal::Markup(markup))
                 }
                 None => {
                     if let Some(_) = catalog.functions().get(name) {
                         Ok(Literal::Callable(name.clone()))
+                    } else if filters::is_builtin(name) {
+                        Ok(Literal::Callable(name.clone()))
                     } else {
                         Err(PyErr::new::<pyo3::exceptions::PyUnboundLocalError, _>(
                             format!("{:?} is undefined", name),
@@ -270,97 +625,95 @@ pub fn eval_ast<'py>(
                 }
             }
         }
-        AST::FieldAccess(obj, field) => {
-            let base = eval_ast(py, &obj, &catalog, context)?;
-            match base {
-                Literal::Dict(map) => {
-                    // no integer cannot be a field name here
-                    if let Some(val) = map.get(&LiteralKey::Str(field.clone())) {
-                        return Ok(val.clone());
-                    }
-                    if let Some(val) = map.get(&LiteralKey::Uuid(field.clone())) {
-                        return Ok(val.clone());
-                    }
-                    Err(PyErr::new::<pyo3::exceptions::PyKeyError, _>(format!(
-                        "Field '{}' not found in {:?}",
-                        field, map
-                    )))
-                }
-                Literal::Object(o) => {
-                    // only string here. maybe callable
-                    let item = o.obj().getattr(py, field)?.into_bound(py);
-                    Literal::downcast(py, item)
-                }
-                _ => {
-                    let item = base.into_py(py).getattr(field)?;
-                    Literal::downcast(py, item)
-                }
+        AST::Slice { start, stop, step } => {
+            let mut bounds = Vec::with_capacity(3);
+            for bound in [start, stop, step] {
+                bounds.push(match bound {
+                    Some(bound) => eval_ast(py, bound, catalog, context)?.into_py(py),
+                    None => py.None().into_bound(py),
+                });
             }
+            let slice = py.get_type::<PySlice>().call1(PyTuple::new(py, bounds)?)?;
+            Ok(Literal::Object(PyObj::new(slice.unbind())))
         }
-
-        AST::IndexAccess(obj, index) => {
+        AST::FieldAccess(obj, field, _) => {
+            let base = eval_ast(py, obj, catalog, context)?;
+            eval_field(py, base, field)
+        }
+        AST::IndexAccess(obj, index, _) => {
             // obj[index]
             let base = eval_ast(py, obj, catalog, context)?;
             let key = eval_ast(py, index, catalog, context)?;
-            match base {
-                Literal::Dict(map) => {
-                    let value = map
-                        .get(&LiteralKey::try_from(key.clone())?)
-                        .ok_or_else(|| PyKeyError::new_err(format!("{:?}", key)))?;
-                    Ok(value.clone())
-                }
-                Literal::List(lst) => match key {
-                    Literal::Int(idx) => {
-                        let real_index = if idx > 0 {
-                            idx as isize
-                        } else {
-                            (lst.len() as isize + idx) as isize
-                        };
-                        if real_index < 0 {
-                            Err(PyIndexError::new_err(format!("Index out of range {}", idx)))
-                        } else {
-                            let value = lst.get(real_index as usize).ok_or_else(|| {
-                                PyIndexError::new_err(format!("Index out of range {}", idx))
-                            })?;
-                            Ok(value.clone())
-                        }
-                    }
-                    _ => Err(PyTypeError::new_err(format!("{:?}", key))),
-                },
-                Literal::Object(o) => {
-                    let item = match key {
-                        Literal::Int(idx) => {
-                            // FIXME, add len call here for negatif index
-                            o.obj().into_pyobject(py).unwrap().call_method(
-                                "__getitem__",
-                                (idx,),
-                                None,
-                            )
-                        }
-                        _ => Err(PyTypeError::new_err(format!("Index access{:?}", key))),
-                    }?;
-                    Literal::downcast(py, item)
-                }
-                _ => Err(PyErr::new::<PyTypeError, _>(format!(
-                    "Cannot access index '{:?}' on non-object",
-                    base
-                ))),
-            }
+            eval_index(py, base, key)
         }
+        AST::OptionalFieldAccess(obj, field, _) => match eval_ast(py, obj, catalog, context)? {
+            Literal::None(()) => Ok(Literal::None(())),
+            base => eval_field(py, base, field).or_else(|err| missing_as_none(py, err)),
+        },
+        AST::OptionalIndexAccess(obj, index, _) => match eval_ast(py, obj, catalog, context)? {
+            Literal::None(()) => Ok(Literal::None(())),
+            base => {
+                let key = eval_ast(py, index, catalog, context)?;
+                eval_index(py, base, key).or_else(|err| missing_as_none(py, err))
+            }
+        },
 
-        AST::CallAccess { left, args, kwargs } => {
+        AST::CallAccess {
+            left, args, kwargs, ..
+        } => {
             // left(*args, **kwargs)
-            let base = eval_ast(py, left, catalog, context)?;
+            let (base, method) = match left.as_ref() {
+                AST::FieldAccess(obj, field, _) => {
+                    let receiver = render_slot(py, eval_ast(py, obj, catalog, context)?)?;
+                    if methods::is_method(&receiver, field) {
+                        (receiver, Some(field))
+                    } else {
+                        (eval_field(py, receiver, field)?, None)
+                    }
+                }
+                left => (eval_ast(py, left, catalog, context)?, None),
+            };
 
+            // the slots are given to the arrow functions as is, and rendered
+            // for the other functions, that receive a markup string
+            let is_lambda =
+                matches!(&base, Literal::Object(o) if o.obj().bind(py).is_instance_of::<XLambda>());
+            let eval_arg = |arg: &AST, context: &mut RenderContext| {
+                let value = eval_ast(py, arg, catalog, context)?;
+                if is_lambda {
+                    Ok(value)
+                } else {
+                    render_slot(py, value)
+                }
+            };
             let lit_args = args
                 .iter()
-                .map(|arg| eval_ast(py, arg, catalog, context))
+                .map(|arg| eval_arg(arg, context))
                 .collect::<Result<Vec<_>, _>>()?;
 
             let lit_kwargs = kwargs
                 .iter()
-                .map(|(name, arg)| Ok((name.clone(), eval_ast(py, arg, catalog, context)?)))
+                .map(|(name, arg)| Ok((name.clone(), eval_arg(arg, context)?)))
                 .collect::<Result<HashMap<String, Literal>, PyErr>>()?;
+            // the common methods of the strings, lists and dicts don't call python
+            if let Some(method) = method {
+                return methods::call_method(
+                    py, catalog, context, base, method, lit_args, lit_kwargs,
+                );
+            }
+            if let Literal::Object(o) = &base {
+                // the arrow functions are evaluated without python
+                if let Ok(lambda) = o.obj().bind(py).downcast::<XLambda>() {
+                    return lambda.get().call(py, lit_args, lit_kwargs);
+                }
+            }
+            if let Literal::Callable(ident) = &base {
+                // the functions registered in the catalog override the filters
+                if !catalog.functions().contains_key(ident) {
+                    return filters::call_filter(py, catalog, context, ident, lit_args, lit_kwargs)
+                        .map_err(|err| render_error(py, err, || format!("{}()", ident)));
+                }
+            }
             let py_args = PyTuple::new(py, lit_args.iter().map(|v| v.into_py(py)))?;
             let py_kwargs = PyDict::new(py);
             for (k, v) in lit_kwargs {
@@ -382,6 +735,42 @@ pub fn eval_ast<'py>(
             }
         }
 
+        AST::Pipe {
+            name, args, kwargs, ..
+        } => {
+            // the filters are the functions of the catalog and the filters
+            // implemented in Rust, they are not shadowed by the variables
+            let lit_args = args
+                .iter()
+                .map(|arg| render_slot(py, eval_ast(py, arg, catalog, context)?))
+                .collect::<Result<Vec<_>, _>>()?;
+            let lit_kwargs = kwargs
+                .iter()
+                .map(|(name, arg)| {
+                    Ok((
+                        name.clone(),
+                        render_slot(py, eval_ast(py, arg, catalog, context)?)?,
+                    ))
+                })
+                .collect::<Result<HashMap<String, Literal>, PyErr>>()?;
+            if catalog.functions().contains_key(name) {
+                let py_args = PyTuple::new(py, lit_args.iter().map(|v| v.into_py(py)))?;
+                let py_kwargs = PyDict::new(py);
+                for (k, v) in lit_kwargs {
+                    py_kwargs.set_item(k, v.into_py(py))?;
+                }
+                let res = catalog.call(py, name, &py_args, &py_kwargs)?;
+                return Literal::downcast(py, res);
+            }
+            if !filters::is_filter(name) {
+                return Err(PyErr::new::<pyo3::exceptions::PyUnboundLocalError, _>(
+                    format!("{:?} is not a filter", name),
+                ));
+            }
+            filters::call_filter(py, catalog, context, name, lit_args, lit_kwargs)
+                .map_err(|err| render_error(py, err, || format!("{}()", name)))
+        }
+
         AST::IfStatement {
             condition,
             then_branch,
@@ -398,60 +787,450 @@ pub fn eval_ast<'py>(
                 }
             }
         }
-        AST::ForStatement {
-            ident,
-            iterable,
-            body,
-        } => {
-            let iter_lit = eval_ast(py, iterable, catalog, context)?;
-
-            // let var = params.get(iterable).map(|x| Ok(x)).unwrap_or_else(|| {
-            //     return Err(PyUnboundLocalError::new_err(format!(
-            //         "{:?} is not defined in {:?}",
-            //         ident, params
-            //     )));
-            // })?;
-            match iter_lit {
-                Literal::List(iter) => {
-                    let mut res = String::new();
-                    for v in iter {
-                        context.insert(LiteralKey::Str(ident.clone()), v);
-                        let item = eval_ast(py, body, catalog, context)?;
-                        res.push_str(item.to_html(py, catalog, context)?.as_str());
-                        context.pop()
-                    }
-                    Ok(Literal::Str(res))
-                }
-                _ => Err(PyTypeError::new_err(format!(
-                    "{} {:?} is not iterable",
-                    ident, iter_lit
-                ))),
-            }
+        AST::ForStatement { .. } => {
+            let mut res = String::new();
+            write_for(py, ast, catalog, context, &mut res)?;
+            Ok(Literal::Markup(res))
         }
         AST::LetStatement { ident, expr } => {
             let value = eval_ast(py, expr, catalog, context)?;
             context.insert_current(LiteralKey::Str(ident.clone()), value);
             Ok(Literal::Str("".to_string()))
         }
+        AST::Match { subject, arms } => {
+            let value = eval_ast(py, subject, catalog, context)?;
+            eval_match(py, value, arms, catalog, context)
+        }
+        AST::Lambda { params, body } => {
+            let lambda = XLambda::new(params, body, catalog, context);
+            Ok(Literal::Object(PyObj::new(Py::new(py, lambda)?.into_any())))
+        }
     }
 }
 
-pub fn eval_expression<'py>(
+/// The field of a dict, or the attribute of an object.
+fn eval_field(py: Python<'_>, base: Literal, field: &str) -> PyResult<Literal> {
+    match base {
+        Literal::Dict(map) => {
+            // no integer cannot be a field name here
+            if let Some(val) = map.get(&LiteralKey::Str(field.to_string())) {
+                return Ok(val.clone());
+            }
+            if let Some(val) = map.get(&LiteralKey::Uuid(field.to_string())) {
+                return Ok(val.clone());
+            }
+            Err(PyErr::new::<pyo3::exceptions::PyKeyError, _>(format!(
+                "Field '{}' not found in {:?}",
+                field, map
+            )))
+        }
+        Literal::Object(o) => {
+            // only string here. maybe callable
+            let item = o.obj().getattr(py, field)?.into_bound(py);
+            Literal::downcast(py, item)
+        }
+        _ => {
+            let item = base.into_py(py).getattr(field)?;
+            Literal::downcast(py, item)
+        }
+    }
+}
+
+/// The item of a list, a string, a dict or an object, or a slice of it.
+fn eval_index(py: Python<'_>, base: Literal, key: Literal) -> PyResult<Literal> {
+    if let Literal::Object(o) = &key {
+        if let Ok(slice) = o.obj().bind(py).downcast::<PySlice>() {
+            return eval_slice(py, base, slice);
+        }
+    }
+    match base {
+        Literal::Dict(map) => {
+            let value = map
+                .get(&LiteralKey::try_from(key.clone())?)
+                .ok_or_else(|| PyKeyError::new_err(format!("{:?}", key)))?;
+            Ok(value.clone())
+        }
+        Literal::List(lst) => match key {
+            Literal::Int(idx) => Ok(lst[sequence_index(idx, lst.len())?].clone()),
+            _ => Err(PyTypeError::new_err(format!(
+                "list indices must be integers, got {:?}",
+                key
+            ))),
+        },
+        Literal::Str(s) => match key {
+            Literal::Int(idx) => {
+                let idx = sequence_index(idx, s.chars().count())?;
+                Ok(Literal::Str(s.chars().nth(idx).unwrap().to_string()))
+            }
+            _ => Err(PyTypeError::new_err(format!(
+                "string indices must be integers, got {:?}",
+                key
+            ))),
+        },
+        Literal::Object(o) => {
+            let obj = o.obj().bind(py);
+            // the sequences that don't support negative indexes, such as
+            // querysets, are indexed from their length
+            let key = match key {
+                Literal::Int(idx) if idx < 0 && obj.hasattr("__len__")? => {
+                    Literal::Int(idx + obj.len()? as isize)
+                }
+                key => key,
+            };
+            Literal::downcast(py, obj.get_item(key.into_py(py))?)
+        }
+        _ => Err(PyErr::new::<PyTypeError, _>(format!(
+            "Cannot access index '{:?}' on non-object",
+            base
+        ))),
+    }
+}
+
+/// The position of a python index in a sequence, negative indexes are
+/// counted from the end.
+fn sequence_index(idx: isize, len: usize) -> PyResult<usize> {
+    let real_index = if idx >= 0 { idx } else { len as isize + idx };
+    if real_index < 0 || real_index >= len as isize {
+        Err(PyIndexError::new_err(format!("Index out of range {}", idx)))
+    } else {
+        Ok(real_index as usize)
+    }
+}
+
+/// The slice of a list or a string, python objects are sliced by their
+/// `__getitem__` method.
+fn eval_slice(py: Python<'_>, base: Literal, slice: &Bound<'_, PySlice>) -> PyResult<Literal> {
+    let positions = |len: usize| -> PyResult<Vec<usize>> {
+        let indices = slice.indices(len as isize)?;
+        Ok((0..indices.slicelength)
+            .map(|n| (indices.start + n as isize * indices.step) as usize)
+            .collect())
+    };
+    match base {
+        Literal::List(lst) => Ok(Literal::List(
+            positions(lst.len())?
+                .into_iter()
+                .map(|i| lst[i].clone())
+                .collect(),
+        )),
+        Literal::Str(s) => {
+            let chars = s.chars().collect::<Vec<_>>();
+            Ok(Literal::Str(
+                positions(chars.len())?
+                    .into_iter()
+                    .map(|i| chars[i])
+                    .collect(),
+            ))
+        }
+        Literal::Object(o) => Literal::downcast(py, o.obj().bind(py).get_item(slice)?),
+        _ => Err(PyTypeError::new_err(format!(
+            "Cannot slice {:?}, it is not a sequence",
+            base
+        ))),
+    }
+}
+
+/// The missing keys, items and attributes are None for the null-safe
+/// accesses, other errors are raised.
+fn missing_as_none(py: Python<'_>, err: PyErr) -> PyResult<Literal> {
+    if err.is_instance_of::<PyKeyError>(py)
+        || err.is_instance_of::<PyIndexError>(py)
+        || err.is_instance_of::<PyAttributeError>(py)
+    {
+        Ok(Literal::None(()))
+    } else {
+        Err(err)
+    }
+}
+
+/// Evaluate the body of the first arm matching the value, an empty string if
+/// no arm matches.
+fn eval_match<'py>(
     py: Python<'py>,
-    expression: &str,
+    value: Literal,
+    arms: &'py [MatchArm],
     catalog: &XCatalog,
     context: &mut RenderContext,
-) -> Result<Literal, PyErr> {
-    info!(
-        "Evaluating expression {}...",
-        &expression[..min(expression.len(), 24)]
+) -> PyResult<Literal> {
+    for arm in arms {
+        for pattern in arm.patterns.iter() {
+            let capture = match pattern {
+                MatchPattern::Wildcard => None,
+                MatchPattern::Capture(ident) => Some(ident),
+                // values of different types don't match
+                MatchPattern::Value(literal) => {
+                    if !eval_raw_eq(value.clone(), literal.clone(), "==".to_string())
+                        .unwrap_or(false)
+                    {
+                        continue;
+                    }
+                    None
+                }
+            };
+            if let Some(ident) = capture {
+                context.insert(LiteralKey::Str(ident.clone()), value.clone());
+            }
+            let res = eval_match_arm(py, arm, catalog, context);
+            if capture.is_some() {
+                context.pop();
+            }
+            if let Some(res) = res.transpose() {
+                return res;
+            }
+        }
+    }
+    Ok(Literal::Str("".to_string()))
+}
+
+/// Evaluate the body of the arm, None if its guard is false.
+fn eval_match_arm<'py>(
+    py: Python<'py>,
+    arm: &'py MatchArm,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+) -> PyResult<Option<Literal>> {
+    if let Some(guard) = &arm.guard {
+        if !eval_ast(py, guard, catalog, context)?.is_truthy() {
+            return Ok(None);
+        }
+    }
+    eval_ast(py, &arm.body, catalog, context).map(Some)
+}
+
+/// Render the for statement into the output, iteration by iteration.
+///
+/// The `loop` variable describes the iteration, the else branch is
+/// rendered if there is no item.
+fn write_for<'py>(
+    py: Python<'py>,
+    statement: &'py AST,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    out: &mut dyn fmt::Write,
+) -> PyResult<()> {
+    let AST::ForStatement {
+        idents,
+        iterable,
+        body,
+        else_branch,
+        ..
+    } = statement
+    else {
+        unreachable!("write_for is only called with for statements")
+    };
+    let iter_lit = eval_ast(py, iterable, catalog, context)?;
+    // the `loop` variable is only built if the body uses it, its length
+    // and its last item are not computed otherwise
+    let uses_loop = body.references("loop");
+    let (items, length) = for_items(py, idents, iter_lit, uses_loop)?;
+    let mut items = items.peekable();
+    let loop_key = LiteralKey::Str("loop".to_string());
+    let parent = context.get(&loop_key).cloned();
+    let mut index0 = 0;
+    while let Some(item) = items.next() {
+        let item = item?;
+        bind_for_targets(py, idents, item, context)?;
+        if uses_loop {
+            let is_last = items.peek().is_none();
+            let meta = loop_meta(index0, is_last, length, parent.clone());
+            context.insert_current(loop_key.clone(), meta);
+        }
+        let res = eval_ast(py, body, catalog, context)
+            .and_then(|item| item.write_html(py, catalog, context, out));
+        context.pop();
+        res?;
+        index0 += 1;
+    }
+    match else_branch {
+        Some(else_branch) if index0 == 0 => {
+            eval_ast(py, else_branch, catalog, context)?.write_html(py, catalog, context, out)
+        }
+        _ => Ok(()),
+    }
+}
+
+type ForItems<'py> = Box<dyn Iterator<Item = PyResult<Literal>> + 'py>;
+
+/// The items of the iterable of a for statement, with their number if it is
+/// known without consuming the iterable, the length of python iterables is
+/// only computed if `with_length` is set.
+///
+/// Dicts are iterated by keys, or by (key, value) pairs when there are two
+/// targets, python iterables are consumed lazily.
+fn for_items<'py>(
+    py: Python<'py>,
+    idents: &[String],
+    iter_lit: Literal,
+    with_length: bool,
+) -> PyResult<(ForItems<'py>, Option<usize>)> {
+    match iter_lit {
+        Literal::List(items) => {
+            let length = items.len();
+            Ok((Box::new(items.into_iter().map(Ok)), Some(length)))
+        }
+        Literal::Dict(map) if idents.len() == 2 => {
+            let length = map.len();
+            let items = map
+                .into_iter()
+                .map(|(k, v)| Ok(Literal::List(vec![k.into(), v])));
+            Ok((Box::new(items), Some(length)))
+        }
+        Literal::Dict(map) => {
+            let length = map.len();
+            Ok((
+                Box::new(map.into_keys().map(|k| Ok(k.into()))),
+                Some(length),
+            ))
+        }
+        Literal::Str(s) => {
+            let chars = s
+                .chars()
+                .map(|c| Ok(Literal::Str(c.to_string())))
+                .collect::<Vec<_>>();
+            let length = chars.len();
+            Ok((Box::new(chars.into_iter()), Some(length)))
+        }
+        Literal::Object(o) => {
+            let obj = o.obj().bind(py).clone();
+            let iter = match obj.try_iter() {
+                Ok(iter) => iter,
+                Err(_) => {
+                    return Err(PyTypeError::new_err(format!(
+                        "{} {:?} is not iterable",
+                        idents.join(", "),
+                        Literal::Object(o)
+                    )))
+                }
+            };
+            // the length of a generator is unknown until it is consumed
+            let length = if with_length && obj.hasattr("__len__")? {
+                Some(obj.len()?)
+            } else {
+                None
+            };
+            let items = iter.map(move |item| Literal::downcast(py, item?));
+            Ok((Box::new(items), length))
+        }
+        _ => Err(PyTypeError::new_err(format!(
+            "{} {:?} is not iterable",
+            idents.join(", "),
+            iter_lit
+        ))),
+    }
+}
+
+/// The `loop` variable of an iteration, `length` is None if the iterable
+/// is not sized.
+fn loop_meta(
+    index0: usize,
+    is_last: bool,
+    length: Option<usize>,
+    parent: Option<Literal>,
+) -> Literal {
+    let mut meta = IndexMap::new();
+    let mut set = |key: &str, value: Literal| {
+        meta.insert(LiteralKey::Str(key.to_string()), value);
+    };
+    set("index", Literal::Int(index0 as isize + 1));
+    set("index0", Literal::Int(index0 as isize));
+    set("first", Literal::Bool(index0 == 0));
+    set("last", Literal::Bool(is_last));
+    set("even", Literal::Bool(!index0.is_multiple_of(2)));
+    set("odd", Literal::Bool(index0.is_multiple_of(2)));
+    set(
+        "length",
+        length.map_or(Literal::None(()), |l| Literal::Int(l as isize)),
     );
-    let token = tokenize(expression)?;
-    match token {
-        ExpressionToken::Noop => Ok(Literal::Str("".to_string())),
-        _ => {
-            let ast = parse(&[token], 0)?;
-            eval_ast(py, &ast, catalog, context)
+    set("parent", parent.unwrap_or(Literal::None(())));
+    Literal::Dict(meta)
+}
+
+/// Bind the item to the targets of the for statement in a new frame,
+/// the item is unpacked when there are many targets.
+fn bind_for_targets(
+    py: Python<'_>,
+    idents: &[String],
+    item: Literal,
+    context: &mut RenderContext,
+) -> PyResult<()> {
+    if let [ident] = idents {
+        context.insert(LiteralKey::Str(ident.clone()), item);
+        return Ok(());
+    }
+    let expected = idents.len();
+    let values = match item {
+        Literal::List(values) => values,
+        Literal::Str(s) => s.chars().map(|c| Literal::Str(c.to_string())).collect(),
+        Literal::Object(o) => {
+            let mut values = Vec::new();
+            for value in o.obj().bind(py).try_iter()? {
+                values.push(Literal::downcast(py, value?)?);
+                if values.len() > expected {
+                    break;
+                }
+            }
+            values
+        }
+        other => {
+            return Err(PyTypeError::new_err(format!(
+                "cannot unpack non-iterable {:?}",
+                other
+            )))
+        }
+    };
+    if values.len() < expected {
+        return Err(PyValueError::new_err(format!(
+            "not enough values to unpack (expected {}, got {})",
+            expected,
+            values.len()
+        )));
+    }
+    if values.len() > expected {
+        return Err(PyValueError::new_err(format!(
+            "too many values to unpack (expected {})",
+            expected
+        )));
+    }
+    let mut values = idents.iter().zip(values);
+    if let Some((ident, value)) = values.next() {
+        context.insert(LiteralKey::Str(ident.clone()), value);
+    }
+    for (ident, value) in values {
+        context.insert_current(LiteralKey::Str(ident.clone()), value);
+    }
+    Ok(())
+}
+
+/// Render the expression into the output.
+///
+/// The for statements are rendered while iterating, other expressions are
+/// evaluated, then rendered.
+pub fn write_expression<'py>(
+    py: Python<'py>,
+    expression: &XExpression,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    out: &mut dyn fmt::Write,
+) -> PyResult<()> {
+    match expression.compiled()? {
+        Some(ast @ AST::ForStatement { span, .. }) => {
+            write_for(py, ast, catalog, context, out).map_err(|err| span.locate(py, err))
         }
+        Some(ast) => eval_ast(py, ast, catalog, context)?.write_html(py, catalog, context, out),
+        None => Ok(()),
+    }
+}
+
+pub fn eval_expression<'py>(
+    py: Python<'py>,
+    expression: &XExpression,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+) -> Result<Literal, PyErr> {
+    let raw = expression.expression();
+    info!("Evaluating expression {}...", &raw[..min(raw.len(), 24)]);
+    match expression.compiled()? {
+        Some(ast) => eval_ast(py, ast, catalog, context),
+        None => Ok(Literal::Str("".to_string())),
     }
 }
@@ -1,26 +1,56 @@
 use crate::context::Literal;
 use crate::expression::tokens::{Operator, UnaryOperator};
+use crate::serialize::{Decode, Decoder, Encode, Encoder};
+use crate::span::Span;
+use pyo3::PyResult;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
 
 #[derive(Debug, Clone)]
 pub enum AST {
-    Variable(String),
+    Variable(String, Span),
     Literal(Literal),
+    /// The value converted to a string, for the f-strings.
+    Format(Box<AST>),
+    List(Vec<AST>),
+    Dict(Vec<(AST, AST)>, Span),
+    /// A python slice, used as an index.
+    Slice {
+        start: Option<Box<AST>>,
+        stop: Option<Box<AST>>,
+        step: Option<Box<AST>>,
+    },
     Unary {
         op: UnaryOperator,
         expr: Box<AST>,
+        span: Span,
     },
     Binary {
         left: Box<AST>,
         op: Operator,
         right: Box<AST>,
+        span: Span,
     },
-    FieldAccess(Box<AST>, String),
-    IndexAccess(Box<AST>, Box<AST>),
+    FieldAccess(Box<AST>, String, Span),
+    IndexAccess(Box<AST>, Box<AST>, Span),
+    /// `obj?.field`, None if obj is None or has no such field.
+    OptionalFieldAccess(Box<AST>, String, Span),
+    /// `obj?[index]`, None if obj is None or has no such item.
+    OptionalIndexAccess(Box<AST>, Box<AST>, Span),
     CallAccess {
         left: Box<AST>,
         args: Vec<AST>,
         kwargs: HashMap<String, AST>,
+        span: Span,
+    },
+    /// `value | name(args)`, the filter `name` called with the value as
+    /// first argument.
+    Pipe {
+        name: String,
+        args: Vec<AST>,
+        kwargs: HashMap<String, AST>,
+        span: Span,
     },
     IfStatement {
         condition: Box<AST>,
@@ -28,12 +58,474 @@ pub enum AST {
         else_branch: Option<Box<AST>>,
     },
     ForStatement {
-        ident: String,
+        idents: Vec<String>,
         iterable: Box<AST>,
         body: Box<AST>,
+        else_branch: Option<Box<AST>>,
+        span: Span,
     },
     LetStatement {
         ident: String,
         expr: Box<AST>,
     },
+    Match {
+        subject: Box<AST>,
+        arms: Vec<MatchArm>,
+    },
+    /// An arrow function, the body is shared with the functions created.
+    Lambda {
+        params: Vec<String>,
+        body: Arc<AST>,
+    },
+}
+
+/// A pattern of a match arm.
+#[derive(Debug, Clone)]
+pub enum MatchPattern {
+    /// `_`, matches any value.
+    Wildcard,
+    /// A name, matches any value and binds it in the arm.
+    Capture(String),
+    /// A literal, matches the equal values.
+    Value(Literal),
+}
+
+#[derive(Debug, Clone)]
+pub struct MatchArm {
+    pub patterns: Vec<MatchPattern>,
+    pub guard: Option<AST>,
+    pub body: AST,
+}
+
+impl AST {
+    /// The position of the node in its template, for error reporting.
+    pub fn span(&self) -> Option<&Span> {
+        match self {
+            AST::Variable(_, span)
+            | AST::Unary { span, .. }
+            | AST::Binary { span, .. }
+            | AST::FieldAccess(_, _, span)
+            | AST::IndexAccess(_, _, span)
+            | AST::OptionalFieldAccess(_, _, span)
+            | AST::OptionalIndexAccess(_, _, span)
+            | AST::CallAccess { span, .. }
+            | AST::Pipe { span, .. }
+            | AST::ForStatement { span, .. }
+            | AST::Dict(_, span) => Some(span),
+            AST::Literal(_)
+            | AST::Format(_)
+            | AST::List(_)
+            | AST::Slice { .. }
+            | AST::IfStatement { .. }
+            | AST::LetStatement { .. }
+            | AST::Match { .. }
+            | AST::Lambda { .. } => None,
+        }
+    }
+}
+
+impl AST {
+    /// True if the expression, or the markup it contains, references the
+    /// variable `name`.
+    pub fn references(&self, name: &str) -> bool {
+        fn any<'a>(asts: impl IntoIterator<Item = &'a AST>, name: &str) -> bool {
+            asts.into_iter().any(|ast| ast.references(name))
+        }
+        match self {
+            AST::Variable(ident, _) => ident == name,
+            AST::Literal(Literal::XNode(node)) => node.references(name),
+            AST::Literal(_) => false,
+            AST::Format(expr) | AST::Unary { expr, .. } | AST::FieldAccess(expr, _, _) => {
+                expr.references(name)
+            }
+            AST::OptionalFieldAccess(expr, _, _) | AST::LetStatement { expr, .. } => {
+                expr.references(name)
+            }
+            AST::List(items) => any(items, name),
+            AST::Dict(items, _) => any(items.iter().flat_map(|(k, v)| [k, v]), name),
+            AST::Slice { start, stop, step } => any(
+                [start, stop, step]
+                    .into_iter()
+                    .flatten()
+                    .map(|b| b.as_ref()),
+                name,
+            ),
+            AST::Binary { left, right, .. }
+            | AST::IndexAccess(left, right, _)
+            | AST::OptionalIndexAccess(left, right, _) => {
+                left.references(name) || right.references(name)
+            }
+            AST::CallAccess {
+                left, args, kwargs, ..
+            } => left.references(name) || any(args.iter().chain(kwargs.values()), name),
+            AST::Pipe { args, kwargs, .. } => any(args.iter().chain(kwargs.values()), name),
+            AST::IfStatement {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                condition.references(name)
+                    || then_branch.references(name)
+                    || else_branch.as_ref().is_some_and(|e| e.references(name))
+            }
+            AST::ForStatement {
+                iterable,
+                body,
+                else_branch,
+                ..
+            } => {
+                iterable.references(name)
+                    || body.references(name)
+                    || else_branch.as_ref().is_some_and(|e| e.references(name))
+            }
+            AST::Match { subject, arms } => {
+                subject.references(name)
+                    || arms.iter().any(|arm| {
+                        arm.body.references(name)
+                            || arm.guard.as_ref().is_some_and(|g| g.references(name))
+                    })
+            }
+            AST::Lambda { body, .. } => body.references(name),
+        }
+    }
+}
+
+/// The AST of an expression, compiled on its first evaluation, and shared
+/// by all the clones of the expression.
+///
+/// An empty expression is compiled to `None`.
+#[derive(Clone, Default)]
+pub struct CompiledAST(Arc<OnceLock<Option<AST>>>);
+
+impl CompiledAST {
+    pub fn get_or_compile<F>(&self, compile: F) -> PyResult<Option<&AST>>
+    where
+        F: FnOnce() -> PyResult<Option<AST>>,
+    {
+        if let Some(ast) = self.0.get() {
+            return Ok(ast.as_ref());
+        }
+        let ast = compile()?;
+        Ok(self.0.get_or_init(|| ast).as_ref())
+    }
+}
+
+impl fmt::Debug for CompiledAST {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.get() {
+            Some(_) => write!(f, "compiled"),
+            None => write!(f, "not compiled"),
+        }
+    }
+}
+
+/// The compiled AST is derived from the expression, it is not part of its
+/// identity.
+impl PartialEq for CompiledAST {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// The compiled AST is encoded with the expression, in order to not compile
+/// the expression again once decoded.
+impl Encode for CompiledAST {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        match self.0.get() {
+            None => {
+                enc.write_u8(0);
+                Ok(())
+            }
+            Some(ast) => {
+                enc.write_u8(1);
+                ast.encode(enc)
+            }
+        }
+    }
+}
+
+impl Decode for CompiledAST {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        match dec.read_u8()? {
+            0 => Ok(CompiledAST::default()),
+            1 => Ok(CompiledAST(Arc::new(OnceLock::from(
+                Option::<AST>::decode(dec)?,
+            )))),
+            tag => Err(dec.invalid("compiled expression", tag)),
+        }
+    }
+}
+
+impl Encode for AST {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        match self {
+            AST::Variable(name, span) => {
+                enc.write_u8(0);
+                name.encode(enc)?;
+                span.encode(enc)
+            }
+            AST::Literal(value) => {
+                enc.write_u8(1);
+                value.encode(enc)
+            }
+            AST::Format(expr) => {
+                enc.write_u8(2);
+                expr.encode(enc)
+            }
+            AST::List(items) => {
+                enc.write_u8(3);
+                items.encode(enc)
+            }
+            AST::Dict(items, span) => {
+                enc.write_u8(4);
+                items.encode(enc)?;
+                span.encode(enc)
+            }
+            AST::Slice { start, stop, step } => {
+                enc.write_u8(5);
+                start.encode(enc)?;
+                stop.encode(enc)?;
+                step.encode(enc)
+            }
+            AST::Unary { op, expr, span } => {
+                enc.write_u8(6);
+                op.encode(enc)?;
+                expr.encode(enc)?;
+                span.encode(enc)
+            }
+            AST::Binary {
+                left,
+                op,
+                right,
+                span,
+            } => {
+                enc.write_u8(7);
+                left.encode(enc)?;
+                op.encode(enc)?;
+                right.encode(enc)?;
+                span.encode(enc)
+            }
+            AST::FieldAccess(obj, field, span) => {
+                enc.write_u8(8);
+                obj.encode(enc)?;
+                field.encode(enc)?;
+                span.encode(enc)
+            }
+            AST::IndexAccess(obj, index, span) => {
+                enc.write_u8(9);
+                obj.encode(enc)?;
+                index.encode(enc)?;
+                span.encode(enc)
+            }
+            AST::OptionalFieldAccess(obj, field, span) => {
+                enc.write_u8(10);
+                obj.encode(enc)?;
+                field.encode(enc)?;
+                span.encode(enc)
+            }
+            AST::OptionalIndexAccess(obj, index, span) => {
+                enc.write_u8(11);
+                obj.encode(enc)?;
+                index.encode(enc)?;
+                span.encode(enc)
+            }
+            AST::CallAccess {
+                left,
+                args,
+                kwargs,
+                span,
+            } => {
+                enc.write_u8(12);
+                left.encode(enc)?;
+                args.encode(enc)?;
+                kwargs.encode(enc)?;
+                span.encode(enc)
+            }
+            AST::IfStatement {
+                condition,
+                then_branch,
+                else_branch,
+            } => {
+                enc.write_u8(13);
+                condition.encode(enc)?;
+                then_branch.encode(enc)?;
+                else_branch.encode(enc)
+            }
+            AST::ForStatement {
+                idents,
+                iterable,
+                body,
+                else_branch,
+                span,
+            } => {
+                enc.write_u8(14);
+                idents.encode(enc)?;
+                iterable.encode(enc)?;
+                body.encode(enc)?;
+                else_branch.encode(enc)?;
+                span.encode(enc)
+            }
+            AST::LetStatement { ident, expr } => {
+                enc.write_u8(15);
+                ident.encode(enc)?;
+                expr.encode(enc)
+            }
+            AST::Match { subject, arms } => {
+                enc.write_u8(16);
+                subject.encode(enc)?;
+                arms.encode(enc)
+            }
+            AST::Lambda { params, body } => {
+                enc.write_u8(17);
+                params.encode(enc)?;
+                body.encode(enc)
+            }
+            AST::Pipe {
+                name,
+                args,
+                kwargs,
+                span,
+            } => {
+                enc.write_u8(18);
+                name.encode(enc)?;
+                args.encode(enc)?;
+                kwargs.encode(enc)?;
+                span.encode(enc)
+            }
+        }
+    }
+}
+
+impl Decode for AST {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        let ast = match dec.read_u8()? {
+            0 => AST::Variable(Decode::decode(dec)?, Decode::decode(dec)?),
+            1 => AST::Literal(Decode::decode(dec)?),
+            2 => AST::Format(Decode::decode(dec)?),
+            3 => AST::List(Decode::decode(dec)?),
+            4 => AST::Dict(Decode::decode(dec)?, Decode::decode(dec)?),
+            5 => AST::Slice {
+                start: Decode::decode(dec)?,
+                stop: Decode::decode(dec)?,
+                step: Decode::decode(dec)?,
+            },
+            6 => AST::Unary {
+                op: Decode::decode(dec)?,
+                expr: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+            },
+            7 => AST::Binary {
+                left: Decode::decode(dec)?,
+                op: Decode::decode(dec)?,
+                right: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+            },
+            8 => AST::FieldAccess(
+                Decode::decode(dec)?,
+                Decode::decode(dec)?,
+                Decode::decode(dec)?,
+            ),
+            9 => AST::IndexAccess(
+                Decode::decode(dec)?,
+                Decode::decode(dec)?,
+                Decode::decode(dec)?,
+            ),
+            10 => AST::OptionalFieldAccess(
+                Decode::decode(dec)?,
+                Decode::decode(dec)?,
+                Decode::decode(dec)?,
+            ),
+            11 => AST::OptionalIndexAccess(
+                Decode::decode(dec)?,
+                Decode::decode(dec)?,
+                Decode::decode(dec)?,
+            ),
+            12 => AST::CallAccess {
+                left: Decode::decode(dec)?,
+                args: Decode::decode(dec)?,
+                kwargs: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+            },
+            13 => AST::IfStatement {
+                condition: Decode::decode(dec)?,
+                then_branch: Decode::decode(dec)?,
+                else_branch: Decode::decode(dec)?,
+            },
+            14 => AST::ForStatement {
+                idents: Decode::decode(dec)?,
+                iterable: Decode::decode(dec)?,
+                body: Decode::decode(dec)?,
+                else_branch: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+            },
+            15 => AST::LetStatement {
+                ident: Decode::decode(dec)?,
+                expr: Decode::decode(dec)?,
+            },
+            16 => AST::Match {
+                subject: Decode::decode(dec)?,
+                arms: Decode::decode(dec)?,
+            },
+            17 => AST::Lambda {
+                params: Decode::decode(dec)?,
+                body: Decode::decode(dec)?,
+            },
+            18 => AST::Pipe {
+                name: Decode::decode(dec)?,
+                args: Decode::decode(dec)?,
+                kwargs: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+            },
+            tag => return Err(dec.invalid("expression", tag)),
+        };
+        Ok(ast)
+    }
+}
+
+impl Encode for MatchArm {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        self.patterns.encode(enc)?;
+        self.guard.encode(enc)?;
+        self.body.encode(enc)
+    }
+}
+
+impl Decode for MatchArm {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        Ok(MatchArm {
+            patterns: Decode::decode(dec)?,
+            guard: Decode::decode(dec)?,
+            body: Decode::decode(dec)?,
+        })
+    }
+}
+
+impl Encode for MatchPattern {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        match self {
+            MatchPattern::Wildcard => {
+                enc.write_u8(0);
+                Ok(())
+            }
+            MatchPattern::Capture(name) => {
+                enc.write_u8(1);
+                name.encode(enc)
+            }
+            MatchPattern::Value(value) => {
+                enc.write_u8(2);
+                value.encode(enc)
+            }
+        }
+    }
+}
+
+impl Decode for MatchPattern {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        match dec.read_u8()? {
+            0 => Ok(MatchPattern::Wildcard),
+            1 => Ok(MatchPattern::Capture(Decode::decode(dec)?)),
+            2 => Ok(MatchPattern::Value(Decode::decode(dec)?)),
+            tag => Err(dec.invalid("match pattern", tag)),
+        }
+    }
 }
@@ -1,21 +1,37 @@
 use std::slice::Iter;
+use std::sync::Arc;
 
 use pyo3::exceptions::PySyntaxError;
 use pyo3::prelude::*;
+use pyo3::types::PyInt;
 
 use crate::context::Literal;
 use crate::expression::{
-    ast::model::AST,
-    tokens::{ExpressionToken, PostfixOp},
+    ast::model::{MatchArm, MatchPattern, AST},
+    parser::tokenize,
+    tokens::{ExpressionToken, Operator, PostfixOp, UnaryOperator},
 };
+use crate::span::Span;
+
+fn token_to_pattern(tok: &ExpressionToken) -> Result<MatchPattern, PyErr> {
+    match tok {
+        ExpressionToken::Ident(ident, _) if ident == "_" => Ok(MatchPattern::Wildcard),
+        ExpressionToken::Ident(ident, _) => Ok(MatchPattern::Capture(ident.clone())),
+        tok => match token_to_ast(tok, 0)? {
+            AST::Literal(literal) => Ok(MatchPattern::Value(literal)),
+            _ => Err(PySyntaxError::new_err(format!("Invalid pattern {}", tok))),
+        },
+    }
+}
 
 pub fn token_to_ast(tok: &ExpressionToken, min_prec: u8) -> Result<AST, PyErr> {
     let ast = match tok {
-        ExpressionToken::UnaryExpression { op, expr } => {
+        ExpressionToken::UnaryExpression { op, expr, span } => {
             let inner = token_to_ast(expr, min_prec)?;
             Ok(AST::Unary {
                 op: op.clone(),
                 expr: Box::new(inner),
+                span: span.clone(),
             })
         }
         ExpressionToken::BinaryExpression(ex) => {
@@ -23,12 +39,65 @@ pub fn token_to_ast(tok: &ExpressionToken, min_prec: u8) -> Result<AST, PyErr> {
             Ok(parse(&mut exp, min_prec)?)
         }
         ExpressionToken::String(s) => Ok(AST::Literal(Literal::Str(s.to_string()))),
+        // f"a{b}" is compiled to "a" + format(b)
+        ExpressionToken::FString(parts, span) => {
+            let mut parts = parts.iter().map(|part| -> Result<AST, PyErr> {
+                match part {
+                    ExpressionToken::String(s) => Ok(AST::Literal(Literal::Str(s.to_string()))),
+                    expr => Ok(AST::Format(Box::new(token_to_ast(expr, 0)?))),
+                }
+            });
+            let mut ast = parts
+                .next()
+                .unwrap_or_else(|| Ok(AST::Literal(Literal::Str("".to_string()))))?;
+            for part in parts {
+                ast = AST::Binary {
+                    left: Box::new(ast),
+                    op: Operator::Add,
+                    right: Box::new(part?),
+                    span: span.clone(),
+                };
+            }
+            Ok(ast)
+        }
         // ExpressionToken::Uuid(s) => Ok(AST::Literal(Literal::Uuid(s.to_string()))),
         ExpressionToken::Boolean(b) => Ok(AST::Literal(Literal::Bool(b.clone()))),
+        ExpressionToken::None => Ok(AST::Literal(Literal::None(()))),
+        ExpressionToken::List(items) => Ok(AST::List(
+            items
+                .iter()
+                .map(|item| token_to_ast(item, 0))
+                .collect::<Result<_, _>>()?,
+        )),
+        ExpressionToken::Slice { start, stop, step } => {
+            let bound = |b: &Option<Box<ExpressionToken>>| -> Result<_, PyErr> {
+                match b {
+                    Some(b) => Ok(Some(Box::new(token_to_ast(b, 0)?))),
+                    None => Ok(None),
+                }
+            };
+            Ok(AST::Slice {
+                start: bound(start)?,
+                stop: bound(stop)?,
+                step: bound(step)?,
+            })
+        }
+        ExpressionToken::Dict(items, span) => Ok(AST::Dict(
+            items
+                .iter()
+                .map(|(k, v)| Ok((token_to_ast(k, 0)?, token_to_ast(v, 0)?)))
+                .collect::<Result<_, PyErr>>()?,
+            span.clone(),
+        )),
         ExpressionToken::Integer(n) => Ok(AST::Literal(Literal::Int(n.clone()))),
-        ExpressionToken::Ident(ident) => Ok(AST::Variable(ident.to_string())),
+        ExpressionToken::BigInteger(digits) => Python::with_gil(|py| {
+            let value = py.get_type::<PyInt>().call1((digits.as_str(),))?;
+            Ok(AST::Literal(Literal::downcast(py, value)?))
+        }),
+        ExpressionToken::Float(n) => Ok(AST::Literal(Literal::Float(*n))),
+        ExpressionToken::Ident(ident, span) => Ok(AST::Variable(ident.to_string(), span.clone())),
         ExpressionToken::XNode(n) => Ok(AST::Literal(Literal::XNode(n.clone()))),
-        ExpressionToken::PostfixOp(op) => {
+        ExpressionToken::PostfixOp(op, _) => {
             // the ast is handled by the
             error!("Should never enter postfix op code : {:?}", op);
             Ok(AST::Literal(Literal::Str("".to_string())))
@@ -46,19 +115,50 @@ pub fn token_to_ast(tok: &ExpressionToken, min_prec: u8) -> Result<AST, PyErr> {
             },
         }),
         ExpressionToken::ForExpression {
-            ident,
+            idents,
             iterable,
             body,
+            else_branch,
+            span,
         } => Ok(AST::ForStatement {
-            ident: ident.clone(),
+            idents: idents.clone(),
             iterable: token_to_ast(iterable, min_prec).map(|x| Box::new(x))?,
             body: token_to_ast(body, min_prec).map(|x| Box::new(x))?,
+            else_branch: match else_branch {
+                Some(token) => Some(token_to_ast(token, min_prec).map(Box::new)?),
+                None => None,
+            },
+            span: span.clone(),
         }),
 
         ExpressionToken::LetExpression { ident, expr } => Ok(AST::LetStatement {
             ident: ident.clone(),
             expr: token_to_ast(expr, min_prec).map(|x| Box::new(x))?,
         }),
+        ExpressionToken::Lambda { params, body } => Ok(AST::Lambda {
+            params: params.clone(),
+            body: Arc::new(token_to_ast(body, 0)?),
+        }),
+        ExpressionToken::MatchExpression { subject, arms } => Ok(AST::Match {
+            subject: Box::new(token_to_ast(subject, 0)?),
+            arms: arms
+                .iter()
+                .map(|arm| {
+                    Ok(MatchArm {
+                        patterns: arm
+                            .patterns
+                            .iter()
+                            .map(token_to_pattern)
+                            .collect::<Result<_, PyErr>>()?,
+                        guard: match &arm.guard {
+                            Some(guard) => Some(token_to_ast(guard, 0)?),
+                            None => None,
+                        },
+                        body: token_to_ast(&arm.body, 0)?,
+                    })
+                })
+                .collect::<Result<_, PyErr>>()?,
+        }),
         // Comment produce a Noop
         ExpressionToken::Noop => Ok(AST::Literal(Literal::Str("".to_string()))),
         _ => Err(PySyntaxError::new_err(format!(
@@ -76,6 +176,20 @@ pub fn get_left(iter: &mut std::iter::Peekable<Iter<ExpressionToken>>) -> Result
             .ok_or(PySyntaxError::new_err("expected at least one token"))?;
         match tok {
             ExpressionToken::Noop => {}
+            // the power binds tighter than the unary minus, `-2 ** 2` is `-(2 ** 2)`
+            ExpressionToken::UnaryExpression {
+                op: UnaryOperator::Neg,
+                expr,
+                span,
+            } => {
+                let operand = token_to_ast(expr, 0)?;
+                let operand = parse_operators(iter, operand, Operator::Pow.precedence())?;
+                return Ok(AST::Unary {
+                    op: UnaryOperator::Neg,
+                    expr: Box::new(operand),
+                    span: span.clone(),
+                });
+            }
             _ => {
                 return token_to_ast(&tok, 0);
             }
@@ -87,35 +201,63 @@ fn get_next_token(
     iter: &mut std::iter::Peekable<Iter<ExpressionToken>>,
     min_prec: u8,
 ) -> Result<AST, PyErr> {
-    let mut left = get_left(iter)?;
+    let left = get_left(iter)?;
+    parse_operators(iter, left, min_prec)
+}
 
+/// Apply the operators following the left operand, while their precedence
+/// is at least `min_prec`.
+fn parse_operators(
+    iter: &mut std::iter::Peekable<Iter<ExpressionToken>>,
+    mut left: AST,
+    min_prec: u8,
+) -> Result<AST, PyErr> {
     while let Some(token) = iter.peek() {
         match token {
-            ExpressionToken::Operator(op) if op.precedence() >= min_prec => {
-                let op = if let Some(ExpressionToken::Operator(op)) = iter.next() {
-                    op.clone()
+            ExpressionToken::Operator(op, _) if op.precedence() >= min_prec => {
+                let (op, span) = if let Some(ExpressionToken::Operator(op, span)) = iter.next() {
+                    (op.clone(), span.clone())
                 } else {
                     break;
                 };
 
-                let right = get_next_token(iter, op.precedence() + 1)?;
+                let right_prec = if op.is_right_associative() {
+                    op.precedence()
+                } else {
+                    op.precedence() + 1
+                };
+                let right = get_next_token(iter, right_prec)?;
                 left = AST::Binary {
                     left: Box::new(left),
                     op,
                     right: Box::new(right),
+                    span,
                 };
             }
-            ExpressionToken::PostfixOp(_) => {
+            ExpressionToken::PostfixOp(_, _) => {
                 let op = iter.next().unwrap(); // safe to consume
                 match op {
-                    ExpressionToken::PostfixOp(PostfixOp::Field(f)) => {
-                        left = AST::FieldAccess(Box::new(left), f.clone())
+                    ExpressionToken::PostfixOp(PostfixOp::Field(f), span) => {
+                        left = AST::FieldAccess(Box::new(left), f.clone(), span.clone())
+                    }
+                    ExpressionToken::PostfixOp(PostfixOp::Index(i), span) => {
+                        left = AST::IndexAccess(
+                            Box::new(left),
+                            Box::new(token_to_ast(&i, min_prec)?),
+                            span.clone(),
+                        )
+                    }
+                    ExpressionToken::PostfixOp(PostfixOp::OptionalField(f), span) => {
+                        left = AST::OptionalFieldAccess(Box::new(left), f.clone(), span.clone())
                     }
-                    ExpressionToken::PostfixOp(PostfixOp::Index(i)) => {
-                        left =
-                            AST::IndexAccess(Box::new(left), Box::new(token_to_ast(&i, min_prec)?))
+                    ExpressionToken::PostfixOp(PostfixOp::OptionalIndex(i), span) => {
+                        left = AST::OptionalIndexAccess(
+                            Box::new(left),
+                            Box::new(token_to_ast(&i, min_prec)?),
+                            span.clone(),
+                        )
                     }
-                    ExpressionToken::PostfixOp(PostfixOp::Call { args, kwargs }) => {
+                    ExpressionToken::PostfixOp(PostfixOp::Call { args, kwargs }, span) => {
                         left = AST::CallAccess {
                             left: Box::new(left),
                             args: args
@@ -128,6 +270,24 @@ fn get_next_token(
                                     Ok((k.clone(), token_to_ast(&v, min_prec)?))
                                 })
                                 .collect::<Result<_, _>>()?,
+                            span: span.clone(),
+                        };
+                    }
+                    ExpressionToken::PostfixOp(PostfixOp::Pipe { name, args, kwargs }, span) => {
+                        let mut call_args = vec![left];
+                        for arg in args {
+                            call_args.push(token_to_ast(arg, min_prec)?);
+                        }
+                        left = AST::Pipe {
+                            name: name.clone(),
+                            args: call_args,
+                            kwargs: kwargs
+                                .iter()
+                                .map(|(k, v)| -> Result<(String, AST), PyErr> {
+                                    Ok((k.clone(), token_to_ast(v, min_prec)?))
+                                })
+                                .collect::<Result<_, _>>()?,
+                            span: span.clone(),
                         };
                     }
                     _ => unreachable!(),
@@ -148,3 +308,11 @@ pub fn parse(tokens: &[ExpressionToken], min_prec: u8) -> Result<AST, PyErr> {
     let mut iter = tokens.iter().peekable();
     get_next_token(&mut iter, min_prec)
 }
+
+/// Compile a raw expression to its AST, an empty expression is compiled to `None`.
+pub fn compile(raw: &str, span: &Span) -> Result<Option<AST>, PyErr> {
+    match tokenize(raw, span)? {
+        ExpressionToken::Noop => Ok(None),
+        token => parse(&[token], 0).map(Some),
+    }
+}
@@ -0,0 +1,455 @@
+use std::collections::HashMap;
+
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+
+use crate::catalog::XCatalog;
+use crate::context::{format_uuid, Literal, LiteralKey, PyObj, RenderContext, Truthy};
+use crate::expression::ast::eval::{eval_raw_lt, format_literal};
+use crate::markup::escape::{to_json, Escape};
+use crate::markup::tokens::ToHtml;
+
+pub(crate) type Filter = fn(Python<'_>, &XCatalog, &mut RenderContext, Args) -> PyResult<Literal>;
+
+/// The filters implemented in Rust, with the names of their parameters,
+/// the value filtered is the first positional argument.
+const FILTERS: &[(&str, &[&str], Filter)] = &[
+    ("upper", &[], upper),
+    ("lower", &[], lower),
+    ("title", &[], title),
+    ("trim", &["chars"], trim),
+    ("length", &[], length),
+    ("join", &["separator"], join),
+    ("default", &["default_value", "boolean"], default),
+    ("first", &[], first),
+    ("last", &[], last),
+    ("sort", &["reverse"], sort),
+    ("reverse", &[], reverse),
+    ("json", &[], json),
+    ("urlencode", &[], urlencode),
+];
+
+/// The functions implemented in Rust, they are not filters, and they are
+/// bound like the filters, the first positional argument is the value.
+const FUNCTIONS: &[(&str, &[&str], Filter)] = &[
+    ("range", &["stop", "step"], range),
+    ("provide", &["value"], provide),
+    ("inject", &["default"], inject),
+];
+
+/// Arguments of a filter call, bound to the parameters of the filter.
+pub(crate) struct Args {
+    name: &'static str,
+    pub(crate) value: Literal,
+    params: Vec<Option<Literal>>,
+}
+
+impl Args {
+    pub(crate) fn bind(
+        name: &'static str,
+        params: &[&str],
+        args: Vec<Literal>,
+        mut kwargs: HashMap<String, Literal>,
+    ) -> PyResult<Self> {
+        let mut args = args.into_iter();
+        let value = args.next().ok_or_else(|| {
+            PyTypeError::new_err(format!("{}() missing the value to filter", name))
+        })?;
+        let mut bound: Vec<Option<Literal>> = args.map(Some).collect();
+        if bound.len() > params.len() {
+            return Err(PyTypeError::new_err(format!(
+                "{}() takes {} positional arguments but {} were given",
+                name,
+                params.len() + 1,
+                bound.len() + 1
+            )));
+        }
+        bound.resize(params.len(), None);
+        for (param, slot) in params.iter().zip(bound.iter_mut()) {
+            if let Some(value) = kwargs.remove(*param) {
+                if slot.is_some() {
+                    return Err(PyTypeError::new_err(format!(
+                        "{}() got multiple values for argument '{}'",
+                        name, param
+                    )));
+                }
+                *slot = Some(value);
+            }
+        }
+        if let Some(key) = kwargs.keys().next() {
+            return Err(PyTypeError::new_err(format!(
+                "{}() got an unexpected keyword argument '{}'",
+                name, key
+            )));
+        }
+        Ok(Args {
+            name,
+            value,
+            params: bound,
+        })
+    }
+
+    pub(crate) fn param(&self, idx: usize) -> Option<&Literal> {
+        self.params[idx].as_ref()
+    }
+
+    pub(crate) fn type_error(&self, expected: &str) -> PyErr {
+        PyTypeError::new_err(format!(
+            "{}() expects {}, got {:?}",
+            self.name, expected, self.value
+        ))
+    }
+}
+
+/// True if a filter is implemented in Rust with that name.
+pub fn is_filter(name: &str) -> bool {
+    FILTERS.iter().any(|(n, _, _)| *n == name)
+}
+
+/// True if a filter or a function is implemented in Rust with that name.
+pub fn is_builtin(name: &str) -> bool {
+    is_filter(name) || FUNCTIONS.iter().any(|(n, _, _)| *n == name)
+}
+
+/// Call the filter, or the function, implemented in Rust, the value is the
+/// first argument.
+pub fn call_filter<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    name: &str,
+    args: Vec<Literal>,
+    kwargs: HashMap<String, Literal>,
+) -> PyResult<Literal> {
+    let (name, params, filter) = FILTERS
+        .iter()
+        .chain(FUNCTIONS)
+        .find(|(n, _, _)| *n == name)
+        .ok_or_else(|| PyErr::new::<pyo3::exceptions::PyKeyError, _>("Filter not found"))?;
+    let args = Args::bind(name, params, args, kwargs)?;
+    filter(py, catalog, context, args)
+}
+
+/// Apply the function to the string, the markup stays a markup.
+pub(crate) fn map_str(args: Args, f: impl FnOnce(&str) -> String) -> PyResult<Literal> {
+    match &args.value {
+        Literal::Str(s) => Ok(Literal::Str(f(s))),
+        Literal::Markup(s) => Ok(Literal::Markup(f(s))),
+        _ => Err(args.type_error("a string")),
+    }
+}
+
+/// The items of a list, or of any python iterable.
+fn to_list(py: Python<'_>, args: &Args) -> PyResult<Vec<Literal>> {
+    match &args.value {
+        Literal::List(items) => Ok(items.clone()),
+        Literal::Object(o) => {
+            let builtins = PyModule::import(py, "builtins")?;
+            match Literal::downcast(py, builtins.getattr("list")?.call1((o.obj(),))?)? {
+                Literal::List(items) => Ok(items),
+                _ => Err(args.type_error("an iterable")),
+            }
+        }
+        _ => Err(args.type_error("a list")),
+    }
+}
+
+pub(crate) fn upper(
+    _: Python<'_>,
+    _: &XCatalog,
+    _: &mut RenderContext,
+    args: Args,
+) -> PyResult<Literal> {
+    map_str(args, |s| s.to_uppercase())
+}
+
+pub(crate) fn lower(
+    _: Python<'_>,
+    _: &XCatalog,
+    _: &mut RenderContext,
+    args: Args,
+) -> PyResult<Literal> {
+    map_str(args, |s| s.to_lowercase())
+}
+
+/// Uppercase the first letter of the words, and lowercase the others,
+/// like the python `str.title` method.
+fn title(_: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    map_str(args, |s| {
+        let mut res = String::with_capacity(s.len());
+        let mut in_word = false;
+        for c in s.chars() {
+            if in_word {
+                res.extend(c.to_lowercase());
+            } else {
+                res.extend(c.to_uppercase());
+            }
+            in_word = c.is_alphabetic();
+        }
+        res
+    })
+}
+
+pub(crate) fn trim(
+    _: Python<'_>,
+    _: &XCatalog,
+    _: &mut RenderContext,
+    args: Args,
+) -> PyResult<Literal> {
+    match args.param(0).cloned() {
+        None | Some(Literal::None(())) => map_str(args, |s| s.trim().to_string()),
+        Some(Literal::Str(chars)) => {
+            map_str(args, |s| s.trim_matches(|c| chars.contains(c)).to_string())
+        }
+        Some(other) => Err(PyTypeError::new_err(format!(
+            "trim() expects chars to be a string, got {:?}",
+            other
+        ))),
+    }
+}
+
+pub(crate) fn length(
+    py: Python<'_>,
+    _: &XCatalog,
+    _: &mut RenderContext,
+    args: Args,
+) -> PyResult<Literal> {
+    let len = match &args.value {
+        Literal::Str(s) | Literal::Markup(s) => s.chars().count(),
+        Literal::List(items) => items.len(),
+        Literal::Dict(map) => map.len(),
+        Literal::Object(o) => o.obj().bind(py).len()?,
+        _ => return Err(args.type_error("a sized value")),
+    };
+    Ok(Literal::Int(len as isize))
+}
+
+/// Join the items, if the separator or an item is a markup, the other
+/// items are escaped, and the result is a markup.
+pub(crate) fn join(
+    py: Python<'_>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    args: Args,
+) -> PyResult<Literal> {
+    let items = to_list(py, &args)?;
+    let separator = args
+        .param(0)
+        .cloned()
+        .unwrap_or_else(|| Literal::Str("".to_string()));
+    let is_markup = std::iter::once(&separator)
+        .chain(items.iter())
+        .any(|item| matches!(item, Literal::Markup(_) | Literal::XNode(_)));
+    if is_markup {
+        let mut res = String::new();
+        for (idx, item) in items.iter().enumerate() {
+            if idx > 0 {
+                separator.write_html(py, catalog, context, &mut res)?;
+            }
+            item.write_html(py, catalog, context, &mut res)?;
+        }
+        Ok(Literal::Markup(res))
+    } else {
+        let separator = format_literal(py, catalog, context, &separator)?;
+        let texts = items
+            .iter()
+            .map(|item| format_literal(py, catalog, context, item))
+            .collect::<PyResult<Vec<_>>>()?;
+        Ok(Literal::Str(texts.join(&separator)))
+    }
+}
+
+/// The default value replaces None, or any falsy value if boolean is true.
+fn default(_: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    let boolean = args.param(1).map(|b| b.is_truthy()).unwrap_or(false);
+    let use_default = match &args.value {
+        Literal::None(()) => true,
+        value => boolean && !value.is_truthy(),
+    };
+    if use_default {
+        Ok(args
+            .param(0)
+            .cloned()
+            .unwrap_or_else(|| Literal::Str("".to_string())))
+    } else {
+        Ok(args.value)
+    }
+}
+
+fn first(py: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    match &args.value {
+        Literal::Str(s) => Ok(Literal::Str(s.chars().take(1).collect())),
+        _ => Ok(to_list(py, &args)?
+            .into_iter()
+            .next()
+            .unwrap_or(Literal::None(()))),
+    }
+}
+
+fn last(py: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    match &args.value {
+        Literal::Str(s) => Ok(Literal::Str(
+            s.chars().last().map(String::from).unwrap_or_default(),
+        )),
+        _ => Ok(to_list(py, &args)?.pop().unwrap_or(Literal::None(()))),
+    }
+}
+
+fn sort(py: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    let mut items = to_list(py, &args)?;
+    let mut error = None;
+    items.sort_by(|a, b| {
+        let mut lt = |x: &Literal, y: &Literal| match eval_raw_lt(x.clone(), y.clone()) {
+            Ok(lt) => lt,
+            Err(err) => {
+                error.get_or_insert(err);
+                false
+            }
+        };
+        if lt(a, b) {
+            std::cmp::Ordering::Less
+        } else if lt(b, a) {
+            std::cmp::Ordering::Greater
+        } else {
+            std::cmp::Ordering::Equal
+        }
+    });
+    if let Some(err) = error {
+        return Err(err);
+    }
+    if args.param(0).is_some_and(|r| r.is_truthy()) {
+        items.reverse();
+    }
+    Ok(Literal::List(items))
+}
+
+fn reverse(py: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    match &args.value {
+        Literal::Str(s) => Ok(Literal::Str(s.chars().rev().collect())),
+        _ => {
+            let mut items = to_list(py, &args)?;
+            items.reverse();
+            Ok(Literal::List(items))
+        }
+    }
+}
+
+/// Serialize the value to JSON, the document is safe to be rendered
+/// in a script tag.
+fn json(
+    py: Python<'_>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    args: Args,
+) -> PyResult<Literal> {
+    let json = to_json(py, catalog, context, &args.value)?;
+    Ok(Literal::Str(Escape::Script.escape(&json).into_owned()))
+}
+
+/// Percent encode a string, or encode a dict as a query string.
+fn urlencode(
+    py: Python<'_>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    args: Args,
+) -> PyResult<Literal> {
+    match &args.value {
+        Literal::Dict(map) => {
+            let items = map
+                .iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        LiteralKey::Int(i) => i.to_string(),
+                        LiteralKey::Str(s) => s.clone(),
+                        LiteralKey::Uuid(u) => format_uuid(u),
+                    };
+                    Ok((key, format_literal(py, catalog, context, v)?))
+                })
+                .collect::<PyResult<Vec<_>>>()?;
+            Ok(Literal::Str(
+                items
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", quote(k, true), quote(v, true)))
+                    .collect::<Vec<_>>()
+                    .join("&"),
+            ))
+        }
+        value => Ok(Literal::Str(quote(
+            &format_literal(py, catalog, context, value)?,
+            false,
+        ))),
+    }
+}
+
+/// The python range, `range(stop)` or `range(start, stop, step)`, it is not
+/// a list, the for statements iterate over it lazily.
+fn range(py: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    let mut bounds = vec![args.value.into_py(py)];
+    match (&args.params[0], &args.params[1]) {
+        (None, None) => {}
+        (Some(stop), None) => bounds.push(stop.into_py(py)),
+        (Some(stop), Some(step)) => bounds.extend([stop.into_py(py), step.into_py(py)]),
+        (None, Some(_)) => {
+            return Err(PyTypeError::new_err("range() expects a stop with a step"));
+        }
+    }
+    let builtins = PyModule::import(py, "builtins")?;
+    let range = builtins
+        .getattr("range")?
+        .call1(pyo3::types::PyTuple::new(py, bounds)?)?;
+    Ok(Literal::Object(PyObj::new(range.unbind())))
+}
+
+/// Percent encode the string, like `urllib.parse.quote`, or `quote_plus`
+/// for a query string.
+fn quote(value: &str, for_query: bool) -> String {
+    let mut res = String::with_capacity(value.len());
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                res.push(b as char)
+            }
+            b'/' if !for_query => res.push('/'),
+            b' ' if for_query => res.push('+'),
+            _ => res.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    res
+}
+
+/// Provide a value to the rest of the component, and to its descendants,
+/// `provide("theme", "dark")`.
+fn provide(
+    _: Python<'_>,
+    _: &XCatalog,
+    context: &mut RenderContext,
+    args: Args,
+) -> PyResult<Literal> {
+    let Literal::Str(name) = &args.value else {
+        return Err(args.type_error("a name"));
+    };
+    let value = args
+        .param(0)
+        .ok_or_else(|| PyTypeError::new_err("provide() missing the value to provide"))?;
+    context.provide(name.clone(), value.clone());
+    Ok(Literal::Str("".to_string()))
+}
+
+/// The value provided by a parent, `inject("theme", "light")`.
+fn inject(
+    _: Python<'_>,
+    _: &XCatalog,
+    context: &mut RenderContext,
+    args: Args,
+) -> PyResult<Literal> {
+    let Literal::Str(name) = &args.value else {
+        return Err(args.type_error("a name"));
+    };
+    match (context.inject(name), args.param(0)) {
+        (Some(value), _) | (None, Some(value)) => Ok(value.clone()),
+        (None, None) => Err(PyErr::new::<pyo3::exceptions::PyKeyError, _>(format!(
+            "{:?} has not been provided",
+            name
+        ))),
+    }
+}
@@ -7,6 +7,7 @@ use crate::{
         parser::tokenize,
     },
     markup::tokens::XNode,
+    span::Span,
 };
 
 #[pyclass]
@@ -101,8 +102,9 @@ fn extract_from_ast(ast: AST) -> PyResult<Vec<ExtractedMessage>> {
             left,
             args,
             kwargs: _,
+            span: _,
         } => match *left {
-            AST::FieldAccess(_, s) => match s.as_str() {
+            AST::FieldAccess(_, s, _) => match s.as_str() {
                 "gettext" => match args.first() {
                     Some(AST::Literal(Literal::Str(v))) => res.push(ExtractedMessage::new(
                         0,
@@ -278,15 +280,34 @@ fn extract_from_ast(ast: AST) -> PyResult<Vec<ExtractedMessage>> {
             }
         }
         AST::ForStatement {
-            ident: _,
+            idents: _,
             iterable: _,
             body,
+            else_branch,
+            span: _,
         } => {
             res.extend(extract_from_ast(*body.clone())?);
+            if let Some(else_branch) = else_branch {
+                res.extend(extract_from_ast(*else_branch.clone())?);
+            }
+        }
+        // the messages of the filtered values, `{i18n.gettext("a") | upper}`
+        AST::Pipe { args, .. } => {
+            for arg in args {
+                res.extend(extract_from_ast(arg)?);
+            }
         }
         AST::LetStatement { ident: _, expr } => {
             res.extend(extract_from_ast(*expr.clone())?);
         }
+        AST::Lambda { params: _, body } => {
+            res.extend(extract_from_ast((*body).clone())?);
+        }
+        AST::Match { subject: _, arms } => {
+            for arm in arms {
+                res.extend(extract_from_ast(arm.body)?);
+            }
+        }
         AST::Literal(Literal::XNode(XNode::Element(node))) => {
             for child in node.attrs().values() {
                 if let XNode::Expression(expr) = child {
@@ -335,7 +356,7 @@ fn extract_from_ast(ast: AST) -> PyResult<Vec<ExtractedMessage>> {
 
 #[pyfunction]
 pub(crate) fn extract_expr_i18n_messages(raw: &str) -> PyResult<Vec<ExtractedMessage>> {
-    let token = tokenize(raw)?;
+    let token = tokenize(raw, &Span::default())?;
     let ast = parse(&[token], 0)?;
 
     extract_from_ast(ast)
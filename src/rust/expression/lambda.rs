@@ -0,0 +1,131 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyTuple};
+
+use crate::catalog::XCatalog;
+use crate::context::{Literal, LiteralKey, RenderContext};
+use crate::expression::ast::eval::eval_ast;
+use crate::expression::ast::model::AST;
+
+/// An arrow function, with the context it has been created in.
+///
+/// The expressions call it without python, and it is a python callable
+/// for the functions registered in the catalog.
+#[pyclass(frozen)]
+pub struct XLambda {
+    params: Vec<String>,
+    body: Arc<AST>,
+    context: RenderContext,
+    catalog: XCatalog,
+}
+
+impl XLambda {
+    pub fn new(
+        params: &[String],
+        body: &Arc<AST>,
+        catalog: &XCatalog,
+        context: &RenderContext,
+    ) -> Self {
+        XLambda {
+            params: params.to_vec(),
+            body: body.clone(),
+            context: context.clone(),
+            catalog: catalog.share(),
+        }
+    }
+
+    /// Evaluate the body in the captured context and catalog, with the
+    /// arguments bound to the parameters.
+    pub fn call<'py>(
+        &self,
+        py: Python<'py>,
+        args: Vec<Literal>,
+        mut kwargs: HashMap<String, Literal>,
+    ) -> PyResult<Literal> {
+        if args.len() > self.params.len() {
+            return Err(PyTypeError::new_err(format!(
+                "{} takes {} positional arguments but {} were given",
+                self.name(),
+                self.params.len(),
+                args.len()
+            )));
+        }
+        let mut values: Vec<Option<Literal>> = args.into_iter().map(Some).collect();
+        values.resize(self.params.len(), None);
+        for (param, value) in self.params.iter().zip(values.iter_mut()) {
+            if let Some(kwarg) = kwargs.remove(param) {
+                if value.is_some() {
+                    return Err(PyTypeError::new_err(format!(
+                        "{} got multiple values for argument '{}'",
+                        self.name(),
+                        param
+                    )));
+                }
+                *value = Some(kwarg);
+            }
+        }
+        if let Some(key) = kwargs.keys().next() {
+            return Err(PyTypeError::new_err(format!(
+                "{} got an unexpected keyword argument '{}'",
+                self.name(),
+                key
+            )));
+        }
+
+        let mut context = self.context.clone();
+        for (idx, (param, value)) in self.params.iter().zip(values).enumerate() {
+            let value = value.ok_or_else(|| {
+                PyTypeError::new_err(format!("{} missing argument '{}'", self.name(), param))
+            })?;
+            if idx == 0 {
+                context.insert(LiteralKey::Str(param.clone()), value);
+            } else {
+                context.insert_current(LiteralKey::Str(param.clone()), value);
+            }
+        }
+        let catalog = &self.catalog;
+        match eval_ast(py, &self.body, catalog, &mut context)? {
+            // the markup is rendered while the parameters are in the context
+            Literal::XNode(node) => catalog
+                .render_node(py, &node, &mut context)
+                .map(Literal::Markup),
+            value => Ok(value),
+        }
+    }
+
+    fn name(&self) -> String {
+        format!("<lambda({})>", self.params.join(", "))
+    }
+}
+
+#[pymethods]
+impl XLambda {
+    #[pyo3(signature = (*args, **kwargs))]
+    fn __call__<'py>(
+        &self,
+        py: Python<'py>,
+        args: Bound<'py, PyTuple>,
+        kwargs: Option<Bound<'py, PyDict>>,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let args = args
+            .iter()
+            .map(|arg| Literal::downcast(py, arg))
+            .collect::<PyResult<Vec<_>>>()?;
+        let kwargs = match kwargs {
+            Some(kwargs) => kwargs
+                .iter()
+                .map(|(k, v)| Ok((k.extract::<String>()?, Literal::downcast(py, v)?)))
+                .collect::<PyResult<HashMap<_, _>>>()?,
+            None => HashMap::new(),
+        };
+        let res = self.call(py, args, kwargs)?;
+        Ok(res.into_py(py))
+    }
+
+    fn __repr__(&self) -> String {
+        self.name()
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+
+use pyo3::exceptions::{PyIndexError, PyKeyError, PyTypeError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyString, PyTuple};
+
+use crate::catalog::XCatalog;
+use crate::context::{Literal, LiteralKey, RenderContext};
+use crate::expression::ast::eval::{eval_raw_eq, format_literal};
+use crate::expression::filters::{self, map_str, Args, Filter};
+
+/// The methods of the strings implemented in Rust, with the names of their
+/// parameters, the string is the first positional argument.
+const STR_METHODS: &[(&str, &[&str], Filter)] = &[
+    ("upper", &[], filters::upper),
+    ("lower", &[], filters::lower),
+    ("strip", &["chars"], filters::trim),
+    ("startswith", &["prefix"], startswith),
+    ("endswith", &["suffix"], endswith),
+    ("replace", &["old", "new", "count"], replace),
+    ("split", &["sep", "maxsplit"], split),
+];
+
+const LIST_METHODS: &[(&str, &[&str], Filter)] = &[
+    ("len", &[], filters::length),
+    ("contains", &["value"], contains),
+    ("index", &["value"], index),
+    ("join", &["separator"], filters::join),
+];
+
+const DICT_METHODS: &[(&str, &[&str], Filter)] = &[
+    ("get", &["key", "default"], get),
+    ("keys", &[], keys),
+    ("values", &[], values),
+    ("items", &[], items),
+];
+
+fn methods(value: &Literal) -> &'static [(&'static str, &'static [&'static str], Filter)] {
+    match value {
+        Literal::Str(_) | Literal::Markup(_) => STR_METHODS,
+        Literal::List(_) => LIST_METHODS,
+        Literal::Dict(_) => DICT_METHODS,
+        _ => &[],
+    }
+}
+
+/// True if the method is implemented in Rust for the value, the keys of
+/// a dict are not shadowed by its methods.
+pub fn is_method(value: &Literal, name: &str) -> bool {
+    match value {
+        Literal::Str(_) if name == "format" => true,
+        Literal::Dict(map) if map.contains_key(&LiteralKey::Str(name.to_string())) => false,
+        _ => methods(value).iter().any(|(n, _, _)| *n == name),
+    }
+}
+
+/// Call the method implemented in Rust on the value.
+pub fn call_method<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    value: Literal,
+    name: &str,
+    args: Vec<Literal>,
+    kwargs: HashMap<String, Literal>,
+) -> PyResult<Literal> {
+    if let Literal::Str(template) = &value {
+        if name == "format" {
+            return format(py, catalog, context, template, args, kwargs);
+        }
+    }
+    let (name, params, method) = methods(&value)
+        .iter()
+        .find(|(n, _, _)| *n == name)
+        .ok_or_else(|| PyKeyError::new_err("Method not found"))?;
+    let args = Args::bind(
+        name,
+        params,
+        std::iter::once(value).chain(args).collect(),
+        kwargs,
+    )?;
+    method(py, catalog, context, args)
+}
+
+/// The string of a str or a markup receiver.
+fn receiver_str(args: &Args) -> PyResult<&str> {
+    match &args.value {
+        Literal::Str(s) | Literal::Markup(s) => Ok(s),
+        _ => Err(args.type_error("a string")),
+    }
+}
+
+/// A string parameter, `None` if it is not set.
+fn str_param<'a>(args: &'a Args, idx: usize, name: &str) -> PyResult<Option<&'a str>> {
+    match args.param(idx) {
+        None | Some(Literal::None(())) => Ok(None),
+        Some(Literal::Str(s)) | Some(Literal::Markup(s)) => Ok(Some(s)),
+        Some(other) => Err(PyTypeError::new_err(format!(
+            "{} must be a string, got {:?}",
+            name, other
+        ))),
+    }
+}
+
+/// An integer parameter, -1 if it is not set, as the python count arguments.
+fn count_param(args: &Args, idx: usize, name: &str) -> PyResult<isize> {
+    match args.param(idx) {
+        None | Some(Literal::None(())) => Ok(-1),
+        Some(Literal::Int(i)) => Ok(*i),
+        Some(other) => Err(PyTypeError::new_err(format!(
+            "{} must be an integer, got {:?}",
+            name, other
+        ))),
+    }
+}
+
+/// Test the affixes, a string or a list of strings, like `str.startswith`.
+fn test_affix(args: &Args, name: &str, test: impl Fn(&str, &str) -> bool) -> PyResult<Literal> {
+    let s = receiver_str(args)?;
+    let affixes = match args.param(0) {
+        Some(Literal::Str(a)) | Some(Literal::Markup(a)) => vec![a.as_str()],
+        Some(Literal::List(items)) => items
+            .iter()
+            .map(|item| match item {
+                Literal::Str(a) | Literal::Markup(a) => Ok(a.as_str()),
+                other => Err(PyTypeError::new_err(format!(
+                    "{} must be a string, got {:?}",
+                    name, other
+                ))),
+            })
+            .collect::<PyResult<_>>()?,
+        other => {
+            return Err(PyTypeError::new_err(format!(
+                "{} must be a string or a list of strings, got {:?}",
+                name, other
+            )))
+        }
+    };
+    Ok(Literal::Bool(affixes.into_iter().any(|a| test(s, a))))
+}
+
+fn startswith(_: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    test_affix(&args, "prefix", |s, a| s.starts_with(a))
+}
+
+fn endswith(_: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    test_affix(&args, "suffix", |s, a| s.ends_with(a))
+}
+
+fn replace(_: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    let old = str_param(&args, 0, "old")?
+        .ok_or_else(|| PyTypeError::new_err("replace() missing the old string"))?
+        .to_string();
+    let new = str_param(&args, 1, "new")?
+        .ok_or_else(|| PyTypeError::new_err("replace() missing the new string"))?
+        .to_string();
+    let count = count_param(&args, 2, "count")?;
+    map_str(args, |s| {
+        if count < 0 {
+            s.replace(&old, &new)
+        } else {
+            s.replacen(&old, &new, count as usize)
+        }
+    })
+}
+
+/// Split the string by the separator, or by the whitespaces, like `str.split`.
+fn split(_: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    let s = receiver_str(&args)?;
+    let maxsplit = count_param(&args, 1, "maxsplit")?;
+    let parts: Vec<String> = match str_param(&args, 0, "sep")? {
+        Some("") => return Err(PyValueError::new_err("empty separator")),
+        Some(sep) if maxsplit < 0 => s.split(sep).map(String::from).collect(),
+        Some(sep) => s
+            .splitn(maxsplit as usize + 1, sep)
+            .map(String::from)
+            .collect(),
+        None => {
+            let mut parts = Vec::new();
+            let mut rest = s.trim_start();
+            while !rest.is_empty() {
+                if maxsplit >= 0 && parts.len() == maxsplit as usize {
+                    parts.push(rest.to_string());
+                    break;
+                }
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                parts.push(rest[..end].to_string());
+                rest = rest[end..].trim_start();
+            }
+            parts
+        }
+    };
+    Ok(Literal::List(parts.into_iter().map(Literal::Str).collect()))
+}
+
+fn contains(_: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    let value = args.param(0).cloned().unwrap_or(Literal::None(()));
+    match &args.value {
+        Literal::List(items) => Ok(Literal::Bool(items.iter().any(|item| {
+            eval_raw_eq(item.clone(), value.clone(), "==".to_string()).unwrap_or(false)
+        }))),
+        _ => Err(args.type_error("a list")),
+    }
+}
+
+fn index(_: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    let value = args.param(0).cloned().unwrap_or(Literal::None(()));
+    match &args.value {
+        Literal::List(items) => items
+            .iter()
+            .position(|item| {
+                eval_raw_eq(item.clone(), value.clone(), "==".to_string()).unwrap_or(false)
+            })
+            .map(|idx| Literal::Int(idx as isize))
+            .ok_or_else(|| PyValueError::new_err(format!("{:?} is not in list", value))),
+        _ => Err(args.type_error("a list")),
+    }
+}
+
+fn get(_: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    let default = args.param(1).cloned().unwrap_or(Literal::None(()));
+    let key = match args.param(0) {
+        Some(key) => LiteralKey::try_from(key.clone())?,
+        None => return Err(PyTypeError::new_err("get() missing the key")),
+    };
+    match &args.value {
+        Literal::Dict(map) => Ok(map.get(&key).cloned().unwrap_or(default)),
+        _ => Err(args.type_error("a dict")),
+    }
+}
+
+fn keys(_: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    match args.value {
+        Literal::Dict(map) => Ok(Literal::List(map.into_keys().map(Literal::from).collect())),
+        _ => Err(args.type_error("a dict")),
+    }
+}
+
+fn values(_: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    match args.value {
+        Literal::Dict(map) => Ok(Literal::List(map.into_values().collect())),
+        _ => Err(args.type_error("a dict")),
+    }
+}
+
+/// The (key, value) pairs, as lists, they can be unpacked by the for statements.
+fn items(_: Python<'_>, _: &XCatalog, _: &mut RenderContext, args: Args) -> PyResult<Literal> {
+    match args.value {
+        Literal::Dict(map) => Ok(Literal::List(
+            map.into_iter()
+                .map(|(k, v)| Literal::List(vec![k.into(), v]))
+                .collect(),
+        )),
+        _ => Err(args.type_error("a dict")),
+    }
+}
+
+/// Replace the `{}`, `{0}` and `{name}` fields of the string, like `str.format`.
+///
+/// The fields with a format spec, a conversion or an attribute access are
+/// formatted by python.
+fn format(
+    py: Python<'_>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    template: &str,
+    args: Vec<Literal>,
+    kwargs: HashMap<String, Literal>,
+) -> PyResult<Literal> {
+    let mut res = String::with_capacity(template.len());
+    let mut auto_index = 0;
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                res.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                res.push('}');
+            }
+            '}' => {
+                return Err(PyValueError::new_err(
+                    "Single '}' encountered in format string",
+                ))
+            }
+            '{' => {
+                let mut field = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => field.push(c),
+                        None => {
+                            return Err(PyValueError::new_err(
+                                "Single '{' encountered in format string",
+                            ))
+                        }
+                    }
+                }
+                if field.contains([':', '!', '.', '[', '{']) {
+                    return py_format(py, template, args, kwargs);
+                }
+                let positional = if field.is_empty() {
+                    auto_index += 1;
+                    Some(auto_index - 1)
+                } else {
+                    field.parse::<usize>().ok()
+                };
+                let value = if let Some(idx) = positional {
+                    args.get(idx).ok_or_else(|| {
+                        PyIndexError::new_err(format!(
+                            "Replacement index {} out of range for positional args tuple",
+                            idx
+                        ))
+                    })?
+                } else {
+                    kwargs
+                        .get(&field)
+                        .ok_or_else(|| PyKeyError::new_err(field.clone()))?
+                };
+                res.push_str(&format_literal(py, catalog, context, value)?);
+            }
+            c => res.push(c),
+        }
+    }
+    Ok(Literal::Str(res))
+}
+
+/// Format the string with the python `str.format` method.
+fn py_format(
+    py: Python<'_>,
+    template: &str,
+    args: Vec<Literal>,
+    kwargs: HashMap<String, Literal>,
+) -> PyResult<Literal> {
+    let py_args = PyTuple::new(py, args.iter().map(|v| v.into_py(py)))?;
+    let py_kwargs = PyDict::new(py);
+    for (k, v) in kwargs {
+        py_kwargs.set_item(k, v.into_py(py))?;
+    }
+    let res = PyString::new(py, template).call_method("format", py_args, Some(&py_kwargs))?;
+    Literal::downcast(py, res)
+}
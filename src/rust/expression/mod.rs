@@ -1,4 +1,7 @@
 pub(crate) mod ast;
+pub(crate) mod filters;
 pub(crate) mod i18n;
+pub(crate) mod lambda;
+pub(crate) mod methods;
 pub(crate) mod parser;
 pub(crate) mod tokens;
@@ -7,30 +7,117 @@ use pyo3::exceptions::{PySyntaxError, PyValueError};
 use pyo3::PyErr;
 
 use crate::expression::tokens::ExpressionToken;
-use crate::markup::parser::parse_markup;
+use crate::markup::parser::{parse_markup, parse_template};
+use crate::span::Span;
 
-use super::tokens::{PostfixOp, UnaryOperator};
+use super::tokens::{MatchArmToken, PostfixOp, UnaryOperator};
 
 #[derive(Parser)]
 #[grammar = "rust/expression/grammar.pest"]
 pub struct ExpressionParser;
 
-fn parse_expression_token(pair: Pair<Rule>) -> Result<ExpressionToken, String> {
+/// Decode the escape sequences of a string, and the doubled braces of
+/// the f-strings.
+fn unescape(value: &str, braces: bool) -> Result<String, String> {
+    let mut res = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n') => res.push('\n'),
+                Some('t') => res.push('\t'),
+                Some('r') => res.push('\r'),
+                Some('0') => res.push('\0'),
+                Some('u') => {
+                    // the grammar ensures the escape is \u{...}
+                    let code: String = chars.by_ref().skip(1).take_while(|c| *c != '}').collect();
+                    let c = u32::from_str_radix(&code, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| format!("Invalid unicode escape \\u{{{}}}", code))?;
+                    res.push(c);
+                }
+                Some(c) => res.push(c),
+                None => res.push('\\'),
+            },
+            '{' | '}' if braces => {
+                // the grammar ensures the braces are doubled
+                chars.next();
+                res.push(c);
+            }
+            c => res.push(c),
+        }
+    }
+    Ok(res)
+}
+
+fn parse_expression_token(pair: Pair<Rule>, base: &Span) -> Result<ExpressionToken, String> {
+    let span = base.slice(pair.as_span().start(), pair.as_span().end());
     match pair.as_rule() {
-        Rule::expression => parse_expression_token(pair.into_inner().next().unwrap()),
+        Rule::expression => parse_expression_token(pair.into_inner().next().unwrap(), base),
         Rule::field => {
             let inner = pair.into_inner().next().unwrap();
             let postfix = inner.as_str();
-            Ok(ExpressionToken::PostfixOp(PostfixOp::Field(
-                postfix.to_string(),
-            )))
+            Ok(ExpressionToken::PostfixOp(
+                PostfixOp::Field(postfix.to_string()),
+                span,
+            ))
         }
         Rule::index => {
             let mut inner = pair.into_inner();
-            let postfix = parse_expression_token(inner.next().unwrap())?;
-            Ok(ExpressionToken::PostfixOp(PostfixOp::Index(Box::new(
-                postfix,
-            ))))
+            let postfix = parse_expression_token(inner.next().unwrap(), base)?;
+            Ok(ExpressionToken::PostfixOp(
+                PostfixOp::Index(Box::new(postfix)),
+                span,
+            ))
+        }
+        Rule::slice => {
+            let (mut start, mut stop, mut step) = (None, None, None);
+            for bound in pair.into_inner() {
+                let rule = bound.as_rule();
+                let token = Some(Box::new(parse_expression_token(
+                    bound.into_inner().next().unwrap(),
+                    base,
+                )?));
+                match rule {
+                    Rule::slice_start => start = token,
+                    Rule::slice_stop => stop = token,
+                    _ => step = token,
+                }
+            }
+            Ok(ExpressionToken::Slice { start, stop, step })
+        }
+        Rule::optional_field => {
+            let inner = pair.into_inner().next().unwrap();
+            Ok(ExpressionToken::PostfixOp(
+                PostfixOp::OptionalField(inner.as_str().to_string()),
+                span,
+            ))
+        }
+        Rule::optional_index => {
+            let mut inner = pair.into_inner();
+            let postfix = parse_expression_token(inner.next().unwrap(), base)?;
+            Ok(ExpressionToken::PostfixOp(
+                PostfixOp::OptionalIndex(Box::new(postfix)),
+                span,
+            ))
+        }
+        Rule::pipe => {
+            let mut inner = pair.into_inner();
+            let name = inner.next().unwrap().as_str().to_string();
+            let (args, kwargs) = match inner.next() {
+                Some(call) => match parse_expression_token(call, base)? {
+                    ExpressionToken::PostfixOp(PostfixOp::Call { args, kwargs }, _) => {
+                        (args, kwargs)
+                    }
+                    _ => unreachable!(),
+                },
+                None => (Vec::new(), HashMap::new()),
+            };
+            Ok(ExpressionToken::PostfixOp(
+                PostfixOp::Pipe { name, args, kwargs },
+                span,
+            ))
         }
         Rule::call => {
             let inner = pair.into_inner();
@@ -41,26 +128,29 @@ fn parse_expression_token(pair: Pair<Rule>) -> Result<ExpressionToken, String> {
                     Rule::kw_arg => {
                         let mut kw_inner = arg.into_inner();
                         let key = kw_inner.next().unwrap().as_str().to_string();
-                        let value = parse_expression_token(kw_inner.next().unwrap())?;
+                        let value = parse_expression_token(kw_inner.next().unwrap(), base)?;
                         kwargs.insert(key, value);
                     }
                     Rule::pos_arg => {
                         let arg_inner = arg.into_inner().next().unwrap();
-                        let value = parse_expression_token(arg_inner)?;
+                        let value = parse_expression_token(arg_inner, base)?;
                         args.push(value);
                     }
                     _ => return Err(format!("Unexpected rule in call: {:?}", arg.as_rule()).into()),
                 }
             }
 
-            Ok(ExpressionToken::PostfixOp(PostfixOp::Call { args, kwargs }))
+            Ok(ExpressionToken::PostfixOp(
+                PostfixOp::Call { args, kwargs },
+                span,
+            ))
         }
         Rule::binary_expression => {
             let mut inner = pair.into_inner();
             let mut tokens = Vec::new();
 
             while let Some(p) = inner.next() {
-                tokens.push(parse_expression_token(p)?);
+                tokens.push(parse_expression_token(p, base)?);
             }
             Ok(ExpressionToken::BinaryExpression(tokens))
         }
@@ -69,11 +159,24 @@ fn parse_expression_token(pair: Pair<Rule>) -> Result<ExpressionToken, String> {
 
             // Since the rule is: "not" ~ binary_expression
             // We don't need to parse the "not" token explicitly; it's implicit in the rule
-            let expr = parse_expression_token(inner.next().unwrap())?;
+            let expr = parse_expression_token(inner.next().unwrap(), base)?;
 
             Ok(ExpressionToken::UnaryExpression {
                 op: UnaryOperator::Not,
                 expr: Box::new(expr),
+                span,
+            })
+        }
+        Rule::negative_expression => {
+            // the operand is a term followed by its postfix operators
+            let tokens = pair
+                .into_inner()
+                .map(|p| parse_expression_token(p, base))
+                .collect::<Result<_, _>>()?;
+            Ok(ExpressionToken::UnaryExpression {
+                op: UnaryOperator::Neg,
+                expr: Box::new(ExpressionToken::BinaryExpression(tokens)),
+                span,
             })
         }
         Rule::if_expression => {
@@ -82,13 +185,15 @@ fn parse_expression_token(pair: Pair<Rule>) -> Result<ExpressionToken, String> {
             let condition_pair = inner.next().unwrap(); // expression
             let then_pair = inner.next().unwrap(); // block
 
-            let condition = Box::new(parse_expression_token(condition_pair)?);
+            let condition = Box::new(parse_expression_token(condition_pair, base)?);
             let then_branch = Box::new(parse_expression_token(
                 then_pair.into_inner().next().unwrap(),
+                base,
             )?);
 
             let else_branch = if let Some(else_block) = inner.next() {
-                let else_expr = parse_expression_token(else_block.into_inner().next().unwrap())?;
+                let else_expr =
+                    parse_expression_token(else_block.into_inner().next().unwrap(), base)?;
                 Some(Box::new(else_expr))
             } else {
                 None
@@ -100,43 +205,148 @@ fn parse_expression_token(pair: Pair<Rule>) -> Result<ExpressionToken, String> {
                 else_branch,
             })
         }
+        Rule::match_expression => {
+            let mut inner = pair.into_inner();
+            let subject = Box::new(parse_expression_token(inner.next().unwrap(), base)?);
+            let arms = inner
+                .map(|arm| -> Result<MatchArmToken, String> {
+                    let mut arm = arm.into_inner();
+                    let patterns = arm
+                        .next()
+                        .unwrap()
+                        .into_inner()
+                        .map(|pattern| parse_expression_token(pattern, base))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    let mut next = arm.next().unwrap();
+                    let guard = if next.as_rule() == Rule::match_guard {
+                        let guard = next.into_inner().next().unwrap();
+                        next = arm.next().unwrap();
+                        Some(Box::new(parse_expression_token(guard, base)?))
+                    } else {
+                        None
+                    };
+                    let body = Box::new(parse_expression_token(next, base)?);
+                    Ok(MatchArmToken {
+                        patterns,
+                        guard,
+                        body,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            Ok(ExpressionToken::MatchExpression { subject, arms })
+        }
+        Rule::match_negative => {
+            match parse_expression_token(pair.into_inner().next().unwrap(), base)? {
+                ExpressionToken::Integer(value) => Ok(ExpressionToken::Integer(-value)),
+                ExpressionToken::BigInteger(value) => {
+                    Ok(ExpressionToken::BigInteger(format!("-{}", value)))
+                }
+                ExpressionToken::Float(value) => Ok(ExpressionToken::Float(-value)),
+                token => Err(format!("Unexpected pattern {}", token)),
+            }
+        }
         Rule::for_expression => {
             let mut inner = pair.into_inner();
-            let ident = inner.next().unwrap().as_str().to_string();
+            let idents = inner
+                .next()
+                .unwrap()
+                .into_inner()
+                .map(|ident| ident.as_str().to_string())
+                .collect();
             let iterable_expr = inner.next().unwrap();
             let body_expr = inner.next().unwrap().into_inner().next().unwrap();
 
-            let iterable = Box::new(parse_expression_token(iterable_expr)?);
-            let body = Box::new(parse_expression_token(body_expr)?);
+            let iterable = Box::new(parse_expression_token(iterable_expr, base)?);
+            let body = Box::new(parse_expression_token(body_expr, base)?);
+            let else_branch = match inner.next() {
+                Some(else_block) => Some(Box::new(parse_expression_token(
+                    else_block.into_inner().next().unwrap(),
+                    base,
+                )?)),
+                None => None,
+            };
 
             Ok(ExpressionToken::ForExpression {
-                ident,
+                idents,
                 iterable,
                 body,
+                else_branch,
+                span,
             })
         }
+        Rule::lambda_expression => {
+            let mut inner = pair.into_inner();
+            let params = inner
+                .next()
+                .unwrap()
+                .into_inner()
+                .map(|ident| ident.as_str().to_string())
+                .collect();
+            let body = inner.next().unwrap();
+            let body = match body.as_rule() {
+                Rule::block => body.into_inner().next().unwrap(),
+                _ => body,
+            };
+            let body = Box::new(parse_expression_token(body, base)?);
+            Ok(ExpressionToken::Lambda { params, body })
+        }
         Rule::let_expression => {
             let mut inner = pair.into_inner();
             let ident = inner.next().unwrap().as_str().to_string();
             let expr_expr = inner.next().unwrap();
-            let expr = Box::new(parse_expression_token(expr_expr)?);
+            let expr = Box::new(parse_expression_token(expr_expr, base)?);
 
             Ok(ExpressionToken::LetExpression { ident, expr })
         }
         Rule::ident => {
             let content = pair.as_str();
             debug!("Pushing ident {}", content);
-            Ok(ExpressionToken::Ident(content.to_string()))
+            Ok(ExpressionToken::Ident(content.to_string(), span))
         }
         Rule::operator => {
-            let op = pair.as_str();
+            // normalize the spaces of `not in` and `is not`
+            let op = pair
+                .as_str()
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
             debug!("Pushing operator {}", op);
-            Ok(ExpressionToken::Operator(op.parse().unwrap()))
+            Ok(ExpressionToken::Operator(op.parse().unwrap(), span))
         }
         Rule::integer => {
-            let value: isize = pair.as_str().parse().unwrap();
-            debug!("Pushing integer {}", value);
-            Ok(ExpressionToken::Integer(value))
+            let digits = pair.as_str().replace('_', "");
+            debug!("Pushing integer {}", digits);
+            match digits.parse() {
+                Ok(value) => Ok(ExpressionToken::Integer(value)),
+                Err(_) => Ok(ExpressionToken::BigInteger(digits)),
+            }
+        }
+        Rule::float => {
+            let value: f64 = pair.as_str().replace('_', "").parse().unwrap();
+            debug!("Pushing float {}", value);
+            Ok(ExpressionToken::Float(value))
+        }
+        // tuples are built as lists
+        Rule::list | Rule::tuple => {
+            let items = pair
+                .into_inner()
+                .map(|p| parse_expression_token(p, base))
+                .collect::<Result<_, _>>()?;
+            Ok(ExpressionToken::List(items))
+        }
+        Rule::dict => {
+            let mut items = Vec::new();
+            for item in pair.into_inner() {
+                let mut inner = item.into_inner();
+                let key = parse_expression_token(inner.next().unwrap(), base)?;
+                let value = parse_expression_token(inner.next().unwrap(), base)?;
+                items.push((key, value));
+            }
+            Ok(ExpressionToken::Dict(items, span))
+        }
+        Rule::none => {
+            debug!("Pushing None");
+            Ok(ExpressionToken::None)
         }
         Rule::boolean => {
             let value: bool = pair.as_str().parse().unwrap();
@@ -172,20 +382,37 @@ fn parse_expression_token(pair: Pair<Rule>) -> Result<ExpressionToken, String> {
                 }
             }
 
+            let vstr = unescape(&vstr, false)?;
             debug!("Pushing dedent string {}", vstr);
             Ok(ExpressionToken::String(vstr))
         }
         Rule::normal_string => {
             let value = pair.as_str();
-            let vstr = value[1..value.len() - 1].to_string();
+            let vstr = unescape(&value[1..value.len() - 1], false)?;
             debug!("Pushing string {}", vstr);
             Ok(ExpressionToken::String(vstr))
         }
+        Rule::fstring => {
+            let parts = pair
+                .into_inner()
+                .map(|part| match part.as_rule() {
+                    Rule::fstring_expression => {
+                        parse_expression_token(part.into_inner().next().unwrap(), base)
+                    }
+                    _ => Ok(ExpressionToken::String(unescape(part.as_str(), true)?)),
+                })
+                .collect::<Result<_, _>>()?;
+            Ok(ExpressionToken::FString(parts, span))
+        }
         Rule::component => {
             debug!("Pushing component");
             let raw = pair.as_str();
             debug!("Pushing component {}", raw);
-            parse_markup(raw)
+            let markup = match span.source() {
+                Some(source) => parse_template(raw, source, span.start()),
+                None => parse_markup(raw),
+            };
+            markup
                 .map(|n| ExpressionToken::XNode(n))
                 .map_err(|e| format!("Syntax error: {}", e))
         }
@@ -200,12 +427,32 @@ fn parse_expression_token(pair: Pair<Rule>) -> Result<ExpressionToken, String> {
     }
 }
 
-pub(crate) fn tokenize(raw: &str) -> Result<ExpressionToken, PyErr> {
+/// Check that the raw string is a single and complete expression.
+pub(crate) fn is_expression(raw: &str) -> bool {
+    let raw = raw.trim();
+    ExpressionParser::parse(Rule::expression, raw)
+        .ok()
+        .and_then(|mut pairs| pairs.next())
+        .is_some_and(|pair| pair.as_span().end() == raw.len())
+}
+
+/// Tokenize the expression `raw`, located at `span` in its template.
+pub(crate) fn tokenize(raw: &str, span: &Span) -> Result<ExpressionToken, PyErr> {
+    let base = span.slice(raw.len() - raw.trim_start().len(), raw.len());
     let mut pairs = ExpressionParser::parse(Rule::expression, raw.trim())
         .map_err(|e| PySyntaxError::new_err(format!("{}", e)))?;
 
     if let Some(init) = pairs.next() {
-        return parse_expression_token(init).map_err(|e| PySyntaxError::new_err(e));
+        // the rest of the expression, such as `_` in `1_`, can't be ignored
+        let end = init.as_span().end();
+        if end != raw.trim().len() {
+            return Err(PySyntaxError::new_err(format!(
+                "Unexpected {:?} in expression {:?}",
+                &raw.trim()[end..],
+                raw.trim()
+            )));
+        }
+        return parse_expression_token(init, &base).map_err(|e| PySyntaxError::new_err(e));
     }
 
     Err(PyValueError::new_err(format!(
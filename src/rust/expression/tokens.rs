@@ -1,7 +1,10 @@
 use std::{collections::HashMap, fmt, str::FromStr};
 
+use crate::context::format_float;
 use crate::markup::tokens::XNode;
-use pyo3::prelude::*;
+use crate::serialize::{Decode, Decoder, Encode, Encoder};
+use crate::span::Span;
+use pyo3::{exceptions::PyValueError, prelude::*};
 
 #[pyclass(eq, eq_int)]
 #[derive(Debug, Clone, PartialEq)]
@@ -11,6 +14,7 @@ pub enum ExpType {
     Operator,
     String,
     Integer,
+    Float,
     Boolean,
 }
 
@@ -20,6 +24,7 @@ pub struct OperatorErr;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum UnaryOperator {
     Not,
+    Neg,
 }
 
 impl FromStr for UnaryOperator {
@@ -28,6 +33,7 @@ impl FromStr for UnaryOperator {
     fn from_str(op: &str) -> Result<Self, Self::Err> {
         match op {
             "not" => Ok(UnaryOperator::Not),
+            "-" => Ok(UnaryOperator::Neg),
             _ => Err(OperatorErr),
         }
     }
@@ -37,6 +43,7 @@ impl fmt::Display for UnaryOperator {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let op = match self {
             UnaryOperator::Not => "not",
+            UnaryOperator::Neg => "-",
         };
         write!(f, "{}", op)
     }
@@ -48,6 +55,9 @@ pub enum Operator {
     Sub,
     Mul,
     Div,
+    FloorDiv,
+    Mod,
+    Pow,
     And,
     Or,
     Eq,
@@ -56,6 +66,11 @@ pub enum Operator {
     Gte,
     Lt,
     Lte,
+    In,
+    NotIn,
+    Is,
+    IsNot,
+    Coalesce,
 }
 
 impl FromStr for Operator {
@@ -67,6 +82,9 @@ impl FromStr for Operator {
             "-" => Ok(Operator::Sub),
             "*" => Ok(Operator::Mul),
             "/" => Ok(Operator::Div),
+            "//" => Ok(Operator::FloorDiv),
+            "%" => Ok(Operator::Mod),
+            "**" => Ok(Operator::Pow),
             "and" => Ok(Operator::And),
             "or" => Ok(Operator::Or),
             "==" => Ok(Operator::Eq),
@@ -75,6 +93,11 @@ impl FromStr for Operator {
             "<" => Ok(Operator::Lt),
             ">=" => Ok(Operator::Gte),
             "<=" => Ok(Operator::Lte),
+            "in" => Ok(Operator::In),
+            "not in" => Ok(Operator::NotIn),
+            "is" => Ok(Operator::Is),
+            "is not" => Ok(Operator::IsNot),
+            "??" => Ok(Operator::Coalesce),
             _ => Err(OperatorErr),
         }
     }
@@ -83,14 +106,27 @@ impl FromStr for Operator {
 impl Operator {
     pub fn precedence(&self) -> u8 {
         match self {
-            Operator::Or => 1,
-            Operator::And => 2,
-            Operator::Eq | Operator::Neq => 3,
-            Operator::Gt | Operator::Gte | Operator::Lt | Operator::Lte => 4,
-            Operator::Add | Operator::Sub => 5,
-            Operator::Mul | Operator::Div => 6,
+            Operator::Coalesce => 1,
+            Operator::Or => 2,
+            Operator::And => 3,
+            Operator::Eq
+            | Operator::Neq
+            | Operator::In
+            | Operator::NotIn
+            | Operator::Is
+            | Operator::IsNot => 4,
+            Operator::Gt | Operator::Gte | Operator::Lt | Operator::Lte => 5,
+            Operator::Add | Operator::Sub => 6,
+            Operator::Mul | Operator::Div | Operator::FloorDiv | Operator::Mod => 7,
+            // the unary minus is between the product and the power
+            Operator::Pow => 9,
         }
     }
+
+    /// The power is right associative, `2 ** 3 ** 2` is `2 ** (3 ** 2)`.
+    pub fn is_right_associative(&self) -> bool {
+        *self == Operator::Pow
+    }
 }
 
 impl fmt::Display for Operator {
@@ -100,6 +136,9 @@ impl fmt::Display for Operator {
             Operator::Sub => "-",
             Operator::Mul => "*",
             Operator::Div => "/",
+            Operator::FloorDiv => "//",
+            Operator::Mod => "%",
+            Operator::Pow => "**",
             Operator::And => "and",
             Operator::Or => "or",
             Operator::Eq => "==",
@@ -108,19 +147,74 @@ impl fmt::Display for Operator {
             Operator::Lt => "<",
             Operator::Gte => ">=",
             Operator::Lte => "<=",
+            Operator::In => "in",
+            Operator::NotIn => "not in",
+            Operator::Is => "is",
+            Operator::IsNot => "is not",
+            Operator::Coalesce => "??",
         };
         write!(f, "{}", op)
     }
 }
 
+/// The operators are encoded by their symbol.
+impl Encode for Operator {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        enc.write_str(&self.to_string());
+        Ok(())
+    }
+}
+
+impl Decode for Operator {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        let op = dec.read_str()?;
+        op.parse()
+            .map_err(|_| PyValueError::new_err(format!("Invalid data, unknown operator {}", op)))
+    }
+}
+
+impl Encode for UnaryOperator {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        enc.write_str(&self.to_string());
+        Ok(())
+    }
+}
+
+impl Decode for UnaryOperator {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        let op = dec.read_str()?;
+        op.parse()
+            .map_err(|_| PyValueError::new_err(format!("Invalid data, unknown operator {}", op)))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum PostfixOp {
     Field(String),
     Index(Box<ExpressionToken>),
+    /// `?.field`, None if the value is None or has no such field.
+    OptionalField(String),
+    /// `?[index]`, None if the value is None or has no such item.
+    OptionalIndex(Box<ExpressionToken>),
     Call {
         args: Vec<ExpressionToken>,
         kwargs: HashMap<String, ExpressionToken>,
     },
+    /// `value | name(args)`, call the function with the value as first argument.
+    Pipe {
+        name: String,
+        args: Vec<ExpressionToken>,
+        kwargs: HashMap<String, ExpressionToken>,
+    },
+}
+
+/// An arm of a match expression, the patterns are literals, or idents for
+/// the wildcard `_` and the captures.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MatchArmToken {
+    pub patterns: Vec<ExpressionToken>,
+    pub guard: Option<Box<ExpressionToken>>,
+    pub body: Box<ExpressionToken>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -129,29 +223,55 @@ pub enum ExpressionToken {
     UnaryExpression {
         op: UnaryOperator,
         expr: Box<ExpressionToken>,
+        span: Span,
     },
-    Ident(String),
-    Operator(Operator),
+    Ident(String, Span),
+    Operator(Operator, Span),
     String(String),
+    /// The parts of a f-string, strings and expressions.
+    FString(Vec<ExpressionToken>, Span),
     // Uuid(String),
     Integer(isize),
+    /// An integer too large for an isize, its digits, it is a python int.
+    BigInteger(String),
+    Float(f64),
     Boolean(bool),
+    None,
+    List(Vec<ExpressionToken>),
+    Dict(Vec<(ExpressionToken, ExpressionToken)>, Span),
+    /// `start:stop:step` in an index, the bounds are optional.
+    Slice {
+        start: Option<Box<ExpressionToken>>,
+        stop: Option<Box<ExpressionToken>>,
+        step: Option<Box<ExpressionToken>>,
+    },
     XNode(XNode),
-    PostfixOp(PostfixOp),
+    PostfixOp(PostfixOp, Span),
     IfExpression {
         condition: Box<ExpressionToken>,
         then_branch: Box<ExpressionToken>,
         else_branch: Option<Box<ExpressionToken>>,
     },
     ForExpression {
-        ident: String,
+        idents: Vec<String>,
         iterable: Box<ExpressionToken>,
         body: Box<ExpressionToken>,
+        else_branch: Option<Box<ExpressionToken>>,
+        span: Span,
     },
     LetExpression {
         ident: String,
         expr: Box<ExpressionToken>,
     },
+    MatchExpression {
+        subject: Box<ExpressionToken>,
+        arms: Vec<MatchArmToken>,
+    },
+    /// An arrow function, `(a, b) => a + b`.
+    Lambda {
+        params: Vec<String>,
+        body: Box<ExpressionToken>,
+    },
     Noop,
 }
 
@@ -165,25 +285,73 @@ impl std::fmt::Display for ExpressionToken {
                     children.iter().map(|v| v.to_string()).collect::<String>()
                 )
             }
-            ExpressionToken::UnaryExpression { op, expr } => {
+            ExpressionToken::UnaryExpression { op, expr, .. } => {
                 write!(f, "{} {}", op, expr)
             }
-            ExpressionToken::Ident(ident) => {
+            ExpressionToken::Ident(ident, _) => {
                 write!(f, "{}", ident)
             }
-            ExpressionToken::Operator(op) => write!(f, " {} ", op.to_string()),
+            ExpressionToken::Operator(op, _) => write!(f, " {} ", op.to_string()),
             ExpressionToken::String(value) => {
                 write!(f, "\"{}\"", value.replace('"', "\\\""))
             }
+            ExpressionToken::FString(parts, _) => {
+                write!(f, "f\"")?;
+                for part in parts {
+                    match part {
+                        ExpressionToken::String(s) => {
+                            write!(f, "{}", s.replace('{', "{{").replace('}', "}}"))?
+                        }
+                        expr => write!(f, "{{{}}}", expr)?,
+                    }
+                }
+                write!(f, "\"")
+            }
             // ExpressionToken::Uuid(value) => write!(f, "\"{}\"", value),
             ExpressionToken::Integer(value) => write!(f, "{}", value),
+            ExpressionToken::BigInteger(value) => write!(f, "{}", value),
+            ExpressionToken::Float(value) => write!(f, "{}", format_float(*value)),
             ExpressionToken::Boolean(value) => write!(f, "{}", value),
+            ExpressionToken::None => write!(f, "None"),
+            ExpressionToken::List(items) => write!(
+                f,
+                "[{}]",
+                items
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ExpressionToken::Dict(items, _) => write!(
+                f,
+                "{{{}}}",
+                items
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}", k, v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            ExpressionToken::Slice { start, stop, step } => {
+                let bound = |b: &Option<Box<ExpressionToken>>| {
+                    b.as_ref().map(|b| b.to_string()).unwrap_or_default()
+                };
+                write!(f, "{}:{}", bound(start), bound(stop))?;
+                match step {
+                    Some(_) => write!(f, ":{}", bound(step)),
+                    None => Ok(()),
+                }
+            }
             ExpressionToken::XNode(n) => write!(f, "{}", n),
-            ExpressionToken::PostfixOp(op) => match op {
+            ExpressionToken::PostfixOp(op, _) => match op {
                 PostfixOp::Field(field) => write!(f, ".{}", field),
                 PostfixOp::Index(index) => write!(f, "[{}]", index),
+                PostfixOp::OptionalField(field) => write!(f, "?.{}", field),
+                PostfixOp::OptionalIndex(index) => write!(f, "?[{}]", index),
                 // FIXME, display the args and kwargs properly
                 PostfixOp::Call { args, kwargs } => write!(f, "({:?}, {:?})", args, kwargs),
+                PostfixOp::Pipe { name, args, kwargs } => {
+                    write!(f, " | {}({:?}, {:?})", name, args, kwargs)
+                }
             },
             ExpressionToken::IfExpression {
                 condition,
@@ -200,11 +368,44 @@ impl std::fmt::Display for ExpressionToken {
                 }
             },
             ExpressionToken::ForExpression {
-                ident,
+                idents,
                 iterable,
                 body,
-            } => write!(f, "for {} in {} {{ {} }}", ident, iterable, body),
+                else_branch,
+                ..
+            } => {
+                write!(
+                    f,
+                    "for {} in {} {{ {} }}",
+                    idents.join(", "),
+                    iterable,
+                    body
+                )?;
+                match else_branch {
+                    Some(else_branch) => write!(f, " else {{ {} }}", else_branch),
+                    None => Ok(()),
+                }
+            }
             ExpressionToken::LetExpression { ident, expr } => write!(f, "let {} = {}", ident, expr),
+            ExpressionToken::Lambda { params, body } => {
+                write!(f, "({}) => {}", params.join(", "), body)
+            }
+            ExpressionToken::MatchExpression { subject, arms } => {
+                write!(f, "match {} {{ ", subject)?;
+                for arm in arms {
+                    let patterns = arm
+                        .patterns
+                        .iter()
+                        .map(|p| p.to_string())
+                        .collect::<Vec<_>>();
+                    write!(f, "{}", patterns.join(" | "))?;
+                    if let Some(guard) = &arm.guard {
+                        write!(f, " if {}", guard)?;
+                    }
+                    write!(f, " => {}, ", arm.body)?;
+                }
+                write!(f, "}}")
+            }
             ExpressionToken::Noop => write!(f, ""), // ??
         }
     }
@@ -10,13 +10,19 @@ use pyo3::prelude::*;
 
 mod catalog;
 mod context;
+mod error;
 mod expression;
 mod markup;
+mod serialize;
+mod span;
 
-use crate::catalog::XCatalog;
-use crate::context::RenderContext;
+use crate::catalog::{stop_renderings, XCatalog, XRenderIter, XTemplate};
+use crate::context::{markup_type, RenderContext};
+use crate::error::render_error_type;
 use crate::expression::i18n::extract_expr_i18n_messages;
+use crate::expression::lambda::XLambda;
 use crate::markup::parser::parse_markup;
+use crate::markup::slot::XSlot;
 use crate::markup::tokens::{
     NodeType, XComment, XElement, XExpression, XFragment, XNSElement, XNode, XText,
 };
@@ -36,10 +42,20 @@ fn xcore(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<XText>()?;
     m.add_class::<XExpression>()?;
     m.add_class::<XCatalog>()?;
+    m.add_class::<XTemplate>()?;
+    m.add_class::<XRenderIter>()?;
     m.add_class::<RenderContext>()?;
+    m.add_class::<XLambda>()?;
+    m.add_class::<XSlot>()?;
+    m.add("Markup", markup_type(m.py())?)?;
+    m.add("XComponentRenderError", render_error_type(m.py())?)?;
 
     m.add_function(wrap_pyfunction!(parse_markup, m)?)?;
     m.add_function(wrap_pyfunction!(extract_expr_i18n_messages, m)?)?;
 
+    // streamed renderings run in threads that must not outlive the interpreter
+    PyModule::import(m.py(), "atexit")?
+        .call_method1("register", (wrap_pyfunction!(stop_renderings, m)?,))?;
+
     Ok(())
 }
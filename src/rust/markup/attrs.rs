@@ -0,0 +1,173 @@
+use indexmap::IndexMap;
+use pyo3::exceptions::{PyTypeError, PyValueError};
+use pyo3::prelude::*;
+
+use crate::catalog::XCatalog;
+use crate::context::{Literal, LiteralKey, RenderContext};
+use crate::error::render_error;
+use crate::expression::ast::eval::{eval_expression, format_literal};
+use crate::markup::tokens::XNode;
+
+/// The name of a spread attribute, `{...attrs}`, is its expression
+/// prefixed by the spread operator.
+pub const SPREAD_PREFIX: &str = "...";
+
+pub fn has_spread(attrs: &IndexMap<String, XNode>) -> bool {
+    attrs.keys().any(|name| name.starts_with(SPREAD_PREFIX))
+}
+
+/// The name of the parameter of a component for the attribute `name`.
+pub fn prop_name(name: &str) -> String {
+    match name {
+        "class" => "class_".to_string(),
+        "for" => "for_".to_string(),
+        _ => name.replace('-', "_"),
+    }
+}
+
+/// Evaluate the attributes of the element `<tag>`, in the order of the markup.
+///
+/// The items of the spread attributes are merged with the other attributes,
+/// the last value of an attribute wins, except for the `class` attributes,
+/// which are concatenated, and the `style` attributes, where the last value
+/// of each declaration wins.
+pub fn eval_attrs<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    tag: &str,
+    attrs: &IndexMap<String, XNode>,
+) -> PyResult<IndexMap<String, Literal>> {
+    let mut values = IndexMap::with_capacity(attrs.len());
+    for (name, node) in attrs.iter() {
+        match node {
+            XNode::Expression(expression) => {
+                let value = eval_expression(py, expression, catalog, context).map_err(|err| {
+                    render_error(py, err, || {
+                        if name.starts_with(SPREAD_PREFIX) {
+                            format!("<{} {{{}}}>", tag, name)
+                        } else {
+                            format!("<{} {}={{{}}}>", tag, name, expression.expression())
+                        }
+                    })
+                })?;
+                if name.starts_with(SPREAD_PREFIX) {
+                    for (name, value) in spread_items(value)? {
+                        merge_attr(py, catalog, context, &mut values, name, value)?;
+                    }
+                } else {
+                    merge_attr(py, catalog, context, &mut values, name.clone(), value)?;
+                }
+            }
+            // the text attribute is escaped when rendered
+            XNode::Text(text) => {
+                let value = Literal::Str(text.text().to_string());
+                merge_attr(py, catalog, context, &mut values, name.clone(), value)?;
+            }
+            _ => {
+                let value = Literal::Markup(catalog.render_node(py, node, context)?);
+                merge_attr(py, catalog, context, &mut values, name.clone(), value)?;
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// The attributes of a spread dict.
+fn spread_items(value: Literal) -> PyResult<Vec<(String, Literal)>> {
+    let map = match value {
+        Literal::None(()) => return Ok(Vec::new()),
+        Literal::Dict(map) => map,
+        _ => {
+            return Err(PyTypeError::new_err(format!(
+                "{:?} cannot be spread, a dict is expected",
+                value
+            )))
+        }
+    };
+    map.into_iter()
+        .map(|(key, value)| {
+            let name = match key {
+                LiteralKey::Int(i) => i.to_string(),
+                LiteralKey::Str(s) | LiteralKey::Uuid(s) => s,
+            };
+            if !is_attr_name(&name) {
+                return Err(PyValueError::new_err(format!(
+                    "Invalid attribute name {:?}",
+                    name
+                )));
+            }
+            Ok((name, value))
+        })
+        .collect()
+}
+
+/// Names that can be rendered as attribute without breaking the markup.
+fn is_attr_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.chars().any(|c| {
+            c.is_whitespace() || c.is_control() || matches!(c, '"' | '\'' | '>' | '/' | '=' | '<')
+        })
+}
+
+fn merge_attr<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    values: &mut IndexMap<String, Literal>,
+    name: String,
+    value: Literal,
+) -> PyResult<()> {
+    let value = match (name.as_str(), values.get(&name)) {
+        ("class", Some(previous)) => {
+            let classes = [previous, &value]
+                .into_iter()
+                .map(|v| attr_text(py, catalog, context, v))
+                .collect::<PyResult<Vec<_>>>()?;
+            let classes = classes.into_iter().flatten().collect::<Vec<_>>();
+            if classes.is_empty() {
+                Literal::None(())
+            } else {
+                Literal::Str(classes.join(" "))
+            }
+        }
+        ("style", Some(previous)) => {
+            let mut declarations = IndexMap::new();
+            for style in [previous, &value] {
+                let style = attr_text(py, catalog, context, style)?.unwrap_or_default();
+                for declaration in style.split(';') {
+                    if let Some((property, value)) = declaration.split_once(':') {
+                        declarations
+                            .insert(property.trim().to_lowercase(), value.trim().to_string());
+                    }
+                }
+            }
+            let style = declarations
+                .iter()
+                .map(|(property, value)| format!("{}: {}", property, value))
+                .collect::<Vec<_>>()
+                .join("; ");
+            Literal::Str(style)
+        }
+        _ => value,
+    };
+    values.insert(name, value);
+    Ok(())
+}
+
+/// The text of a `class` or `style` value, `None` and `false` have no text.
+fn attr_text<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    value: &Literal,
+) -> PyResult<Option<String>> {
+    match value {
+        Literal::None(()) | Literal::Bool(false) => Ok(None),
+        _ => {
+            let text = format_literal(py, catalog, context, value)?;
+            let text = text.trim();
+            Ok((!text.is_empty()).then(|| text.to_string()))
+        }
+    }
+}
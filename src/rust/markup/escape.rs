@@ -0,0 +1,242 @@
+use std::borrow::Cow;
+
+use pyo3::prelude::*;
+
+use crate::{
+    catalog::XCatalog,
+    context::{format_float, format_uuid, Literal, LiteralKey, RenderContext},
+};
+
+/// Attributes containing an URL, they are filtered to avoid script injection.
+const URL_ATTRIBUTES: [&str; 10] = [
+    "href",
+    "src",
+    "action",
+    "formaction",
+    "cite",
+    "poster",
+    "background",
+    "xlink:href",
+    "data",
+    "manifest",
+];
+
+/// Attributes containing a list of URLs, such as the image candidates
+/// of `srcset`, each URL is filtered.
+const URL_LIST_ATTRIBUTES: [&str; 2] = ["srcset", "ping"];
+
+/// Replacement for urls using a scheme that can execute scripts.
+const UNSAFE_URL: &str = "about:invalid#unsafe-url";
+
+/// The place where a value is interpolated in the rendered HTML.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Escape {
+    /// Inside a text node.
+    Text,
+    /// Inside a double quoted attribute value.
+    Attribute,
+    /// Inside a double quoted attribute value containing an URL.
+    Url,
+    /// Inside a double quoted attribute value containing a list of URLs.
+    UrlList,
+    /// Inside a `<script>` tag, the value is a JSON document.
+    Script,
+}
+
+impl Escape {
+    /// The escaping context of the attribute `name` of an HTML element.
+    pub fn for_attribute(name: &str) -> Self {
+        let name = name.to_lowercase();
+        if URL_LIST_ATTRIBUTES.contains(&name.as_str()) {
+            Escape::UrlList
+        } else if URL_ATTRIBUTES.contains(&name.as_str()) {
+            Escape::Url
+        } else {
+            Escape::Attribute
+        }
+    }
+
+    /// Escape an unsafe string.
+    pub fn escape<'a>(&self, value: &'a str) -> Cow<'a, str> {
+        match self {
+            Escape::Text => html_escape::encode_text(value),
+            Escape::Attribute => html_escape::encode_double_quoted_attribute(value),
+            Escape::Url => {
+                if is_safe_url(value) {
+                    html_escape::encode_double_quoted_attribute(value)
+                } else {
+                    Cow::Borrowed(UNSAFE_URL)
+                }
+            }
+            Escape::UrlList => {
+                if is_safe_url_list(value) {
+                    html_escape::encode_double_quoted_attribute(value)
+                } else {
+                    Cow::Borrowed(UNSAFE_URL)
+                }
+            }
+            Escape::Script => escape_script(value),
+        }
+    }
+
+    /// Escape a markup that has already been escaped for a text node.
+    ///
+    /// The markup is trusted, only the double quote is escaped to keep
+    /// the attribute well formed, html entities are kept as is.
+    pub fn escape_markup<'a>(&self, markup: &'a str) -> Cow<'a, str> {
+        match self {
+            Escape::Text => Cow::Borrowed(markup),
+            Escape::Attribute => escape_quote(markup),
+            Escape::Url => {
+                if is_safe_url(&html_escape::decode_html_entities(markup)) {
+                    escape_quote(markup)
+                } else {
+                    Cow::Borrowed(UNSAFE_URL)
+                }
+            }
+            Escape::UrlList => {
+                if is_safe_url_list(&html_escape::decode_html_entities(markup)) {
+                    escape_quote(markup)
+                } else {
+                    Cow::Borrowed(UNSAFE_URL)
+                }
+            }
+            Escape::Script => escape_script(markup),
+        }
+    }
+}
+
+fn escape_quote(value: &str) -> Cow<'_, str> {
+    if value.contains('"') {
+        Cow::Owned(value.replace('"', "&quot;"))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// Ensure that the JSON document can't close the script tag, and is valid
+/// javascript.
+fn escape_script(value: &str) -> Cow<'_, str> {
+    if !value.contains(['<', '>', '&', '\u{2028}', '\u{2029}']) {
+        return Cow::Borrowed(value);
+    }
+    let mut res = String::with_capacity(value.len() + 16);
+    for c in value.chars() {
+        match c {
+            '<' => res.push_str("\\u003c"),
+            '>' => res.push_str("\\u003e"),
+            '&' => res.push_str("\\u0026"),
+            '\u{2028}' => res.push_str("\\u2028"),
+            '\u{2029}' => res.push_str("\\u2029"),
+            _ => res.push(c),
+        }
+    }
+    Cow::Owned(res)
+}
+
+/// Reject the urls using the javascript: or the vbscript: scheme,
+/// and the data: urls that are not images.
+fn is_safe_url(url: &str) -> bool {
+    // browsers ignore leading control chars and whitespaces,
+    // and tabs and new lines anywhere in the url.
+    let normalized = url
+        .trim_start_matches(|c: char| c <= ' ')
+        .chars()
+        .filter(|c| !matches!(c, '\t' | '\n' | '\r'))
+        .take(32)
+        .collect::<String>()
+        .to_lowercase();
+    match normalized.split_once(':') {
+        Some((scheme, rest)) if !scheme.contains(['/', '?', '#']) => match scheme {
+            "javascript" | "vbscript" => false,
+            "data" => rest.starts_with("image/") && !rest.starts_with("image/svg"),
+            _ => true,
+        },
+        _ => true,
+    }
+}
+
+/// Check every URL of a list, the URLs are separated by whitespaces,
+/// and by commas for the image candidates of `srcset`.
+///
+/// A comma inside an URL, such as in a `data:` URL, splits it in parts
+/// that are checked too, the scheme is always at the start of a part.
+fn is_safe_url_list(urls: &str) -> bool {
+    urls.split(|c: char| c == ',' || c.is_ascii_whitespace())
+        .all(is_safe_url)
+}
+
+fn json_string(value: &str) -> String {
+    let mut res = String::with_capacity(value.len() + 2);
+    res.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => res.push_str("\\\""),
+            '\\' => res.push_str("\\\\"),
+            '\n' => res.push_str("\\n"),
+            '\r' => res.push_str("\\r"),
+            '\t' => res.push_str("\\t"),
+            c if c < ' ' => res.push_str(format!("\\u{:04x}", c as u32).as_str()),
+            c => res.push(c),
+        }
+    }
+    res.push('"');
+    res
+}
+
+fn json_key(key: &LiteralKey) -> String {
+    match key {
+        LiteralKey::Int(i) => json_string(i.to_string().as_str()),
+        LiteralKey::Str(s) => json_string(s),
+        LiteralKey::Uuid(u) => json_string(format_uuid(u).as_str()),
+    }
+}
+
+/// Serialize a literal to JSON, the result is not escaped.
+pub fn to_json<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    value: &Literal,
+) -> PyResult<String> {
+    let json = match value {
+        Literal::None(_) => "null".to_string(),
+        Literal::Bool(b) => b.to_string(),
+        Literal::Int(i) => i.to_string(),
+        Literal::Float(f) if f.is_finite() => format_float(*f),
+        Literal::Float(f) if f.is_nan() => "NaN".to_string(),
+        Literal::Float(f) => if *f > 0.0 { "Infinity" } else { "-Infinity" }.to_string(),
+        Literal::Str(s) | Literal::Markup(s) | Literal::Callable(s) => json_string(s),
+        Literal::Uuid(u) => json_string(format_uuid(u).as_str()),
+        Literal::XNode(n) => json_string(catalog.render_node(py, n, context)?.as_str()),
+        Literal::List(l) => {
+            let items = l
+                .iter()
+                .map(|item| to_json(py, catalog, context, item))
+                .collect::<PyResult<Vec<_>>>()?;
+            format!("[{}]", items.join(","))
+        }
+        Literal::Dict(d) => {
+            let items = d
+                .iter()
+                .map(|(k, v)| {
+                    Ok(format!(
+                        "{}:{}",
+                        json_key(k),
+                        to_json(py, catalog, context, v)?
+                    ))
+                })
+                .collect::<PyResult<Vec<_>>>()?;
+            format!("{{{}}}", items.join(","))
+        }
+        Literal::Number(o) | Literal::Object(o) => {
+            let json = PyModule::import(py, "json")?;
+            let builtins = PyModule::import(py, "builtins")?;
+            let kwargs = pyo3::types::PyDict::new(py);
+            kwargs.set_item("default", builtins.getattr("str")?)?;
+            json.call_method("dumps", (o.obj(),), Some(&kwargs))?
+                .extract::<String>()?
+        }
+    };
+    Ok(json)
+}
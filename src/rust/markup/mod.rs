@@ -1,2 +1,6 @@
+pub(crate) mod attrs;
+pub(crate) mod escape;
 pub(crate) mod parser;
+pub(crate) mod provider;
+pub(crate) mod slot;
 pub(crate) mod tokens;
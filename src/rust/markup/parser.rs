@@ -1,38 +1,42 @@
 use std::cmp::min;
-use std::collections::HashMap;
+use std::sync::Arc;
 
+use indexmap::IndexMap;
 use pyo3::prelude::*;
 
 use pest::iterators::{Pair, Pairs};
 use pest::Parser;
 use pest_derive::Parser;
 
+use crate::markup::attrs::SPREAD_PREFIX;
 use crate::markup::tokens::{
     XComment, XDocType, XElement, XExpression, XFragment, XNSElement, XNode, XScriptElement, XText,
 };
+use crate::span::{Source, Span};
 
 #[derive(Parser)]
 #[grammar = "rust/markup/grammar.pest"]
 pub struct XParser;
 
-fn parse_nodes(pairs: Pairs<Rule>) -> Vec<XNode> {
+fn parse_nodes(pairs: Pairs<Rule>, source: &Arc<Source>, offset: usize) -> Vec<XNode> {
     let mut result = Vec::new();
 
     for pair in pairs {
-        if let Some(node) = parse_node(pair) {
+        if let Some(node) = parse_node(pair, source, offset) {
             result.push(node);
         }
     }
     return result;
 }
 
-fn parse_node(pair: Pair<Rule>) -> Option<XNode> {
-    match pair.as_rule() {
+fn parse_node(pair: Pair<Rule>, source: &Arc<Source>, offset: usize) -> Option<XNode> {
+    let span = Span::from_pest(source, offset, pair.as_span());
+    let node = match pair.as_rule() {
         Rule::normal_element => {
             debug!("Pushing normal_element");
             let mut inner = pair.into_inner();
             let open_tag = inner.next().unwrap();
-            match parse_open_tag(open_tag) {
+            match parse_open_tag(open_tag, source, offset) {
                 OpenTag::Element(name, attrs) => {
                     match name.as_str() {
                         "script" | "style" => {
@@ -44,7 +48,7 @@ fn parse_node(pair: Pair<Rule>) -> Option<XNode> {
                             )))
                         }
                         _ => {
-                            let mut children = parse_nodes(inner);
+                            let mut children = parse_nodes(inner, source, offset);
                             // we make the distinctions between self closing element
                             // and normal element from the user input, we must ensure that
                             // the normal element are still rendered as normal element since
@@ -58,7 +62,7 @@ fn parse_node(pair: Pair<Rule>) -> Option<XNode> {
                     }
                 }
                 OpenTag::NSElement(ns, name, attrs) => {
-                    let mut children = parse_nodes(inner);
+                    let mut children = parse_nodes(inner, source, offset);
                     // we make the distinctions between self closing element
                     // and normal element from the user input, we must ensure that
                     // the normal element are still rendered as normal element since
@@ -73,12 +77,12 @@ fn parse_node(pair: Pair<Rule>) -> Option<XNode> {
         Rule::fragment => {
             debug!("Pushing fragment");
             let inner = pair.into_inner();
-            let children = parse_nodes(inner);
+            let children = parse_nodes(inner, source, offset);
             Some(XNode::Fragment(XFragment::new(children)))
         }
         Rule::self_closing_element => {
             debug!("Pushing self_closing_element");
-            match parse_open_tag(pair) {
+            match parse_open_tag(pair, source, offset) {
                 OpenTag::Element(name, attrs) => {
                     Some(XNode::Element(XElement::new(name, attrs, Vec::new())))
                 }
@@ -121,51 +125,77 @@ fn parse_node(pair: Pair<Rule>) -> Option<XNode> {
             debug!("No rule defined for {:?}", pair.as_rule());
             None
         }
-    }
+    };
+    node.map(|n| n.with_span(span))
 }
 
 enum OpenTag {
-    Element(String, HashMap<String, XNode>),
-    NSElement(String, String, HashMap<String, XNode>),
+    Element(String, IndexMap<String, XNode>),
+    NSElement(String, String, IndexMap<String, XNode>),
 }
-fn parse_open_tag(pair: Pair<Rule>) -> OpenTag {
+fn parse_open_tag(pair: Pair<Rule>, source: &Arc<Source>, offset: usize) -> OpenTag {
     let mut inner = pair.into_inner();
     let name = inner.next().unwrap().as_str().to_string();
 
-    let mut attrs = HashMap::new();
+    let mut attrs = IndexMap::new();
     for attr in inner {
         if attr.as_rule() == Rule::attribute {
             let mut parts = attr.into_inner();
             let key = parts.next().unwrap().as_str().to_string();
             if let Some(value_pair) = parts.next() {
+                let span = Span::from_pest(source, offset, value_pair.as_span());
                 let value = value_pair.as_str();
                 if value.starts_with('{') {
                     attrs.insert(
                         key,
-                        XNode::Expression(XExpression::new(value[1..value.len() - 1].to_string())),
+                        XNode::Expression(XExpression::new(value[1..value.len() - 1].to_string()))
+                            .with_span(span),
                     );
                 } else {
                     attrs.insert(
                         key,
-                        XNode::Text(XText::new(value[1..value.len() - 1].to_string())),
+                        XNode::Text(XText::new(value[1..value.len() - 1].to_string()))
+                            .with_span(span),
                     );
                 }
             } else {
                 attrs.insert(key, XNode::Expression(XExpression::new("true".to_string())));
             }
+        } else if attr.as_rule() == Rule::spread_attribute {
+            let attr_span = Span::from_pest(source, offset, attr.as_span());
+            let start = attr.as_span().start();
+            let value_pair = attr.into_inner().next().unwrap();
+            // the span of the expression starts on the character before it,
+            // like the curly brace of the other expressions
+            let span = attr_span.slice(
+                value_pair.as_span().start() - start - 1,
+                value_pair.as_span().end() - start + 1,
+            );
+            let value = value_pair.as_str().trim_end();
+            attrs.insert(
+                format!("{}{}", SPREAD_PREFIX, value),
+                XNode::Expression(XExpression::new(value.to_string())).with_span(span),
+            );
         }
     }
 
-    if name.contains('.') {
-        let parts: Vec<&str> = name.split('.').collect();
-        OpenTag::NSElement(parts[0].to_string(), parts[1].to_string(), attrs)
-    } else {
-        OpenTag::Element(name, attrs)
+    // the namespace of `<ui.forms.Input />` is `ui.forms`
+    match name.rsplit_once('.') {
+        Some((namespace, name)) => {
+            OpenTag::NSElement(namespace.to_string(), name.to_string(), attrs)
+        }
+        None => OpenTag::Element(name, attrs),
     }
 }
 
 #[pyfunction]
 pub fn parse_markup(raw: &str) -> PyResult<XNode> {
+    parse_template(raw, &Source::new(None, raw), 0)
+}
+
+/// Parse the markup `raw`, located at `offset` in the given source.
+pub(crate) fn parse_template(raw: &str, source: &Arc<Source>, offset: usize) -> PyResult<XNode> {
+    let offset = offset + raw.len() - raw.trim_start().len();
     let raw = raw.trim();
     info!("Parsing markup {}...", &raw[..min(raw.len(), 24)]);
     debug!("{}", raw);
@@ -177,7 +207,7 @@ pub fn parse_markup(raw: &str) -> PyResult<XNode> {
         .next()
         .ok_or_else(|| pyo3::exceptions::PyValueError::new_err("Expected one node"))?;
 
-    if let Some(token) = parse_node(pair) {
+    if let Some(token) = parse_node(pair, source, offset) {
         debug!("Token parsed {:?}", token);
         Ok(token)
     } else {
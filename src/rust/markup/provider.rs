@@ -0,0 +1,36 @@
+use std::fmt;
+
+use indexmap::IndexMap;
+use pyo3::prelude::*;
+
+use crate::catalog::XCatalog;
+use crate::context::RenderContext;
+use crate::markup::attrs::{eval_attrs, prop_name};
+use crate::markup::slot::lazy_markup;
+use crate::markup::tokens::{ToHtml, XNode};
+
+/// The element providing its attributes to the descendant components,
+/// `<Provider theme={theme}>...</Provider>`.
+pub const PROVIDER: &str = "Provider";
+
+/// Render the children of a provider, with its attributes provided
+/// to all the components rendered by its children.
+pub fn write_provider<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    attrs: &IndexMap<String, XNode>,
+    children: &[XNode],
+    out: &mut dyn fmt::Write,
+) -> PyResult<()> {
+    let provided = context.provided();
+    for (name, value) in eval_attrs(py, catalog, context, PROVIDER, attrs)? {
+        let value = lazy_markup(py, catalog, context, value)?;
+        context.provide(prop_name(&name), value);
+    }
+    let result = children
+        .iter()
+        .try_for_each(|child| child.write_html(py, catalog, context, out));
+    context.set_provided(provided);
+    result
+}
@@ -0,0 +1,159 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+
+use crate::catalog::XCatalog;
+use crate::context::{markup_type, Literal, LiteralKey, PyObj, RenderContext};
+use crate::markup::tokens::{ToHtml, XElement, XFragment, XNode};
+
+const SLOT_PREFIX: &str = "slot:";
+
+/// The content given to a component for one of its slots.
+///
+/// The content is rendered when the component uses it, in the context
+/// of the caller of the component, with the values provided where it is
+/// rendered.
+#[pyclass(frozen)]
+pub struct XSlot {
+    node: XNode,
+    context: RenderContext,
+    catalog: XCatalog,
+}
+
+impl XSlot {
+    pub fn new(node: XNode, catalog: &XCatalog, context: &RenderContext) -> Self {
+        XSlot {
+            node,
+            context: context.clone(),
+            catalog: catalog.share(),
+        }
+    }
+
+    pub fn write_html<'py>(
+        &self,
+        py: Python<'py>,
+        provided: Arc<HashMap<String, Literal>>,
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()> {
+        let mut context = self.context.clone();
+        context.set_provided(provided);
+        self.catalog.write_node(py, &self.node, &mut context, out)
+    }
+
+    fn render<'py>(&self, py: Python<'py>) -> PyResult<String> {
+        let mut result = String::new();
+        self.write_html(py, self.context.provided(), &mut result)?;
+        Ok(result)
+    }
+}
+
+#[pymethods]
+impl XSlot {
+    fn __html__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        markup_type(py)?.call1((self.render(py)?,))
+    }
+
+    fn __str__<'py>(&self, py: Python<'py>) -> PyResult<String> {
+        self.render(py)
+    }
+
+    fn __repr__(&self) -> String {
+        format!("<slot {}>", self.node)
+    }
+}
+
+/// The slot given to a function is rendered, the function receives a markup
+/// string, as the children of the components before the slots.
+pub fn render_slot(py: Python<'_>, value: Literal) -> PyResult<Literal> {
+    if let Literal::Object(o) = &value {
+        if let Ok(slot) = o.obj().bind(py).downcast::<XSlot>() {
+            return Ok(Literal::Markup(slot.get().render(py)?));
+        }
+    }
+    Ok(value)
+}
+
+/// The name of the slot of a `<slot:name>` element.
+pub fn slot_name(element: &str) -> Option<&str> {
+    element.strip_prefix(SLOT_PREFIX)
+}
+
+/// Split the children of a component into the children
+/// and the content of its named slots.
+pub fn split_slots(children: Vec<XNode>) -> (Vec<XNode>, IndexMap<String, Vec<XNode>>) {
+    let mut slots: IndexMap<String, Vec<XNode>> = IndexMap::new();
+    let mut rest = Vec::with_capacity(children.len());
+    for child in children {
+        match &child {
+            XNode::Element(element) => match slot_name(element.name()) {
+                Some(name) => slots
+                    .entry(name.replace('-', "_"))
+                    .or_default()
+                    .extend(element.children()),
+                None => rest.push(child),
+            },
+            _ => rest.push(child),
+        }
+    }
+    (rest, slots)
+}
+
+/// Add the named slots to the props of a component.
+pub fn set_slots<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    context: &RenderContext,
+    slots: IndexMap<String, Vec<XNode>>,
+    node_attrs: &Bound<'py, PyDict>,
+) -> PyResult<()> {
+    for (name, children) in slots {
+        let node = XNode::Fragment(XFragment::new(children));
+        node_attrs.set_item(name, Py::new(py, XSlot::new(node, catalog, context))?)?;
+    }
+    Ok(())
+}
+
+/// The markup given as a prop is a slot too, rendered in the context
+/// of the caller.
+pub fn lazy_markup<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    context: &RenderContext,
+    value: Literal,
+) -> PyResult<Literal> {
+    match value {
+        Literal::XNode(node) => {
+            let slot = Py::new(py, XSlot::new(node, catalog, context))?;
+            Ok(Literal::Object(PyObj::new(slot.into_any())))
+        }
+        value => Ok(value),
+    }
+}
+
+/// Render the `<slot:name>` element of a component, the content of the slot,
+/// or the children of the element if the slot has not been given.
+pub fn write_slot<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    element: &XElement,
+    name: &str,
+    out: &mut dyn fmt::Write,
+) -> PyResult<()> {
+    match context
+        .get(&LiteralKey::Str(name.replace('-', "_")))
+        .cloned()
+    {
+        None | Some(Literal::None(())) => {
+            for child in element.children() {
+                child.write_html(py, catalog, context, out)?;
+            }
+            Ok(())
+        }
+        Some(value) => value.write_html(py, catalog, context, out),
+    }
+}
@@ -1,20 +1,65 @@
-use std::collections::HashMap;
+use std::fmt;
 
-use pyo3::{exceptions::PyValueError, prelude::*, types::PyDict, IntoPyObjectExt};
+use indexmap::IndexMap;
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+    types::{PyBytes, PyDict},
+};
 
 use crate::{
-    catalog::XCatalog,
-    context::{Literal, LiteralKey, RenderContext},
-    expression::ast::eval::eval_expression,
+    catalog::{
+        validation::{default_children, validate_props},
+        XCatalog,
+    },
+    context::{Literal, LiteralKey, PyObj, RenderContext},
+    error::render_error,
+    expression::{
+        ast::{
+            eval::{eval_expression, write_expression},
+            model::{CompiledAST, AST},
+            parse::compile,
+        },
+        parser::is_expression,
+    },
+    markup::attrs::{eval_attrs, has_spread, prop_name, SPREAD_PREFIX},
+    markup::escape::{to_json, Escape},
+    markup::provider::{write_provider, PROVIDER},
+    markup::slot::{lazy_markup, set_slots, slot_name, split_slots, write_slot},
+    serialize::{from_bytes, to_bytes, Decode, Decoder, Encode, Encoder},
+    span::Span,
 };
 
 pub trait ToHtml {
+    /// Render the html into the output, chunk by chunk.
+    fn write_html<'py>(
+        &self,
+        py: Python<'py>,
+        catalog: &XCatalog,
+        context: &mut RenderContext,
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()>;
+
     fn to_html<'py>(
         &self,
         py: Python<'py>,
         catalog: &XCatalog,
         context: &mut RenderContext,
-    ) -> PyResult<String>;
+    ) -> PyResult<String> {
+        let mut result = String::new();
+        self.write_html(py, catalog, context, &mut result)?;
+        Ok(result)
+    }
+}
+
+/// Write a chunk of html to the output.
+///
+/// Writing into a string never fails, the streamed output fails when
+/// its consumer is gone, and the rendering has to be stopped.
+#[inline]
+pub fn write_html(out: &mut dyn fmt::Write, html: &str) -> PyResult<()> {
+    out.write_str(html)
+        .map_err(|_| PyRuntimeError::new_err("Rendering interrupted, the output is closed"))
 }
 
 #[pyclass(eq, eq_int)]
@@ -34,13 +79,17 @@ pub enum NodeType {
 #[derive(Debug, Clone, PartialEq)]
 pub struct XFragment {
     children: Vec<XNode>,
+    span: Span,
 }
 
 #[pymethods]
 impl XFragment {
     #[new]
     pub fn new(children: Vec<XNode>) -> Self {
-        XFragment { children }
+        XFragment {
+            children,
+            span: Span::default(),
+        }
     }
 
     #[getter]
@@ -55,17 +104,17 @@ impl XFragment {
 }
 
 impl ToHtml for XFragment {
-    fn to_html<'py>(
+    fn write_html<'py>(
         &self,
         py: Python<'py>,
         catalog: &XCatalog,
         context: &mut RenderContext,
-    ) -> PyResult<String> {
-        let mut result = String::new();
-        for child in self.children() {
-            result.push_str(child.to_html(py, catalog, context)?.as_str())
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()> {
+        for child in self.children.iter() {
+            child.write_html(py, catalog, context, out)?;
         }
-        Ok(result)
+        Ok(())
     }
 }
 
@@ -73,14 +122,20 @@ impl ToHtml for XFragment {
 #[derive(Debug, Clone, PartialEq)]
 pub struct XScriptElement {
     name: String,
-    attrs: HashMap<String, XNode>,
+    attrs: IndexMap<String, XNode>,
     body: String,
+    span: Span,
 }
 #[pymethods]
 impl XScriptElement {
     #[new]
-    pub fn new(name: String, attrs: HashMap<String, XNode>, body: String) -> Self {
-        XScriptElement { name, attrs, body }
+    pub fn new(name: String, attrs: IndexMap<String, XNode>, body: String) -> Self {
+        XScriptElement {
+            name,
+            attrs,
+            body,
+            span: Span::default(),
+        }
     }
 
     #[getter]
@@ -89,7 +144,7 @@ impl XScriptElement {
     }
 
     #[getter]
-    fn attrs(&self) -> HashMap<String, XNode> {
+    fn attrs(&self) -> IndexMap<String, XNode> {
         self.attrs.clone()
     }
 
@@ -104,28 +159,121 @@ impl XScriptElement {
     }
 }
 
+/// Types of script containing a JSON document instead of javascript.
+const JSON_SCRIPT_TYPES: [&str; 4] = [
+    "application/json",
+    "application/ld+json",
+    "importmap",
+    "speculationrules",
+];
+
+impl XScriptElement {
+    /// The expression of a JSON script that only contains an expression,
+    /// such as `<script type="application/json">{data}</script>`.
+    fn json_expression(&self) -> Option<XExpression> {
+        match self.attrs.get("type") {
+            Some(XNode::Text(t)) if JSON_SCRIPT_TYPES.contains(&t.text()) => (),
+            _ => return None,
+        }
+        let body = self
+            .body
+            .strip_suffix(format!("</{}>", self.name).as_str())?;
+        let expression = body.trim().strip_prefix('{')?.strip_suffix('}')?;
+        // a JSON object is not a valid expression, so it is kept as is.
+        if !is_expression(expression) {
+            return None;
+        }
+        let mut xexpression = XExpression::new(expression.to_string());
+        if self.span.source().is_some() {
+            let start = self.span.end() - self.span.start() - self.body.len() + body.len()
+                - body.trim_start().len();
+            xexpression.span = self.span.slice(start, start + expression.len() + 2);
+        }
+        Some(xexpression)
+    }
+}
+
 impl ToHtml for XScriptElement {
-    fn to_html<'py>(
+    fn write_html<'py>(
         &self,
-        _py: Python<'py>,
-        _catalog: &XCatalog,
-        _context: &mut RenderContext,
-    ) -> PyResult<String> {
+        py: Python<'py>,
+        catalog: &XCatalog,
+        context: &mut RenderContext,
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()> {
         let mut result = String::new();
 
-        let joined_attrs = self
-            .attrs()
-            .iter()
-            .map(|(k, v)| format!(" {}=\"{}\"", k, v.__repr__()))
-            .collect::<String>();
+        result.push_str(format!("<{}", self.name()).as_str());
+        result.push_str(render_attrs(py, catalog, &self.name, &self.attrs, context)?.as_str());
+        result.push('>');
+        if let Some(expression) = self.json_expression() {
+            let value = eval_expression(py, &expression, catalog, context)?;
+            let json = to_json(py, catalog, context, &value)?;
+            result.push_str(Escape::Script.escape(json.as_str()).as_ref());
+            result.push_str(format!("</{}>", self.name()).as_str());
+        } else {
+            // The body ends wigh the closing tag, we don't need to add it.
+            result.push_str(self.body());
+        }
+
+        write_html(out, result.as_str())
+    }
+}
 
-        result.push_str(format!("<{}{}>", self.name(), joined_attrs).as_str());
-        result.push_str(format!("{}", self.body()).as_str());
-        // The body ends wigh the closing tag, we don't need to add it.
-        // result.push_str(format!("</{}>", self.name()).as_str());
+/// The props given to the component `<tag>` by the element: its attributes,
+/// its named slots and its children.
+fn component_props<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    context: &mut RenderContext,
+    tag: &str,
+    attrs: &IndexMap<String, XNode>,
+    children: Vec<XNode>,
+) -> PyResult<Bound<'py, PyDict>> {
+    let props = PyDict::new(py);
+    // the text attributes are escaped when rendered by the component
+    for (attrname, value) in eval_attrs(py, catalog, context, tag, attrs)? {
+        let value = lazy_markup(py, catalog, context, value)?;
+        props.set_item(prop_name(&attrname), value.into_py(py))?;
+    }
+    let (children, mut slots) = split_slots(children);
+    if children.len() > 0 {
+        slots
+            .entry("children".to_string())
+            .or_default()
+            .extend(children);
+    }
+    set_slots(py, catalog, context, slots, &props)?;
+    Ok(props)
+}
 
-        Ok(result)
+/// Render the attributes of an element, the spread attributes are evaluated
+/// and merged with the other attributes.
+fn render_attrs<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    tag: &str,
+    attrs: &IndexMap<String, XNode>,
+    context: &mut RenderContext,
+) -> PyResult<String> {
+    let mut result = String::new();
+    if has_spread(attrs) {
+        for (name, value) in eval_attrs(py, catalog, context, tag, attrs)? {
+            match (attrs.get(&name), &value) {
+                (Some(XNode::Text(text)), Literal::Str(s)) if text.text() == s => {
+                    result.push_str(render_text_attr(text.text(), name.as_str()).as_str())
+                }
+                _ => result.push_str(
+                    render_attr_value(py, catalog, value, name.as_str(), context)?.as_str(),
+                ),
+            }
+        }
+    } else {
+        for (name, node) in attrs.iter() {
+            result.push_str(render_attr(py, catalog, node, name.as_str(), context)?.as_str());
+        }
     }
+    Ok(result)
 }
 
 #[inline]
@@ -136,41 +284,76 @@ fn render_attr<'py>(
     name: &str,
     context: &mut RenderContext,
 ) -> PyResult<String> {
-    let value = catalog.render_node(py, &node, context)?;
-    let attr = if value.contains('"') {
-        format!(" {}='{}'", name, value.replace('\'', "\\'"))
-    } else {
-        format!(" {}=\"{}\"", name, value)
+    let escape = Escape::for_attribute(name);
+    let value = match node {
+        XNode::Expression(expr) => {
+            let value = eval_expression(py, expr, catalog, context)?;
+            return render_attr_value(py, catalog, value, name, context);
+        }
+        XNode::Text(text) => return Ok(render_text_attr(text.text(), name)),
+        _ => escape
+            .escape_markup(catalog.render_node(py, node, context)?.as_str())
+            .to_string(),
+    };
+    Ok(format!(" {}=\"{}\"", name, value))
+}
+
+/// Render a text attribute written in the template, it is trusted, so the
+/// URLs, such as `href="javascript:void(0)"`, are kept as written.
+#[inline]
+fn render_text_attr(text: &str, name: &str) -> String {
+    format!(" {}=\"{}\"", name, Escape::Attribute.escape(text))
+}
+
+#[inline]
+fn render_attr_value<'py>(
+    py: Python<'py>,
+    catalog: &XCatalog,
+    value: Literal,
+    name: &str,
+    context: &mut RenderContext,
+) -> PyResult<String> {
+    let escape = Escape::for_attribute(name);
+    let value = match value {
+        Literal::None(()) | Literal::Bool(false) => return Ok("".to_string()),
+        Literal::Bool(true) => return Ok(format!(" {}", name)),
+        Literal::Str(s) => escape.escape(s.as_str()).to_string(),
+        Literal::Markup(s) => escape.escape_markup(s.as_str()).to_string(),
+        _ => escape
+            .escape_markup(value.to_html(py, catalog, context)?.as_str())
+            .to_string(),
     };
-    Ok(attr)
+    Ok(format!(" {}=\"{}\"", name, value))
 }
 
 #[pyclass(eq)]
 #[derive(Debug, Clone, PartialEq)]
 pub struct XElement {
     name: String,
-    attrs: HashMap<String, XNode>,
+    attrs: IndexMap<String, XNode>,
     children: Vec<XNode>,
+    span: Span,
 }
 
 #[pymethods]
 impl XElement {
     #[new]
-    pub fn new(name: String, attrs: HashMap<String, XNode>, children: Vec<XNode>) -> Self {
+    pub fn new(name: String, attrs: IndexMap<String, XNode>, children: Vec<XNode>) -> Self {
         XElement {
             name,
             attrs,
             children,
+            span: Span::default(),
         }
     }
 
     #[getter]
-    fn name(&self) -> &str {
+    pub fn name(&self) -> &str {
         self.name.as_str()
     }
 
     #[getter]
-    pub fn attrs(&self) -> HashMap<String, XNode> {
+    pub fn attrs(&self) -> IndexMap<String, XNode> {
         self.attrs.clone()
     }
 
@@ -186,15 +369,14 @@ impl XElement {
 }
 
 impl ToHtml for XElement {
-    fn to_html<'py>(
+    fn write_html<'py>(
         &self,
         py: Python<'py>,
         catalog: &XCatalog,
         context: &mut RenderContext,
-    ) -> PyResult<String> {
-        let mut result = String::new();
-
-        match catalog.get(py, self.name()) {
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()> {
+        match catalog.get(py, self.name())? {
             Some(py_template) => {
                 debug!("Rendering template {}", py_template);
                 let namespaces = py_template
@@ -203,77 +385,68 @@ impl ToHtml for XElement {
                     .copy()?;
                 context.push_ns(py, namespaces.clone())?;
 
-                let node = py_template.getattr("node")?.extract::<XNode>()?;
-                let node_attrs = py_template
-                    .getattr("defaults")?
-                    .downcast::<PyDict>()?
-                    .copy()?;
-
-                for (name, attrnode) in self.attrs() {
-                    let name = match name.as_str() {
-                        "class" => "class_".to_string(),
-                        "for" => "for_".to_string(),
-                        _ => name.replace('-', "_"),
-                    };
-                    if let XNode::Expression(ref expression) = attrnode {
-                        let node_attr_v =
-                            eval_expression(py, expression.expression(), &catalog, context)?;
-                        node_attrs.set_item(name, node_attr_v.into_py(py))?;
-                    } else {
-                        node_attrs.set_item(
-                            name,
-                            Literal::Str(catalog.render_node(py, &attrnode, context)?),
-                        )?;
-                    }
-                }
+                let template = py_template.borrow();
+                let node = template.node(py).borrow();
+                let defaults = py_template.getattr("defaults")?;
+                let defaults = defaults.downcast::<PyDict>()?;
+                let node_attrs = defaults.copy()?;
+
+                let props = component_props(
+                    py,
+                    catalog,
+                    context,
+                    &self.name,
+                    &self.attrs,
+                    self.children(),
+                )?;
+                let params = py_template.getattr("params")?;
+                let params = params.downcast::<PyDict>()?;
+                default_children(py, params, defaults, &props)?;
+                validate_props(
+                    py,
+                    catalog.validation(),
+                    &self.name,
+                    params,
+                    defaults,
+                    &props,
+                )?;
+                node_attrs.update(props.as_mapping())?;
                 debug!("Rendered node_attrs {:?}", node_attrs);
-                if self.children().len() > 0 {
-                    let mut childchildren = String::new();
-                    for child in self.children() {
-                        childchildren.push_str(child.to_html(py, catalog, context)?.as_str())
-                    }
-                    node_attrs.set_item("children", childchildren)?;
-                }
 
                 let mut shadow_context = context.shadow();
                 shadow_context.push(py, node_attrs)?;
-                result.push_str(
-                    catalog
-                        .render_node(py, &node, &mut shadow_context)?
-                        .as_str(),
-                );
+                catalog
+                    .write_node(py, &node, &mut shadow_context, out)
+                    .map_err(|err| render_error(py, err, || format!("<{}>", self.name)))?;
                 context.pop_ns();
             }
+            None if self.name == PROVIDER => {
+                write_provider(py, catalog, context, &self.attrs, &self.children, out)?;
+            }
+            None if slot_name(&self.name).is_some() => {
+                let name = slot_name(&self.name).unwrap();
+                write_slot(py, catalog, context, self, name, out)?;
+            }
             None => {
                 debug!("Rendering final element <{}/>", self.name);
+                let mut result = String::new();
                 result.push_str(format!("<{}", self.name).as_str());
-                for (name, node) in self.attrs() {
-                    let attr = match node {
-                        XNode::Expression(ref expr) => {
-                            let v = eval_expression(py, expr.expression(), &catalog, context)?;
-                            match v {
-                                Literal::None(()) => "".to_string(),
-                                Literal::Bool(false) => "".to_string(),
-                                Literal::Bool(true) => format!(" {}", name),
-                                _ => render_attr(py, catalog, &node, name.as_str(), context)?,
-                            }
-                        }
-                        _ => render_attr(py, catalog, &node, name.as_str(), context)?,
-                    };
-                    result.push_str(format!("{}", attr).as_str());
-                }
-                if self.children().len() > 0 {
+                let attrs = render_attrs(py, catalog, &self.name, &self.attrs, context)?;
+                result.push_str(attrs.as_str());
+                if self.children.len() > 0 {
                     result.push_str(">");
-                    for child in self.children() {
-                        result.push_str(child.to_html(py, catalog, context)?.as_str())
+                    write_html(out, result.as_str())?;
+                    for child in self.children.iter() {
+                        child.write_html(py, catalog, context, out)?;
                     }
-                    result.push_str(format!("</{}>", self.name).as_str());
+                    write_html(out, format!("</{}>", self.name).as_str())?;
                 } else {
                     result.push_str("/>");
+                    write_html(out, result.as_str())?;
                 }
             }
         }
-        Ok(result)
+        Ok(())
     }
 }
 
@@ -282,8 +455,9 @@ impl ToHtml for XElement {
 pub struct XNSElement {
     namespace: String,
     name: String,
-    attrs: HashMap<String, XNode>,
+    attrs: IndexMap<String, XNode>,
     children: Vec<XNode>,
+    span: Span,
 }
 
 #[pymethods]
@@ -292,7 +466,7 @@ impl XNSElement {
     pub fn new(
         namespace: String,
         name: String,
-        attrs: HashMap<String, XNode>,
+        attrs: IndexMap<String, XNode>,
         children: Vec<XNode>,
     ) -> Self {
         XNSElement {
@@ -300,6 +474,7 @@ impl XNSElement {
             name,
             attrs,
             children,
+            span: Span::default(),
         }
     }
 
@@ -314,7 +489,7 @@ impl XNSElement {
     }
 
     #[getter]
-    pub fn attrs(&self) -> HashMap<String, XNode> {
+    pub fn attrs(&self) -> IndexMap<String, XNode> {
         self.attrs.clone()
     }
 
@@ -330,85 +505,103 @@ impl XNSElement {
 }
 
 impl XNSElement {
-    fn get_catalog(&self, context: &RenderContext) -> PyResult<Literal> {
-        let rnscatalog = context.get(&LiteralKey::Str(self.namespace.clone()));
-        if rnscatalog.is_none() {
-            error!("{:?}", context);
-            return Err(PyValueError::new_err(format!(
-                "Reference to unknown catalog {}",
-                self.namespace
-            )));
+    /// The catalog of the namespace, the first level of a nested namespace,
+    /// such as `ui` in `ui.forms`, is given by the context or by the
+    /// namespaces of the catalog, the next levels are the namespaces of
+    /// the catalog of their parent.
+    fn get_catalog(
+        &self,
+        py: Python<'_>,
+        catalog: &XCatalog,
+        context: &RenderContext,
+    ) -> PyResult<Literal> {
+        let unknown =
+            || PyValueError::new_err(format!("Reference to unknown catalog {}", self.namespace));
+        let mut parts = self.namespace.split('.');
+        let root = parts.next().unwrap_or_default();
+        let mut nscatalog = match context.get(&LiteralKey::Str(root.to_string())) {
+            Some(nscatalog) => nscatalog.clone(),
+            // the namespaces of the directories loaded in the catalog
+            None => match catalog.namespace(py, root) {
+                Some(nscatalog) => Literal::Object(PyObj::new(nscatalog.into_any().unbind())),
+                None => {
+                    error!("{:?}", context);
+                    return Err(unknown());
+                }
+            },
+        };
+        for part in parts {
+            let child = match &nscatalog {
+                Literal::Object(o) => o
+                    .obj()
+                    .bind(py)
+                    .downcast::<XCatalog>()
+                    .ok()
+                    .and_then(|parent| parent.borrow().namespace(py, part)),
+                _ => None,
+            };
+            let child = child.ok_or_else(unknown)?;
+            nscatalog = Literal::Object(PyObj::new(child.into_any().unbind()));
         }
-        Ok(rnscatalog.unwrap().clone())
+        Ok(nscatalog)
     }
 }
 impl ToHtml for XNSElement {
-    fn to_html<'py>(
+    fn write_html<'py>(
         &self,
         py: Python<'py>,
         catalog: &XCatalog,
         context: &mut RenderContext,
-    ) -> PyResult<String> {
-        let mut result = String::new();
-        let nscatalog = self.get_catalog(&context)?;
-        match &nscatalog {
-            Literal::Object(o) => {
-                // result.push_str(format!("{:?}", nscatalog).as_str());
-                let template = o.obj().call_method1(py, "get", (self.name(),))?;
-                let xnode = template.getattr(py, "node")?;
-
-                let namespaces = template.getattr(py, "namespaces")?;
-                let pynamespaces: &Bound<'_, PyDict> = namespaces.bind(py).downcast().unwrap();
-
-                let defaults = template.getattr(py, "defaults")?;
-                let node_attrs = defaults.bind(py).downcast().unwrap().copy().unwrap();
-
-                context.push_ns(py, pynamespaces.clone())?;
-                for (name, attrnode) in self.attrs() {
-                    let name = match name.as_str() {
-                        "class" => "class_".to_string(),
-                        "for" => "for_".to_string(),
-                        _ => name.replace('-', "_"),
-                    };
-                    if let XNode::Expression(ref expression) = attrnode {
-                        let node_attr_v =
-                            eval_expression(py, expression.expression(), &catalog, context)?;
-                        node_attrs.set_item(name, node_attr_v.into_py(py))?;
-                    } else {
-                        node_attrs.set_item(
-                            name,
-                            Literal::Str(catalog.render_node(py, &attrnode, context)?),
-                        )?;
-                    }
-                }
-
-                if self.children().len() > 0 {
-                    let mut childchildren = String::new();
-                    for child in self.children() {
-                        childchildren.push_str(child.to_html(py, catalog, context)?.as_str())
-                    }
-                    node_attrs.set_item("children", childchildren)?;
-                }
-
-                let mut shadow_context = context.shadow();
-                shadow_context.push(py, node_attrs.clone())?;
-
-                let pycontext = shadow_context.into_py_any(py)?;
-                let res = o
-                    .obj()
-                    .call_method1(py, "render_node", (xnode, pycontext))?;
-
-                context.pop_ns();
-                result.push_str(format!("{}", res).as_str());
-            }
-            _ => {
-                return Err(PyValueError::new_err(format!(
-                    "Reference to catalog {} does not map to catalog: {:?}",
-                    self.namespace, nscatalog
-                )));
-            }
-        }
-        Ok(result)
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()> {
+        let reference = self.get_catalog(py, catalog, context)?;
+        let nscatalog = match &reference {
+            Literal::Object(o) => o.obj().bind(py).downcast::<XCatalog>().ok().cloned(),
+            _ => None,
+        };
+        let Some(nscatalog) = nscatalog else {
+            return Err(PyValueError::new_err(format!(
+                "Reference to catalog {} does not map to catalog: {:?}",
+                self.namespace, reference
+            )));
+        };
+        // the components of the namespace are rendered by a snapshot of its
+        // catalog, which can be updated while a rendering is suspended
+        let nscatalog = nscatalog.borrow().share();
+        let Some(py_template) = nscatalog.get(py, self.name())? else {
+            return Err(PyValueError::new_err(format!(
+                "Unknown component <{}.{}/>",
+                self.namespace, self.name
+            )));
+        };
+        let namespaces = py_template
+            .getattr("namespaces")?
+            .downcast::<PyDict>()?
+            .copy()?;
+        context.push_ns(py, namespaces)?;
+
+        let template = py_template.borrow();
+        let node = template.node(py).borrow();
+        let defaults = py_template.getattr("defaults")?;
+        let defaults = defaults.downcast::<PyDict>()?;
+        let node_attrs = defaults.copy()?;
+
+        let tag = format!("{}.{}", self.namespace, self.name);
+        let props = component_props(py, catalog, context, &tag, &self.attrs, self.children())?;
+        let params = py_template.getattr("params")?;
+        let params = params.downcast::<PyDict>()?;
+        default_children(py, params, defaults, &props)?;
+        // the props are validated by the catalog of the component
+        validate_props(py, nscatalog.validation(), &tag, params, defaults, &props)?;
+        node_attrs.update(props.as_mapping())?;
+
+        let mut shadow_context = context.shadow();
+        shadow_context.push(py, node_attrs)?;
+        nscatalog
+            .write_node(py, &node, &mut shadow_context, out)
+            .map_err(|err| render_error(py, err, || format!("<{}>", tag)))?;
+        context.pop_ns();
+        Ok(())
     }
 }
 
@@ -416,13 +609,17 @@ impl ToHtml for XNSElement {
 #[derive(Debug, Clone, PartialEq)]
 pub struct XDocType {
     doctype: String,
+    span: Span,
 }
 
 #[pymethods]
 impl XDocType {
     #[new]
     pub fn new(doctype: String) -> Self {
-        XDocType { doctype }
+        XDocType {
+            doctype,
+            span: Span::default(),
+        }
     }
 
     #[getter]
@@ -437,13 +634,14 @@ impl XDocType {
 }
 
 impl ToHtml for XDocType {
-    fn to_html<'py>(
+    fn write_html<'py>(
         &self,
         _: Python<'py>,
         _: &XCatalog,
         _: &mut RenderContext,
-    ) -> PyResult<String> {
-        Ok(format!("{}", self.doctype()))
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()> {
+        write_html(out, self.doctype())
     }
 }
 
@@ -451,13 +649,17 @@ impl ToHtml for XDocType {
 #[derive(Debug, Clone, PartialEq)]
 pub struct XComment {
     comment: String,
+    span: Span,
 }
 
 #[pymethods]
 impl XComment {
     #[new]
     pub fn new(comment: String) -> Self {
-        XComment { comment }
+        XComment {
+            comment,
+            span: Span::default(),
+        }
     }
 
     #[getter]
@@ -472,16 +674,17 @@ impl XComment {
 }
 
 impl ToHtml for XComment {
-    fn to_html<'py>(
+    fn write_html<'py>(
         &self,
         _: Python<'py>,
         _: &XCatalog,
         _: &mut RenderContext,
-    ) -> PyResult<String> {
-        Ok(format!(
-            "<!--{}-->",
-            html_escape::encode_text(self.comment())
-        ))
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()> {
+        write_html(
+            out,
+            format!("<!--{}-->", html_escape::encode_text(self.comment())).as_str(),
+        )
     }
 }
 
@@ -489,17 +692,21 @@ impl ToHtml for XComment {
 #[derive(Debug, Clone, PartialEq)]
 pub struct XText {
     text: String,
+    span: Span,
 }
 
 #[pymethods]
 impl XText {
     #[new]
     pub fn new(text: String) -> Self {
-        XText { text }
+        XText {
+            text,
+            span: Span::default(),
+        }
     }
 
     #[getter]
-    fn text(&self) -> &str {
+    pub fn text(&self) -> &str {
         self.text.as_str()
     }
 
@@ -510,13 +717,14 @@ impl XText {
 }
 
 impl ToHtml for XText {
-    fn to_html<'py>(
+    fn write_html<'py>(
         &self,
         _: Python<'py>,
         _: &XCatalog,
         _: &mut RenderContext,
-    ) -> PyResult<String> {
-        return Ok(html_escape::encode_text(self.text()).to_string());
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()> {
+        write_html(out, Escape::Text.escape(self.text()).as_ref())
     }
 }
 
@@ -524,13 +732,19 @@ impl ToHtml for XText {
 #[derive(Debug, Clone, PartialEq)]
 pub struct XExpression {
     expression: String,
+    span: Span,
+    ast: CompiledAST,
 }
 
 #[pymethods]
 impl XExpression {
     #[new]
     pub fn new(expression: String) -> Self {
-        XExpression { expression }
+        XExpression {
+            expression,
+            span: Span::default(),
+            ast: CompiledAST::default(),
+        }
     }
 
     #[getter]
@@ -549,24 +763,41 @@ impl XExpression {
         catalog: &XCatalog,
         context: &mut RenderContext,
     ) -> PyResult<Literal> {
-        eval_expression(py, self.expression(), catalog, context)
+        eval_expression(py, self, catalog, context)
+    }
+}
+
+impl XExpression {
+    /// The AST of the expression, compiled once and reused by every rendering.
+    pub fn compiled(&self) -> PyResult<Option<&AST>> {
+        self.ast
+            .get_or_compile(|| compile(self.expression(), &self.expression_span()))
+    }
+
+    /// The span of the expression, without its curly braces.
+    pub fn expression_span(&self) -> Span {
+        if self.span.source().is_some() {
+            self.span.slice(1, 1 + self.expression.len())
+        } else {
+            self.span.clone()
+        }
     }
 }
 
 impl ToHtml for XExpression {
-    fn to_html<'py>(
+    fn write_html<'py>(
         &self,
         py: Python<'py>,
         catalog: &XCatalog,
         context: &mut RenderContext,
-    ) -> PyResult<String> {
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()> {
         info!("Evaluating xexpression {}", self.expression());
-        let res = self.to_literal(py, catalog, context)?;
-        res.to_html(py, catalog, context)
+        write_expression(py, self, catalog, context, out)
     }
 }
 
-#[pyclass]
+#[pyclass(module = "xcomponent.xcore")]
 #[derive(Debug, Clone, PartialEq)]
 pub enum XNode {
     Fragment(XFragment),
@@ -579,6 +810,15 @@ pub enum XNode {
     Expression(XExpression),
 }
 
+fn repr_attr(name: &str, node: &XNode) -> String {
+    match node {
+        XNode::Expression(expression) if name.starts_with(SPREAD_PREFIX) => {
+            format!(" {{...{}}}", expression.expression())
+        }
+        _ => format!(" {}=\"{}\"", name, node.__repr__()),
+    }
+}
+
 impl std::fmt::Display for XNode {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -586,10 +826,11 @@ impl std::fmt::Display for XNode {
                 name,
                 attrs,
                 children,
+                ..
             }) => {
                 let joined_attrs = attrs
                     .iter()
-                    .map(|(k, v)| format!(" {}=\"{}\"", k, v.__repr__()))
+                    .map(|(k, v)| repr_attr(k, v))
                     .collect::<String>();
 
                 if children.is_empty() {
@@ -608,10 +849,11 @@ impl std::fmt::Display for XNode {
                 name,
                 attrs,
                 children,
+                ..
             }) => {
                 let joined_attrs = attrs
                     .iter()
-                    .map(|(k, v)| format!(" {}=\"{}\"", k, v.__repr__()))
+                    .map(|(k, v)| repr_attr(k, v))
                     .collect::<String>();
 
                 if children.is_empty() {
@@ -624,10 +866,12 @@ impl std::fmt::Display for XNode {
                     write!(f, "</{}.{}>", namespace, name)
                 }
             }
-            XNode::ScriptElement(XScriptElement { name, attrs, body }) => {
+            XNode::ScriptElement(XScriptElement {
+                name, attrs, body, ..
+            }) => {
                 let joined_attrs = attrs
                     .iter()
-                    .map(|(k, v)| format!(" {}=\"{}\"", k, v.__repr__()))
+                    .map(|(k, v)| repr_attr(k, v))
                     .collect::<String>();
 
                 write!(f, "<{}{}>", name, joined_attrs)?;
@@ -635,17 +879,17 @@ impl std::fmt::Display for XNode {
                 write!(f, "</{}>", name)
             }
 
-            XNode::Fragment(XFragment { children }) => {
+            XNode::Fragment(XFragment { children, .. }) => {
                 write!(
                     f,
                     "<>{}</>",
                     children.iter().map(|v| v.__repr__()).collect::<String>()
                 )
             }
-            XNode::DocType(XDocType { doctype }) => write!(f, "{}", doctype),
-            XNode::Text(XText { text }) => write!(f, "{}", text),
-            XNode::Comment(XComment { comment }) => write!(f, "<!--{}-->", comment),
-            XNode::Expression(XExpression { expression }) => write!(f, "{{{}}}", expression),
+            XNode::DocType(XDocType { doctype, .. }) => write!(f, "{}", doctype),
+            XNode::Text(XText { text, .. }) => write!(f, "{}", text),
+            XNode::Comment(XComment { comment, .. }) => write!(f, "<!--{}-->", comment),
+            XNode::Expression(XExpression { expression, .. }) => write!(f, "{{{}}}", expression),
         }
     }
 }
@@ -673,6 +917,26 @@ impl XNode {
         self == other
     }
 
+    /// The node, and its compiled expressions, in a binary format.
+    fn __getstate__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        Ok(PyBytes::new(py, &to_bytes(self)?))
+    }
+
+    /// The node is rebuilt from its state, by [`XNode::from_state`].
+    fn __reduce__<'py>(
+        &self,
+        py: Python<'py>,
+    ) -> PyResult<(Bound<'py, PyAny>, (Bound<'py, PyBytes>,))> {
+        let from_state = py.get_type::<XNode>().getattr("from_state")?;
+        Ok((from_state, (self.__getstate__(py)?,)))
+    }
+
+    /// The node of a state returned by `__getstate__`.
+    #[staticmethod]
+    fn from_state(state: &[u8]) -> PyResult<XNode> {
+        from_bytes(state)
+    }
+
     #[pyo3(signature = ())]
     pub fn unwrap(&self, py: Python<'_>) -> PyObject {
         match self {
@@ -723,6 +987,62 @@ impl XNode {
     }
 }
 
+impl XNode {
+    /// The position of the node in its template.
+    pub fn span(&self) -> &Span {
+        match self {
+            XNode::Fragment(n) => &n.span,
+            XNode::ScriptElement(n) => &n.span,
+            XNode::Element(n) => &n.span,
+            XNode::NSElement(n) => &n.span,
+            XNode::DocType(n) => &n.span,
+            XNode::Text(n) => &n.span,
+            XNode::Comment(n) => &n.span,
+            XNode::Expression(n) => &n.span,
+        }
+    }
+
+    pub fn with_span(mut self, span: Span) -> Self {
+        match &mut self {
+            XNode::Fragment(n) => n.span = span,
+            XNode::ScriptElement(n) => n.span = span,
+            XNode::Element(n) => n.span = span,
+            XNode::NSElement(n) => n.span = span,
+            XNode::DocType(n) => n.span = span,
+            XNode::Text(n) => n.span = span,
+            XNode::Comment(n) => n.span = span,
+            XNode::Expression(n) => n.span = span,
+        }
+        self
+    }
+
+    /// True if an expression of the node references the variable `name`,
+    /// an expression that cannot be compiled may reference it.
+    pub fn references(&self, name: &str) -> bool {
+        let any = |attrs: &IndexMap<String, XNode>, children: &[XNode]| {
+            attrs
+                .values()
+                .chain(children)
+                .any(|node| node.references(name))
+        };
+        match self {
+            XNode::Fragment(n) => n.children.iter().any(|node| node.references(name)),
+            XNode::Element(n) => any(&n.attrs, &n.children),
+            XNode::NSElement(n) => any(&n.attrs, &n.children),
+            XNode::ScriptElement(n) => {
+                any(&n.attrs, &[])
+                    || n.json_expression()
+                        .is_some_and(|expr| XNode::Expression(expr).references(name))
+            }
+            XNode::Expression(n) => match n.compiled() {
+                Ok(ast) => ast.is_some_and(|ast| ast.references(name)),
+                Err(_) => true,
+            },
+            XNode::DocType(_) | XNode::Text(_) | XNode::Comment(_) => false,
+        }
+    }
+}
+
 impl<'py> IntoPyObject<'py> for &'py XNode {
     type Target = XNode;
 
@@ -737,22 +1057,153 @@ impl<'py> IntoPyObject<'py> for &'py XNode {
 }
 
 impl ToHtml for XNode {
-    fn to_html<'py>(
+    fn write_html<'py>(
         &self,
         py: Python<'py>,
         catalog: &XCatalog,
         context: &mut RenderContext,
-    ) -> PyResult<String> {
+        out: &mut dyn fmt::Write,
+    ) -> PyResult<()> {
         debug!("Rendering {:?} with {:?}", self, context);
+        let res = match self {
+            XNode::Fragment(f) => f.write_html(py, catalog, context, out),
+            XNode::Element(e) => e.write_html(py, catalog, context, out),
+            XNode::NSElement(e) => e.write_html(py, catalog, context, out),
+            XNode::ScriptElement(e) => e.write_html(py, catalog, context, out),
+            XNode::DocType(d) => d.write_html(py, catalog, context, out),
+            XNode::Text(t) => t.write_html(py, catalog, context, out),
+            XNode::Comment(c) => c.write_html(py, catalog, context, out),
+            XNode::Expression(e) => e.write_html(py, catalog, context, out),
+        };
+        res.map_err(|err| self.span().locate(py, err))
+    }
+}
+
+impl Encode for XNode {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
         match self {
-            XNode::Fragment(f) => f.to_html(py, catalog, context),
-            XNode::Element(e) => e.to_html(py, catalog, context),
-            XNode::NSElement(e) => e.to_html(py, catalog, context),
-            XNode::ScriptElement(e) => e.to_html(py, catalog, context),
-            XNode::DocType(d) => d.to_html(py, catalog, context),
-            XNode::Text(t) => t.to_html(py, catalog, context),
-            XNode::Comment(c) => c.to_html(py, catalog, context),
-            XNode::Expression(e) => e.to_html(py, catalog, context),
+            XNode::Fragment(XFragment { children, span }) => {
+                enc.write_u8(0);
+                children.encode(enc)?;
+                span.encode(enc)
+            }
+            XNode::ScriptElement(XScriptElement {
+                name,
+                attrs,
+                body,
+                span,
+            }) => {
+                enc.write_u8(1);
+                name.encode(enc)?;
+                attrs.encode(enc)?;
+                body.encode(enc)?;
+                span.encode(enc)
+            }
+            XNode::Element(XElement {
+                name,
+                attrs,
+                children,
+                span,
+            }) => {
+                enc.write_u8(2);
+                name.encode(enc)?;
+                attrs.encode(enc)?;
+                children.encode(enc)?;
+                span.encode(enc)
+            }
+            XNode::NSElement(XNSElement {
+                namespace,
+                name,
+                attrs,
+                children,
+                span,
+            }) => {
+                enc.write_u8(3);
+                namespace.encode(enc)?;
+                name.encode(enc)?;
+                attrs.encode(enc)?;
+                children.encode(enc)?;
+                span.encode(enc)
+            }
+            XNode::DocType(XDocType { doctype, span }) => {
+                enc.write_u8(4);
+                doctype.encode(enc)?;
+                span.encode(enc)
+            }
+            XNode::Text(XText { text, span }) => {
+                enc.write_u8(5);
+                text.encode(enc)?;
+                span.encode(enc)
+            }
+            XNode::Comment(XComment { comment, span }) => {
+                enc.write_u8(6);
+                comment.encode(enc)?;
+                span.encode(enc)
+            }
+            XNode::Expression(
+                expr @ XExpression {
+                    expression,
+                    span,
+                    ast,
+                },
+            ) => {
+                // the expressions are compiled before being saved, the
+                // invalid ones are saved as is, and fail on their rendering
+                let _ = expr.compiled();
+                enc.write_u8(7);
+                expression.encode(enc)?;
+                span.encode(enc)?;
+                ast.encode(enc)
+            }
         }
     }
 }
+
+impl Decode for XNode {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        let node = match dec.read_u8()? {
+            0 => XNode::Fragment(XFragment {
+                children: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+            }),
+            1 => XNode::ScriptElement(XScriptElement {
+                name: Decode::decode(dec)?,
+                attrs: Decode::decode(dec)?,
+                body: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+            }),
+            2 => XNode::Element(XElement {
+                name: Decode::decode(dec)?,
+                attrs: Decode::decode(dec)?,
+                children: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+            }),
+            3 => XNode::NSElement(XNSElement {
+                namespace: Decode::decode(dec)?,
+                name: Decode::decode(dec)?,
+                attrs: Decode::decode(dec)?,
+                children: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+            }),
+            4 => XNode::DocType(XDocType {
+                doctype: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+            }),
+            5 => XNode::Text(XText {
+                text: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+            }),
+            6 => XNode::Comment(XComment {
+                comment: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+            }),
+            7 => XNode::Expression(XExpression {
+                expression: Decode::decode(dec)?,
+                span: Decode::decode(dec)?,
+                ast: Decode::decode(dec)?,
+            }),
+            tag => return Err(dec.invalid("node", tag)),
+        };
+        Ok(node)
+    }
+}
@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::Arc;
+
+use indexmap::IndexMap;
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::span::Source;
+
+/// The prefix of the encoded data, followed by the version of the package,
+/// the data is decoded by the version that has encoded it.
+const MAGIC: &[u8] = b"xcomponent";
+
+/// A value encoded in the binary format of the parsed templates.
+pub trait Encode {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()>;
+}
+
+/// A value decoded from the binary format of the parsed templates.
+pub trait Decode: Sized {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self>;
+}
+
+/// Encode the value, the sources of the templates of its spans are
+/// written once, before the value.
+pub fn to_bytes<T: Encode>(value: &T) -> PyResult<Vec<u8>> {
+    let mut body = Encoder::default();
+    value.encode(&mut body)?;
+
+    let mut enc = Encoder::default();
+    enc.buf.extend_from_slice(MAGIC);
+    enc.write_str(env!("CARGO_PKG_VERSION"));
+    enc.write_usize(body.sources.len());
+    for source in body.sources.iter() {
+        source
+            .name()
+            .map(|name| name.to_string())
+            .encode(&mut enc)?;
+        enc.write_str(source.text());
+    }
+    enc.buf.extend_from_slice(&body.buf);
+    Ok(enc.buf)
+}
+
+pub fn from_bytes<T: Decode>(data: &[u8]) -> PyResult<T> {
+    let mut dec = Decoder {
+        data,
+        pos: 0,
+        sources: Vec::new(),
+    };
+    if dec.read_bytes(MAGIC.len()).ok() != Some(MAGIC) {
+        return Err(PyValueError::new_err(
+            "Invalid data, not encoded by xcomponent",
+        ));
+    }
+    let version = dec.read_str()?;
+    if version != env!("CARGO_PKG_VERSION") {
+        return Err(PyValueError::new_err(format!(
+            "Data encoded by xcomponent {}, it cannot be decoded by xcomponent {}",
+            version,
+            env!("CARGO_PKG_VERSION")
+        )));
+    }
+    for _ in 0..dec.read_usize()? {
+        let name = Option::<String>::decode(&mut dec)?;
+        let text = dec.read_str()?;
+        dec.sources.push(Source::new(name.as_deref(), &text));
+    }
+    let value = T::decode(&mut dec)?;
+    if dec.pos != data.len() {
+        return Err(PyValueError::new_err(
+            "Invalid data, unexpected trailing bytes",
+        ));
+    }
+    Ok(value)
+}
+
+#[derive(Default)]
+pub struct Encoder {
+    buf: Vec<u8>,
+    sources: Vec<Arc<Source>>,
+    source_ids: HashMap<*const Source, usize>,
+}
+
+impl Encoder {
+    pub fn write_u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    /// Unsigned integers are encoded by groups of 7 bits, the small
+    /// integers, such as lengths and positions, take a few bytes.
+    pub fn write_usize(&mut self, value: usize) {
+        let mut value = value;
+        while value >= 0x80 {
+            self.buf.push((value as u8) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    pub fn write_isize(&mut self, value: isize) {
+        // zigzag encoding, the small negative integers are small too
+        self.write_usize(((value << 1) ^ (value >> (isize::BITS - 1))) as usize);
+    }
+
+    pub fn write_str(&mut self, value: &str) {
+        self.write_usize(value.len());
+        self.buf.extend_from_slice(value.as_bytes());
+    }
+
+    /// Write the index of the source in the table of the sources.
+    pub fn write_source(&mut self, source: &Arc<Source>) {
+        let id = *self
+            .source_ids
+            .entry(Arc::as_ptr(source))
+            .or_insert_with(|| {
+                self.sources.push(source.clone());
+                self.sources.len() - 1
+            });
+        self.write_usize(id);
+    }
+}
+
+pub struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    sources: Vec<Arc<Source>>,
+}
+
+impl<'a> Decoder<'a> {
+    fn read_bytes(&mut self, len: usize) -> PyResult<&'a [u8]> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.data.get(self.pos..end))
+            .ok_or_else(|| PyValueError::new_err("Invalid data, unexpected end"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    pub fn read_u8(&mut self) -> PyResult<u8> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub fn read_usize(&mut self) -> PyResult<usize> {
+        let mut value: usize = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_u8()?;
+            if shift >= usize::BITS {
+                return Err(PyValueError::new_err("Invalid data, integer overflow"));
+            }
+            value |= ((byte & 0x7f) as usize) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+            shift += 7;
+        }
+    }
+
+    pub fn read_isize(&mut self) -> PyResult<isize> {
+        let value = self.read_usize()?;
+        Ok(((value >> 1) as isize) ^ -((value & 1) as isize))
+    }
+
+    pub fn read_str(&mut self) -> PyResult<String> {
+        let len = self.read_usize()?;
+        let bytes = self.read_bytes(len)?;
+        String::from_utf8(bytes.to_vec())
+            .map_err(|_| PyValueError::new_err("Invalid data, invalid utf-8 string"))
+    }
+
+    pub fn read_source(&mut self) -> PyResult<Arc<Source>> {
+        let id = self.read_usize()?;
+        self.sources
+            .get(id)
+            .cloned()
+            .ok_or_else(|| PyValueError::new_err("Invalid data, unknown source"))
+    }
+
+    /// The error of an unknown variant of an enum.
+    pub fn invalid(&self, kind: &str, tag: u8) -> PyErr {
+        PyValueError::new_err(format!("Invalid data, unknown {} {}", kind, tag))
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        enc.write_str(self);
+        Ok(())
+    }
+}
+
+impl Decode for String {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        dec.read_str()
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        enc.write_u8(*self as u8);
+        Ok(())
+    }
+}
+
+impl Decode for bool {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        match dec.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            tag => Err(dec.invalid("bool", tag)),
+        }
+    }
+}
+
+impl Encode for f64 {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        enc.buf.extend_from_slice(&self.to_le_bytes());
+        Ok(())
+    }
+}
+
+impl Decode for f64 {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        let bytes = dec.read_bytes(8)?;
+        Ok(f64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        match self {
+            None => {
+                enc.write_u8(0);
+                Ok(())
+            }
+            Some(value) => {
+                enc.write_u8(1);
+                value.encode(enc)
+            }
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        match dec.read_u8()? {
+            0 => Ok(None),
+            1 => Ok(Some(T::decode(dec)?)),
+            tag => Err(dec.invalid("option", tag)),
+        }
+    }
+}
+
+impl<T: Encode> Encode for Box<T> {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        self.as_ref().encode(enc)
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        Ok(Box::new(T::decode(dec)?))
+    }
+}
+
+impl<T: Encode> Encode for Arc<T> {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        self.as_ref().encode(enc)
+    }
+}
+
+impl<T: Decode> Decode for Arc<T> {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        Ok(Arc::new(T::decode(dec)?))
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        self.0.encode(enc)?;
+        self.1.encode(enc)
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        Ok((A::decode(dec)?, B::decode(dec)?))
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        enc.write_usize(self.len());
+        self.iter().try_for_each(|value| value.encode(enc))
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        let len = dec.read_usize()?;
+        // the length is not trusted to allocate
+        let mut values = Vec::with_capacity(len.min(1024));
+        for _ in 0..len {
+            values.push(T::decode(dec)?);
+        }
+        Ok(values)
+    }
+}
+
+impl<K: Encode, V: Encode> Encode for IndexMap<K, V> {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        enc.write_usize(self.len());
+        for (key, value) in self.iter() {
+            key.encode(enc)?;
+            value.encode(enc)?;
+        }
+        Ok(())
+    }
+}
+
+impl<K: Decode + Hash + Eq, V: Decode> Decode for IndexMap<K, V> {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        Ok(Vec::<(K, V)>::decode(dec)?.into_iter().collect())
+    }
+}
+
+impl<K: Encode, V: Encode> Encode for HashMap<K, V> {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        enc.write_usize(self.len());
+        for (key, value) in self.iter() {
+            key.encode(enc)?;
+            value.encode(enc)?;
+        }
+        Ok(())
+    }
+}
+
+impl<K: Decode + Hash + Eq, V: Decode> Decode for HashMap<K, V> {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        Ok(Vec::<(K, V)>::decode(dec)?.into_iter().collect())
+    }
+}
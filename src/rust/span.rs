@@ -0,0 +1,166 @@
+use std::fmt;
+use std::sync::Arc;
+
+use pyo3::prelude::*;
+
+use crate::serialize::{Decode, Decoder, Encode, Encoder};
+
+/// Set on an exception once it has been located in a template.
+const LOCATED_ATTR: &str = "__xcomponent_located__";
+
+/// The raw template a node has been parsed from.
+#[derive(Debug)]
+pub struct Source {
+    name: Option<String>,
+    text: String,
+}
+
+impl Source {
+    pub fn new(name: Option<&str>, text: &str) -> Arc<Self> {
+        Arc::new(Source {
+            name: name.map(|n| n.to_string()),
+            text: text.to_string(),
+        })
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// Position of a node in the source of its template, as byte offsets.
+#[derive(Clone, Default)]
+pub struct Span {
+    start: usize,
+    end: usize,
+    source: Option<Arc<Source>>,
+}
+
+impl Span {
+    pub fn new(source: &Arc<Source>, start: usize, end: usize) -> Self {
+        Span {
+            start,
+            end,
+            source: Some(source.clone()),
+        }
+    }
+
+    /// The span of a pest pair parsed from a slice of the source
+    /// starting at `offset`.
+    pub fn from_pest(source: &Arc<Source>, offset: usize, span: pest::Span<'_>) -> Self {
+        Span::new(source, offset + span.start(), offset + span.end())
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn source(&self) -> Option<&Arc<Source>> {
+        self.source.as_ref()
+    }
+
+    /// A sub span of this span, `start` and `end` are relative to this span.
+    pub fn slice(&self, start: usize, end: usize) -> Self {
+        Span {
+            start: self.start + start,
+            end: self.start + end,
+            source: self.source.clone(),
+        }
+    }
+
+    /// Human readable location of the span, with a snippet of the source.
+    pub fn describe(&self) -> Option<String> {
+        let source = self.source.as_ref()?;
+        let text = source.text.as_str();
+        let start = self.start.min(text.len());
+        let line_start = text[..start].rfind('\n').map(|i| i + 1).unwrap_or(0);
+        let line_end = text[start..]
+            .find('\n')
+            .map(|i| start + i)
+            .unwrap_or(text.len());
+        let lineno = text[..start].matches('\n').count() + 1;
+        let col = text[line_start..start].chars().count() + 1;
+        let width = text[start..self.end.clamp(start, line_end)]
+            .chars()
+            .count()
+            .max(1);
+
+        let location = match source.name.as_ref() {
+            Some(name) => format!("component `{}`, line {}, col {}", name, lineno, col),
+            None => format!("template, line {}, col {}", lineno, col),
+        };
+        let gutter = " ".repeat(lineno.to_string().len());
+        Some(format!(
+            "{location}\n{gutter} |\n{lineno} | {line}\n{gutter} | {pad}{caret}",
+            line = &text[line_start..line_end],
+            pad = " ".repeat(col - 1),
+            caret = "^".repeat(width),
+        ))
+    }
+
+    /// Add the location of the span to the error, as a note, unless the error
+    /// has already been located by a nested node.
+    pub fn locate(&self, py: Python<'_>, err: PyErr) -> PyErr {
+        let value = err.value(py);
+        if value.hasattr(LOCATED_ATTR).unwrap_or(true) {
+            return err;
+        }
+        if let Some(location) = self.describe() {
+            if value.setattr(LOCATED_ATTR, true).is_ok() {
+                if let Err(e) = value.call_method1("add_note", (location,)) {
+                    warn!("Cannot locate error: {}", e);
+                }
+            }
+        }
+        err
+    }
+}
+
+impl fmt::Debug for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// The position is not part of the identity of a node.
+impl PartialEq for Span {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Encode for Span {
+    fn encode(&self, enc: &mut Encoder) -> PyResult<()> {
+        enc.write_usize(self.start);
+        enc.write_usize(self.end);
+        match self.source.as_ref() {
+            Some(source) => {
+                enc.write_u8(1);
+                enc.write_source(source);
+            }
+            None => enc.write_u8(0),
+        }
+        Ok(())
+    }
+}
+
+impl Decode for Span {
+    fn decode(dec: &mut Decoder<'_>) -> PyResult<Self> {
+        let start = dec.read_usize()?;
+        let end = dec.read_usize()?;
+        let source = match dec.read_u8()? {
+            0 => None,
+            1 => Some(dec.read_source()?),
+            tag => return Err(dec.invalid("span", tag)),
+        };
+        Ok(Span { start, end, source })
+    }
+}
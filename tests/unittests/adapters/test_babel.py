@@ -21,6 +21,11 @@ empty_comment: list[str] = []
             [(1, "gettext", "a small text", empty_comment)],
             id="small",
         ),
+        pytest.param(
+            "{globals.gettext('a piped text') | upper}",
+            [(1, "gettext", "a piped text", empty_comment)],
+            id="pipe",
+        ),
         pytest.param(
             """
             {
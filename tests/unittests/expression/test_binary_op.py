@@ -75,6 +75,11 @@ def DivOp(a: int | bool | str, b: int | bool | str) -> str:
     return """<>{a / b}</>"""
 
 
+@catalog.component
+def FloorDivOp(a: int | float | bool, b: int | float | bool) -> str:
+    return """<>{a // b}</>"""
+
+
 @catalog.component
 def AddMany(a: int | bool | str, b: int | bool | str, c: int | bool | str) -> str:
     return """<>{a + b + c}</>"""
@@ -170,16 +175,32 @@ def test_mul(component: str, expected: str):
 @pytest.mark.parametrize(
     "component,expected",
     [
-        pytest.param(DivOp(8, 2), "4", id="mul int"),
-        pytest.param(DivOp(2, 3), "0", id="mul int-2/3"),
-        pytest.param(DivOp(True, 3), "0", id="mul bool and int"),
-        pytest.param(DivOp(2, True), "2", id="mul true-true"),
+        pytest.param(DivOp(8, 2), "4.0", id="mul int"),
+        pytest.param(DivOp(2, 3), "0.6666666666666666", id="mul int-2/3"),
+        pytest.param(DivOp(True, 3), "0.3333333333333333", id="mul bool and int"),
+        pytest.param(DivOp(2, True), "2.0", id="mul true-true"),
     ],
 )
 def test_div(component: str, expected: str):
     assert component == expected
 
 
+@pytest.mark.parametrize(
+    "component,expected",
+    [
+        pytest.param(FloorDivOp(8, 2), "4", id="int"),
+        pytest.param(FloorDivOp(7, 2), "3", id="int-7//2"),
+        pytest.param(FloorDivOp(-7, 2), "-4", id="negative int"),
+        pytest.param(FloorDivOp(7, -2), "-4", id="negative divisor"),
+        pytest.param(FloorDivOp(True, 3), "0", id="bool and int"),
+        pytest.param(FloorDivOp(7.5, 2), "3.0", id="float"),
+        pytest.param(FloorDivOp(-7.5, 2), "-4.0", id="negative float"),
+    ],
+)
+def test_floordiv(component: str, expected: str):
+    assert component == expected
+
+
 @pytest.mark.parametrize(
     "component,args,expected",
     [
@@ -209,6 +230,8 @@ def test_type_error(component: Component, args: Any, expected: str):
     [
         pytest.param(DivOp, (4, 0), "Division by zero", id="int"),
         pytest.param(DivOp, (1, False), "Division by zero", id="bool"),
+        pytest.param(DivOp, (1.5, 0.0), "Division by zero", id="float"),
+        pytest.param(FloorDivOp, (4, 0), "Division by zero", id="floordiv"),
     ],
 )
 def test_div_by_0(component: Component, args: Any, expected: str):
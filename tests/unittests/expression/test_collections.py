@@ -0,0 +1,101 @@
+from typing import Any
+
+import pytest
+from xcomponent import Catalog
+
+
+@pytest.fixture(autouse=True)
+def components(catalog: Catalog):
+    @catalog.component
+    def Select(options: list[str]) -> str:
+        return """
+            <select>{for option in options {<option>{option}</option>}}</select>
+        """
+
+    @catalog.component
+    def Attrs(attrs: dict[str, str]) -> str:
+        return """<p>{attrs["id"]}-{attrs["class"]}</p>"""
+
+    @catalog.function
+    def describe(value: Any) -> str:
+        return repr(value)
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            '<ul>{for tab in ["home", "about"] {<li>{tab}</li>}}</ul>',
+            {},
+            "<ul><li>home</li><li>about</li></ul>",
+            id="for",
+        ),
+        pytest.param(
+            '<Select options={["a", "b"]} />',
+            {},
+            "<select><option>a</option><option>b</option></select>",
+            id="component-attr",
+        ),
+        pytest.param(
+            "<>{describe([1, a, a + 1])}</>", {"a": 2}, "[1, 2, 3]", id="expressions"
+        ),
+        pytest.param("<>{describe([])}</>", {}, "[]", id="empty"),
+        pytest.param(
+            "<>{describe([\n  1,\n  2,\n])}</>", {}, "[1, 2]", id="trailing-comma"
+        ),
+        pytest.param("<>{describe([[1], [2]])}</>", {}, "[[1], [2]]", id="nested"),
+        pytest.param("<>{[1, 2, 3][1]}</>", {}, "2", id="index"),
+        pytest.param("<>{2 in [1, 2]}</>", {}, "true", id="in"),
+    ],
+)
+def test_list(catalog: Catalog, template: str, params: dict[str, Any], expected: str):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            "<Attrs attrs={{'id': 'x', 'class': c}} />",
+            {"c": "btn"},
+            "<p>x-btn</p>",
+            id="component-attr",
+        ),
+        pytest.param("<>{describe({})}</>", {}, "{}", id="empty"),
+        pytest.param(
+            "<>{describe({'a': [1, 2],})}</>", {}, "{'a': [1, 2]}", id="trailing-comma"
+        ),
+        pytest.param("<>{describe({1: 'a'})}</>", {}, "{1: 'a'}", id="int-key"),
+        pytest.param(
+            "<>{describe({k: 1})}</>", {"k": "key"}, "{'key': 1}", id="expression-key"
+        ),
+        pytest.param("<>{{'a': 1, 'b': 2}['b']}</>", {}, "2", id="index"),
+        pytest.param("<>{{'a': 1}.a}</>", {}, "1", id="field"),
+        pytest.param("<>{'a' in {'a': 1}}</>", {}, "true", id="in"),
+        pytest.param(
+            "<>{if true {{'a': 1}['a']}}</>", {}, "1", id="if-block"
+        ),
+    ],
+)
+def test_dict(catalog: Catalog, template: str, params: dict[str, Any], expected: str):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param("<>{describe((1, 2))}</>", {}, "[1, 2]", id="tuple"),
+        pytest.param("<>{describe((1,))}</>", {}, "[1]", id="single"),
+        pytest.param("<>{describe(())}</>", {}, "[]", id="empty"),
+        pytest.param("<>{describe((1))}</>", {}, "1", id="parens"),
+        pytest.param("<>{(a, b)[1]}</>", {"a": 1, "b": 2}, "2", id="index"),
+    ],
+)
+def test_tuple(catalog: Catalog, template: str, params: dict[str, Any], expected: str):
+    assert catalog.render(template, **params) == expected
+
+
+def test_unhashable_key(catalog: Catalog):
+    with pytest.raises(TypeError) as exc:
+        catalog.render("<>{describe({[1]: 1})}</>")
+    assert str(exc.value) == "Unsupported literal type for key List([Int(1)])"
@@ -0,0 +1,243 @@
+from typing import Any
+
+import pytest
+from xcomponent import Catalog, Markup, XComponentRenderError
+
+
+class Items:
+    def __init__(self, *items: Any) -> None:
+        self.items = items
+
+    def __iter__(self):
+        return iter(self.items)
+
+    def __len__(self) -> int:
+        return len(self.items)
+
+
+@pytest.fixture(autouse=True)
+def functions(catalog: Catalog):
+    @catalog.function
+    def truncate(text: str, size: int, end: str = "...") -> str:
+        return text if len(text) <= size else text[:size] + end
+
+    @catalog.function
+    def shout(text: str) -> str:
+        return text + "!"
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param("<>{title | upper}</>", {"title": "hello"}, "HELLO", id="upper"),
+        pytest.param(
+            "<>{title | upper | truncate(3)}</>",
+            {"title": "hello"},
+            "HEL...",
+            id="chained",
+        ),
+        pytest.param(
+            "<>{title|truncate(2, end='~')}</>",
+            {"title": "hello"},
+            "he~",
+            id="kwargs",
+        ),
+        pytest.param(
+            "<>{title | shout | upper}</>",
+            {"title": "hello"},
+            "HELLO!",
+            id="catalog-function",
+        ),
+        pytest.param(
+            "<>{items | length > 1}</>", {"items": [1, 2]}, "true", id="precedence"
+        ),
+        pytest.param(
+            "<>{(a + b) | upper}</>", {"a": "x", "b": "y"}, "XY", id="parenthesis"
+        ),
+        pytest.param(
+            "<>{a + b | upper}</>", {"a": "x", "b": "y"}, "xY", id="binds-tight"
+        ),
+        pytest.param(
+            "<p class={name | lower}>{name | title}</p>",
+            {"name": "mIXED case"},
+            '<p class="mixed case">Mixed Case</p>',
+            id="attribute",
+        ),
+        pytest.param(
+            "<ul>{for x in items | sort {<li>{x}</li>}}</ul>",
+            {"items": [2, 1]},
+            "<ul><li>1</li><li>2</li></ul>",
+            id="for",
+        ),
+        pytest.param("<>{upper(title)}</>", {"title": "hi"}, "HI", id="call"),
+    ],
+)
+def test_pipe(catalog: Catalog, template: str, params: dict[str, Any], expected: str):
+    assert catalog.render(template, **params) == expected
+
+
+def test_function_overrides_filter(catalog: Catalog):
+    @catalog.function
+    def upper(text: str) -> str:
+        return "custom " + text
+
+    assert catalog.render("<>{title | upper}</>", title="x") == "custom x"
+
+
+def test_variable_does_not_shadow_filter(catalog: Catalog):
+    template = "<p title={upper}>{title | upper}</p>"
+    assert catalog.render(template, title="x", upper="y") == '<p title="y">X</p>'
+
+
+@pytest.mark.parametrize(
+    "template",
+    [
+        pytest.param("<>{3 | range}</>", id="range"),
+        pytest.param("<>{'user' | provide('a')}</>", id="provide"),
+        pytest.param("<>{'user' | inject}</>", id="inject"),
+        pytest.param("<>{v | unknown}</>", id="unknown"),
+    ],
+)
+def test_not_a_filter(catalog: Catalog, template: str):
+    with pytest.raises(UnboundLocalError) as exc:
+        catalog.render(template, v="a")
+    assert "is not a filter" in str(exc.value)
+
+
+def test_builtin_functions_are_callable(catalog: Catalog):
+    assert catalog.render("<>{range(3) | join(',')}</>") == "0,1,2"
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param("<>{v | upper}</>", {"v": "école"}, "ÉCOLE", id="upper"),
+        pytest.param("<>{v | lower}</>", {"v": "ÉCOLE"}, "école", id="lower"),
+        pytest.param(
+            "<>{v | title}</>", {"v": "they're bill's"}, "They'Re Bill'S", id="title"
+        ),
+        pytest.param("<>{v | trim}</>", {"v": "  a b \n"}, "a b", id="trim"),
+        pytest.param("<>{v | trim('-')}</>", {"v": "--a-b--"}, "a-b", id="trim-chars"),
+        pytest.param("<>{v | length}</>", {"v": "école"}, "5", id="length-str"),
+        pytest.param("<>{v | length}</>", {"v": [1, 2, 3]}, "3", id="length-list"),
+        pytest.param("<>{v | length}</>", {"v": {"a": 1}}, "1", id="length-dict"),
+        pytest.param("<>{v | length}</>", {"v": Items(1, 2)}, "2", id="length-object"),
+        pytest.param("<>{v | join}</>", {"v": ["a", "b"]}, "ab", id="join"),
+        pytest.param(
+            "<>{v | join(', ')}</>", {"v": ["a", 1, 1.5]}, "a, 1, 1.5", id="join-sep"
+        ),
+        pytest.param(
+            "<>{v | join(', ')}</>",
+            {"v": ["<a>", Markup("<b>x</b>")]},
+            "&lt;a&gt;, <b>x</b>",
+            id="join-markup",
+        ),
+        pytest.param(
+            "<>{v | join(sep)}</>",
+            {"v": ["<a>", "<b>"], "sep": Markup("<br/>")},
+            "&lt;a&gt;<br/>&lt;b&gt;",
+            id="join-markup-separator",
+        ),
+        pytest.param(
+            "<>{v | join('-')}</>", {"v": Items("a", "b")}, "a-b", id="join-iterable"
+        ),
+        pytest.param("<>{v | default('-')}</>", {"v": None}, "-", id="default"),
+        pytest.param("<>{v | default('-')}</>", {"v": ""}, "", id="default-falsy"),
+        pytest.param(
+            "<>{v | default('-', true)}</>", {"v": ""}, "-", id="default-boolean"
+        ),
+        pytest.param(
+            "<>{v | default('-', boolean=true)}</>",
+            {"v": 0},
+            "-",
+            id="default-boolean-kwarg",
+        ),
+        pytest.param("<>{v | default('-')}</>", {"v": "x"}, "x", id="default-value"),
+        pytest.param("<>{v | first}</>", {"v": [1, 2]}, "1", id="first"),
+        pytest.param("<>{v | first}</>", {"v": "abc"}, "a", id="first-str"),
+        pytest.param("<>{v | first is None}</>", {"v": []}, "true", id="first-empty"),
+        pytest.param("<>{v | last}</>", {"v": [1, 2]}, "2", id="last"),
+        pytest.param("<>{v | last}</>", {"v": Items(1, 2)}, "2", id="last-iterable"),
+        pytest.param("<>{v | sort | join(',')}</>", {"v": [3, 1, 2]}, "1,2,3", id="sort"),
+        pytest.param(
+            "<>{v | sort(reverse=true) | join(',')}</>",
+            {"v": ["b", "c", "a"]},
+            "c,b,a",
+            id="sort-reverse",
+        ),
+        pytest.param(
+            "<>{v | reverse | join(',')}</>", {"v": [1, 2, 3]}, "3,2,1", id="reverse"
+        ),
+        pytest.param("<>{v | reverse}</>", {"v": "abc"}, "cba", id="reverse-str"),
+        pytest.param(
+            "<>{v | json}</>",
+            {"v": {"a": [1, None, True]}},
+            '{"a":[1,null,true]}',
+            id="json",
+        ),
+        pytest.param(
+            "<div data-x={v | json}></div>",
+            {"v": ["</script>"]},
+            '<div data-x="[&quot;\\u003c/script\\u003e&quot;]"></div>',
+            id="json-attribute",
+        ),
+        pytest.param(
+            "<>{v | urlencode}</>", {"v": "a b/c&d"}, "a%20b/c%26d", id="urlencode"
+        ),
+        pytest.param(
+            "<>{v | urlencode}</>",
+            {"v": {"q": "a b", "page": 2}},
+            "q=a+b&amp;page=2",
+            id="urlencode-dict",
+        ),
+        pytest.param("<>{v | urlencode}</>", {"v": "é"}, "%C3%A9", id="urlencode-utf8"),
+    ],
+)
+def test_builtin_filters(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,message",
+    [
+        pytest.param(
+            "<>{v | upper}</>", {"v": 1}, "upper() expects a string, got Int(1)", id="type"
+        ),
+        pytest.param(
+            "<>{v | upper(1)}</>",
+            {"v": "a"},
+            "upper() takes 1 positional arguments but 2 were given",
+            id="too-many-args",
+        ),
+        pytest.param(
+            "<>{v | join(sep=',')}</>",
+            {"v": []},
+            "join() got an unexpected keyword argument 'sep'",
+            id="unexpected-kwarg",
+        ),
+        pytest.param(
+            "<>{v | sort}</>",
+            {"v": [1, "a"]},
+            'Cannot compare Str("a") < Int(1), type mismatch',
+            id="sort",
+        ),
+    ],
+)
+def test_filter_errors(
+    catalog: Catalog, template: str, params: dict[str, Any], message: str
+):
+    with pytest.raises(TypeError) as exc:
+        catalog.render(template, **params)
+    assert str(exc.value) == message
+
+
+def test_filter_component_stack(catalog: Catalog):
+    @catalog.component
+    def Title(title: str) -> str:
+        return """<h1>{title | upper}</h1>"""
+
+    with pytest.raises(XComponentRenderError) as exc:
+        catalog.render("<Title title={1} />")
+    assert exc.value.component_stack == ["<Title>", "upper()"]
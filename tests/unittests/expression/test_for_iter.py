@@ -0,0 +1,213 @@
+from collections.abc import Iterator
+from typing import Any
+
+import pytest
+from xcomponent import Catalog
+
+
+class QuerySet:
+    """Iterable that records how many items have been consumed."""
+
+    def __init__(self, *items: Any) -> None:
+        self.items = items
+        self.consumed = 0
+
+    def __iter__(self) -> Iterator[Any]:
+        for item in self.items:
+            self.consumed += 1
+            yield item
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            "<>{for k in d { <b>{k}</b> }}</>",
+            {"d": {"a": 1, "b": 2}},
+            "<b>a</b><b>b</b>",
+            id="dict-keys",
+        ),
+        pytest.param(
+            "<>{for k, v in d { <b>{k}={v}</b> }}</>",
+            {"d": {"a": 1, "b": 2}},
+            "<b>a=1</b><b>b=2</b>",
+            id="dict-items",
+        ),
+        pytest.param(
+            "<>{for (k, v) in d { <b>{k}={v}</b> }}</>",
+            {"d": {"a": 1}},
+            "<b>a=1</b>",
+            id="parenthesized-targets",
+        ),
+        pytest.param(
+            "<>{for x, y in pairs { <b>{x}{y}</b> }}</>",
+            {"pairs": [["a", 1], ["b", 2]]},
+            "<b>a1</b><b>b2</b>",
+            id="list-of-lists",
+        ),
+        pytest.param(
+            "<>{for x, y in pairs { <b>{x}{y}</b> }}</>",
+            {"pairs": [("a", 1), ("b", 2)]},
+            "<b>a1</b><b>b2</b>",
+            id="list-of-tuples",
+        ),
+        pytest.param(
+            "<>{for x, y, z in [[1, 2, 3]] { <b>{x}{y}{z}</b> }}</>",
+            {},
+            "<b>123</b>",
+            id="three-targets",
+        ),
+        pytest.param(
+            "<>{for c in s { <b>{c}</b> }}</>",
+            {"s": "ab"},
+            "<b>a</b><b>b</b>",
+            id="str",
+        ),
+        pytest.param(
+            "<>{for x in t { <b>{x}</b> }}</>",
+            {"t": (1, 2)},
+            "<b>1</b><b>2</b>",
+            id="tuple",
+        ),
+        pytest.param(
+            "<>{for x in s { <b>{x}</b> }}</>",
+            {"s": {3}},
+            "<b>3</b>",
+            id="set",
+        ),
+        pytest.param(
+            "<>{for x in g { <b>{x}</b> }}</>",
+            {"g": (i * 2 for i in range(3))},
+            "<b>0</b><b>2</b><b>4</b>",
+            id="generator",
+        ),
+        pytest.param(
+            "<>{for i, x in e { <b>{i}{x}</b> }}</>",
+            {"e": enumerate("ab")},
+            "<b>0a</b><b>1b</b>",
+            id="enumerate",
+        ),
+        pytest.param(
+            "<>{for i in range(3) { <b>{i}</b> }}</>",
+            {},
+            "<b>0</b><b>1</b><b>2</b>",
+            id="range-stop",
+        ),
+        pytest.param(
+            "<>{for i in range(1, 3) { <b>{i}</b> }}</>",
+            {},
+            "<b>1</b><b>2</b>",
+            id="range-start-stop",
+        ),
+        pytest.param(
+            "<>{for i in range(10, 0, -4) { <b>{i}</b> }}</>",
+            {},
+            "<b>10</b><b>6</b><b>2</b>",
+            id="range-step",
+        ),
+        pytest.param(
+            "<>{for i in range(n) { <b>{i}</b> }}</>",
+            {"n": 0},
+            "",
+            id="range-empty",
+        ),
+        pytest.param("<>{range(3) | length}</>", {}, "3", id="range-length"),
+        pytest.param("<>{range(3) | join(',')}</>", {}, "0,1,2", id="range-join"),
+    ],
+)
+def test_for_iter(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+def test_for_scope(catalog: Catalog):
+    assert (
+        catalog.render(
+            "<>{for k, v in d { <b>{k}{v}</b> }}{k}</>",
+            d={"a": 1},
+            k="outer",
+        )
+        == "<b>a1</b>outer"
+    )
+
+
+class Sized(QuerySet):
+    """Iterable that fails if its length is computed."""
+
+    def __len__(self) -> int:
+        raise AssertionError("the length is computed")
+
+
+@pytest.mark.parametrize(
+    "template,expected",
+    [
+        pytest.param(
+            "<>{for x in qs { <b>{x}{consumed(qs)}</b> }}</>",
+            "<b>a1</b><b>b2</b><b>c3</b>",
+            id="lazy",
+        ),
+        pytest.param(
+            # the next item is consumed ahead to know if the item is the last one
+            "<>{for x in qs { <b>{x}{consumed(qs)}{loop.last}</b> }}</>",
+            "<b>a2false</b><b>b3false</b><b>c3true</b>",
+            id="loop",
+        ),
+    ],
+)
+def test_lazy_iteration(catalog: Catalog, template: str, expected: str):
+    @catalog.function
+    def consumed(qs: QuerySet) -> int:
+        return qs.consumed
+
+    assert catalog.render(template, qs=QuerySet("a", "b", "c")) == expected
+
+
+def test_length_without_loop(catalog: Catalog):
+    qs = Sized("a", "b")
+    assert catalog.render("<>{for x in qs { <b>{x}</b> }}</>", qs=qs) == (
+        "<b>a</b><b>b</b>"
+    )
+
+
+@pytest.mark.parametrize(
+    "template,params,error",
+    [
+        pytest.param(
+            "<>{for x, y in l { x }}</>",
+            {"l": [[1]]},
+            r"not enough values to unpack \(expected 2, got 1\)",
+            id="not-enough",
+        ),
+        pytest.param(
+            "<>{for x, y in l { x }}</>",
+            {"l": [[1, 2, 3]]},
+            r"too many values to unpack \(expected 2\)",
+            id="too-many",
+        ),
+        pytest.param(
+            "<>{for x, y, z in d { x }}</>",
+            {"d": {"a": 1}},
+            r"not enough values to unpack \(expected 3, got 1\)",
+            id="dict-keys-unpack",
+        ),
+    ],
+)
+def test_unpack_error(
+    catalog: Catalog, template: str, params: dict[str, Any], error: str
+):
+    with pytest.raises(ValueError, match=error):
+        catalog.render(template, **params)
+
+
+@pytest.mark.parametrize(
+    "template,params",
+    [
+        pytest.param("<>{for x, y in l { x }}</>", {"l": [1]}, id="int"),
+        pytest.param("<>{for x in o { x }}</>", {"o": object()}, id="object"),
+        pytest.param("<>{for x in n { x }}</>", {"n": 1}, id="number"),
+    ],
+)
+def test_not_iterable(catalog: Catalog, template: str, params: dict[str, Any]):
+    with pytest.raises(TypeError):
+        catalog.render(template, **params)
@@ -0,0 +1,133 @@
+from typing import Any
+
+import pytest
+from xcomponent import Catalog
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            "<>{for x in l { <b>{loop.index}</b> }}</>",
+            {"l": ["a", "b"]},
+            "<b>1</b><b>2</b>",
+            id="index",
+        ),
+        pytest.param(
+            "<>{for x in l { <b>{loop.index0}</b> }}</>",
+            {"l": ["a", "b"]},
+            "<b>0</b><b>1</b>",
+            id="index0",
+        ),
+        pytest.param(
+            "<>{for x in l { <b>{loop.first}-{loop.last}</b> }}</>",
+            {"l": ["a", "b", "c"]},
+            "<b>true-false</b><b>false-false</b><b>false-true</b>",
+            id="first-last",
+        ),
+        pytest.param(
+            "<>{for x in l { <b>{loop.first}-{loop.last}</b> }}</>",
+            {"l": ["a"]},
+            "<b>true-true</b>",
+            id="first-last-single",
+        ),
+        pytest.param(
+            "<>{for x in l { <b>{loop.odd}-{loop.even}</b> }}</>",
+            {"l": ["a", "b"]},
+            "<b>true-false</b><b>false-true</b>",
+            id="parity",
+        ),
+        pytest.param(
+            "<>{for x in l { <b>{loop.index}/{loop.length}</b> }}</>",
+            {"l": ["a", "b"]},
+            "<b>1/2</b><b>2/2</b>",
+            id="length",
+        ),
+        pytest.param(
+            "<>{for k, v in d { <b>{loop.length}</b> }}</>",
+            {"d": {"a": 1}},
+            "<b>1</b>",
+            id="length-dict",
+        ),
+        pytest.param(
+            "<>{for i in range(2) { <b>{loop.length}</b> }}</>",
+            {},
+            "<b>2</b><b>2</b>",
+            id="length-range",
+        ),
+        pytest.param(
+            "<>{for x in g { <b>{loop.length}-{loop.last}</b> }}</>",
+            {"g": (x for x in "ab")},
+            "<b>-false</b><b>-true</b>",
+            id="length-generator",
+        ),
+        pytest.param(
+            "<>{for x in l { for y in x { <b>{loop.parent.index}.{loop.index}</b> } }}</>",
+            {"l": [["a", "b"], ["c"]]},
+            "<b>1.1</b><b>1.2</b><b>2.1</b>",
+            id="parent",
+        ),
+        pytest.param(
+            "<>{for x in l { <b>{loop.parent}</b> }}</>",
+            {"l": ["a"]},
+            "<b></b>",
+            id="no-parent",
+        ),
+        pytest.param(
+            "<>{for x in l { if loop.last { x } else { x + ', ' } }}</>",
+            {"l": ["a", "b"]},
+            "a, b",
+            id="separator",
+        ),
+    ],
+)
+def test_loop(catalog: Catalog, template: str, params: dict[str, Any], expected: str):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            "<>{for x in l { <b>{x}</b> } else { <p>No results</p> }}</>",
+            {"l": []},
+            "<p>No results</p>",
+            id="empty-list",
+        ),
+        pytest.param(
+            "<>{for x in l { <b>{x}</b> } else { <p>No results</p> }}</>",
+            {"l": ["a"]},
+            "<b>a</b>",
+            id="not-empty",
+        ),
+        pytest.param(
+            "<>{for k, v in d { <b>{k}</b> } else { <p>No results</p> }}</>",
+            {"d": {}},
+            "<p>No results</p>",
+            id="empty-dict",
+        ),
+        pytest.param(
+            "<>{for x in g { <b>{x}</b> } else { <p>No results</p> }}</>",
+            {"g": iter(())},
+            "<p>No results</p>",
+            id="empty-iterator",
+        ),
+        pytest.param(
+            "<>{for x in l { <b>{x}</b> } else { 'none' }}</>",
+            {"l": []},
+            "none",
+            id="else-expression",
+        ),
+    ],
+)
+def test_for_else(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+def test_loop_scope(catalog: Catalog):
+    assert (
+        catalog.render("<>{for x in l { <b>{x}</b> }}{loop}</>", l=["a"], loop="outer")
+        == "<b>a</b>outer"
+    )
@@ -0,0 +1,141 @@
+from typing import Any
+
+import pytest
+from xcomponent import Catalog
+
+catalog = Catalog()
+
+
+@catalog.component
+def Row(item: str) -> str:
+    return """<li>{item}</li>"""
+
+
+@catalog.component
+def List(items: list[str], renderRow: Any) -> str:
+    return """<ul>{for item in items { renderRow(item) }}</ul>"""
+
+
+@catalog.component
+def Table(items: list[str]) -> str:
+    return """<List items={items} renderRow={row => <Row item={row}/>} />"""
+
+
+@catalog.component
+def Prefixed(items: list[str], prefix: str) -> str:
+    return """
+        <List items={items} renderRow={row => <Row item={prefix + row}/>} />
+    """
+
+
+@catalog.function
+def sort_by(items: list[Any], key: Any) -> list[Any]:
+    return sorted(items, key=key)
+
+
+@catalog.function("repr")
+def repr_(obj: Any) -> str:
+    return repr(obj)
+
+
+@catalog.function
+def apply(fn: Any, *args: Any, **kwargs: Any) -> Any:
+    return fn(*args, **kwargs)
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            "<Table items={items} />",
+            {"items": ["a", "b"]},
+            "<ul><li>a</li><li>b</li></ul>",
+            id="component-prop",
+        ),
+        pytest.param(
+            "<Prefixed items={items} prefix='#' />",
+            {"items": ["a", "b"]},
+            "<ul><li>#a</li><li>#b</li></ul>",
+            id="capture-props",
+        ),
+        pytest.param(
+            "<>{let double = x => x * 2}{double(21)}</>", {}, "42", id="let"
+        ),
+        pytest.param(
+            "<>{let add = (a, b) => a + b}{add(1, b=2)}</>", {}, "3", id="kwargs"
+        ),
+        pytest.param("<>{let f = () => 'x'}{f()}</>", {}, "x", id="no-params"),
+        pytest.param(
+            "<>{let f = (a,) => a}{f(1)}</>", {}, "1", id="trailing-comma"
+        ),
+        pytest.param(
+            "<>{let f = x => { if x { 'yes' } else { 'no' } }}{f(true)}</>",
+            {},
+            "yes",
+            id="block-body",
+        ),
+        pytest.param(
+            "<>{sort_by(words, w => w | length) | join(',')}</>",
+            {"words": ["ccc", "a", "bb"]},
+            "a,bb,ccc",
+            id="python-callable",
+        ),
+        pytest.param(
+            "<>{apply(x => <b>{x}</b>, x='a')}</>",
+            {},
+            "<b>a</b>",
+            id="python-kwargs",
+        ),
+        pytest.param(
+            "<>{let n = 1}{let f = x => x + n}{let n = 10}{f(1)}</>",
+            {},
+            "2",
+            id="captured-at-creation",
+        ),
+        pytest.param(
+            "<>{let f = x => y => x + y}{f(1)(2)}</>", {}, "3", id="curried"
+        ),
+    ],
+)
+def test_lambda(template: str, params: dict[str, Any], expected: str):
+    assert catalog.render(template, **params) == expected
+
+
+def test_lambda_repr():
+    assert catalog.render("<>{repr((a, b) => a)}</>") == "&lt;lambda(a, b)&gt;"
+
+
+@pytest.mark.parametrize(
+    "template,error",
+    [
+        pytest.param("<>{let f = x => x}{f(1, 2)}</>", "takes 1", id="too-many"),
+        pytest.param("<>{let f = x => x}{f()}</>", "missing", id="missing"),
+        pytest.param("<>{let f = x => x}{f(y=1)}</>", "unexpected", id="unexpected"),
+        pytest.param("<>{let f = x => x}{f(1, x=1)}</>", "multiple", id="multiple"),
+    ],
+)
+def test_lambda_error(template: str, error: str):
+    with pytest.raises(TypeError, match=error):
+        catalog.render(template)
+
+
+def test_lambda_namespace():
+    ui = Catalog()
+
+    @ui.component
+    def List(items: list[str], render: Any) -> str:
+        return """<ul>{for item in items { render(item) }}</ul>"""
+
+    page = Catalog()
+
+    @page.component
+    def Row(item: str) -> str:
+        return """<li>{item}</li>"""
+
+    @page.component(use={"ui": ui})
+    def Page(items: list[str]) -> str:
+        return """<ui.List items={items} render={(item) => <Row item={item}/>} />"""
+
+    assert page.render("<Page items={['a', 'b']} />") == (
+        "<ul><li>a</li><li>b</li></ul>"
+    )
@@ -0,0 +1,141 @@
+from typing import Any
+
+import pytest
+from xcomponent import Catalog
+
+STATUS_TEMPLATE = """
+<>{
+    match status {
+        "draft" => <span class="badge-draft">Draft</span>,
+        "published" | "archived" => <span class="badge">{status}</span>,
+        None => <span>No status</span>,
+        _ => "unknown",
+    }
+}</>
+"""
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            STATUS_TEMPLATE,
+            {"status": "draft"},
+            '<span class="badge-draft">Draft</span>',
+            id="str",
+        ),
+        pytest.param(
+            STATUS_TEMPLATE,
+            {"status": "archived"},
+            '<span class="badge">archived</span>',
+            id="alternatives",
+        ),
+        pytest.param(
+            STATUS_TEMPLATE, {"status": None}, "<span>No status</span>", id="none"
+        ),
+        pytest.param(STATUS_TEMPLATE, {"status": "x"}, "unknown", id="wildcard"),
+        pytest.param(
+            STATUS_TEMPLATE, {"status": 1}, "unknown", id="type-mismatch"
+        ),
+        pytest.param(
+            "<>{match n { 0 => 'zero', 1 | 2 => 'few', -1 => 'negative' }}</>",
+            {"n": 2},
+            "few",
+            id="int",
+        ),
+        pytest.param(
+            "<>{match n { 0 => 'zero', 1 | 2 => 'few', -1 => 'negative' }}</>",
+            {"n": -1},
+            "negative",
+            id="negative-int",
+        ),
+        pytest.param(
+            "<>{match n { 0.5 => 'half', _ => 'other' }}</>",
+            {"n": 0.5},
+            "half",
+            id="float",
+        ),
+        pytest.param(
+            "<>{match b { true => 'yes', false => 'no' }}</>",
+            {"b": False},
+            "no",
+            id="bool",
+        ),
+        pytest.param(
+            "<>{match n { 0 => 'zero' }}</>",
+            {"n": 3},
+            "",
+            id="no-match",
+        ),
+        pytest.param(
+            "<>{match n { x if x > 10 => 'big', x if x > 0 => 'small', _ => 'none' }}</>",
+            {"n": 5},
+            "small",
+            id="guard-capture",
+        ),
+        pytest.param(
+            "<>{match n { _ if n > 10 => 'big', _ => 'other' }}</>",
+            {"n": 50},
+            "big",
+            id="guard-subject",
+        ),
+        pytest.param(
+            "<>{match s { 'a' | 'b' if ok => 'ab', _ => 'other' }}</>",
+            {"s": "b", "ok": False},
+            "other",
+            id="guard-alternatives",
+        ),
+        pytest.param(
+            "<>{match user.role { 'admin' => 'A', _ => 'U' }}</>",
+            {"user": {"role": "admin"}},
+            "A",
+            id="field-subject",
+        ),
+        pytest.param(
+            "<>{match n % 2 { 0 => 'even', _ => 'odd' }}</>",
+            {"n": 3},
+            "odd",
+            id="expression-subject",
+        ),
+        pytest.param(
+            "<>{match value { x => x + 1 }}{x}</>",
+            {"value": 1, "x": "outer"},
+            "2outer",
+            id="capture-scope",
+        ),
+        pytest.param(
+            "<>{for s in l { match s { 'a' => <b>A</b>, _ => <i>{s}</i> } }}</>",
+            {"l": ["a", "b"]},
+            "<b>A</b><i>b</i>",
+            id="in-for",
+        ),
+        pytest.param(
+            "<>{match a { 1 => match b { 2 => 'a1b2', _ => 'a1' }, _ => 'other' }}</>",
+            {"a": 1, "b": 2},
+            "a1b2",
+            id="nested",
+        ),
+        pytest.param("<>{match}</>", {"match": "m"}, "m", id="match-variable"),
+    ],
+)
+def test_match(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template.strip(), **params) == expected
+
+
+def test_match_component(catalog: Catalog):
+    @catalog.component
+    def Badge(status: str | int) -> str:
+        return """
+        <span>{
+            match status {
+                "draft" => "Draft",
+                404 => "Not Found",
+                _ => "?",
+            }
+        }</span>
+        """
+
+    assert catalog.render("<Badge status='draft' />") == "<span>Draft</span>"
+    assert catalog.render("<Badge status={404} />") == "<span>Not Found</span>"
@@ -0,0 +1,148 @@
+from typing import Any
+
+import pytest
+from xcomponent import Catalog, Markup
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param("<>{title.upper()}</>", {"title": "Hello"}, "HELLO", id="upper"),
+        pytest.param("<>{title.lower()}</>", {"title": "Hello"}, "hello", id="lower"),
+        pytest.param("<>{title.strip()}</>", {"title": "  a b "}, "a b", id="strip"),
+        pytest.param("<>{title.strip('-')}</>", {"title": "--a-"}, "a", id="strip-chars"),
+        pytest.param(
+            "<>{url.startswith('https')}</>",
+            {"url": "https://x"},
+            "true",
+            id="startswith",
+        ),
+        pytest.param(
+            "<>{url.startswith(['http:', 'ftp:'])}</>",
+            {"url": "https://x"},
+            "false",
+            id="startswith-list",
+        ),
+        pytest.param(
+            "<>{name.endswith('.py')}</>", {"name": "a.py"}, "true", id="endswith"
+        ),
+        pytest.param(
+            "<>{s.replace('-', '_')}</>", {"s": "a-b-c"}, "a_b_c", id="replace"
+        ),
+        pytest.param(
+            "<>{s.replace('-', '_', 1)}</>", {"s": "a-b-c"}, "a_b-c", id="replace-count"
+        ),
+        pytest.param(
+            "<>{s.split(',') | join('|')}</>", {"s": "a,b,,c"}, "a|b||c", id="split"
+        ),
+        pytest.param(
+            "<>{s.split() | join('|')}</>", {"s": "  a b\n c  "}, "a|b|c", id="split-ws"
+        ),
+        pytest.param(
+            "<>{s.split(',', 1) | join('|')}</>",
+            {"s": "a,b,c"},
+            "a|b,c",
+            id="split-maxsplit",
+        ),
+        pytest.param(
+            "<>{s.split(maxsplit=1) | join('|')}</>",
+            {"s": " a b  c "},
+            "a|b  c ",
+            id="split-ws-maxsplit",
+        ),
+        pytest.param(
+            "<>{'{} of {}'.format(1, n)}</>", {"n": 3}, "1 of 3", id="format-auto"
+        ),
+        pytest.param(
+            "<>{'{1}-{0}'.format('a', 'b')}</>", {}, "b-a", id="format-positional"
+        ),
+        pytest.param(
+            "<>{'Hello {name}'.format(name=n)}</>",
+            {"n": "Bob"},
+            "Hello Bob",
+            id="format-keyword",
+        ),
+        pytest.param(
+            "<>{'{{{}}}'.format(1)}</>", {}, "{1}", id="format-escaped-braces"
+        ),
+        pytest.param(
+            "<>{'{:.2f}'.format(x)}</>", {"x": 3.14159}, "3.14", id="format-spec"
+        ),
+        pytest.param(
+            "<>{'{u[name]}'.format(u=user)}</>",
+            {"user": {"name": "Bob"}},
+            "Bob",
+            id="format-item",
+        ),
+        pytest.param("<>{l.len()}</>", {"l": [1, 2]}, "2", id="list-len"),
+        pytest.param("<>{l.contains(2)}</>", {"l": [1, 2]}, "true", id="contains"),
+        pytest.param(
+            "<>{l.contains('x')}</>", {"l": [1, 2]}, "false", id="contains-mismatch"
+        ),
+        pytest.param("<>{l.index('b')}</>", {"l": ["a", "b"]}, "1", id="index"),
+        pytest.param(
+            "<>{tags.join(', ')}</>", {"tags": ["a", "b"]}, "a, b", id="list-join"
+        ),
+        pytest.param("<>{d.get('a')}</>", {"d": {"a": 1}}, "1", id="get"),
+        pytest.param("<>{d.get('b')}</>", {"d": {"a": 1}}, "", id="get-missing"),
+        pytest.param(
+            "<>{d.get('b', 'x')}</>", {"d": {"a": 1}}, "x", id="get-default"
+        ),
+        pytest.param(
+            "<>{d.keys() | join(',')}</>", {"d": {"a": 1, "b": 2}}, "a,b", id="keys"
+        ),
+        pytest.param(
+            "<>{d.values() | join(',')}</>",
+            {"d": {"a": 1, "b": 2}},
+            "1,2",
+            id="values",
+        ),
+        pytest.param(
+            "<>{for k, v in d.items() { <b>{k}{v}</b> }}</>",
+            {"d": {"a": 1, "b": 2}},
+            "<b>a1</b><b>b2</b>",
+            id="items",
+        ),
+        pytest.param(
+            "<>{d.get()}</>",
+            {"d": {"get": lambda: "key"}},
+            "key",
+            id="dict-key-shadows-method",
+        ),
+        pytest.param(
+            "<>{m.upper()}</>",
+            {"m": Markup("<b>a</b>")},
+            "<B>A</B>",
+            id="markup",
+        ),
+        pytest.param(
+            "<>{title.upper().split() | join('-')}</>",
+            {"title": "a b"},
+            "A-B",
+            id="chain",
+        ),
+        pytest.param(
+            "<>{title.title()}</>", {"title": "a b"}, "A B", id="python-method"
+        ),
+    ],
+)
+def test_method(catalog: Catalog, template: str, params: dict[str, Any], expected: str):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,error",
+    [
+        pytest.param("<>{l.index(3)}</>", {"l": [1]}, ValueError, id="index-missing"),
+        pytest.param("<>{s.split('')}</>", {"s": "a"}, ValueError, id="empty-sep"),
+        pytest.param("<>{s.upper(1)}</>", {"s": "a"}, TypeError, id="too-many-args"),
+        pytest.param("<>{'{}{}'.format(1)}</>", {}, IndexError, id="format-index"),
+        pytest.param("<>{'{a}'.format()}</>", {}, KeyError, id="format-key"),
+        pytest.param("<>{'{'.format()}</>", {}, ValueError, id="format-single-brace"),
+    ],
+)
+def test_method_error(
+    catalog: Catalog, template: str, params: dict[str, Any], error: type[Exception]
+):
+    with pytest.raises(error):
+        catalog.render(template, **params)
@@ -0,0 +1,129 @@
+from dataclasses import dataclass
+from typing import Any
+
+import pytest
+from xcomponent import Catalog
+
+
+@dataclass
+class Profile:
+    avatar: str | None
+
+
+@dataclass
+class User:
+    profile: Profile | None
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            "<>{user?.profile?.avatar}</>",
+            {"user": {"profile": {"avatar": "a.png"}}},
+            "a.png",
+            id="dict",
+        ),
+        pytest.param(
+            "<>{user?.profile?.avatar}</>",
+            {"user": {"profile": None}},
+            "",
+            id="dict-none",
+        ),
+        pytest.param(
+            "<>{user?.profile?.avatar}</>",
+            {"user": {}},
+            "",
+            id="dict-missing-key",
+        ),
+        pytest.param(
+            "<>{user?.profile?.avatar}</>",
+            {"user": None},
+            "",
+            id="none-base",
+        ),
+        pytest.param(
+            "<>{user?.profile?.avatar}</>",
+            {"user": User(Profile("b.png"))},
+            "b.png",
+            id="object",
+        ),
+        pytest.param(
+            "<>{user?.profile?.avatar}</>",
+            {"user": User(None)},
+            "",
+            id="object-none",
+        ),
+        pytest.param(
+            "<>{user?.name}</>",
+            {"user": User(None)},
+            "",
+            id="object-missing-attribute",
+        ),
+        pytest.param("<>{items?[0]}</>", {"items": ["a"]}, "a", id="index"),
+        pytest.param("<>{items?[0]}</>", {"items": []}, "", id="index-empty"),
+        pytest.param("<>{items?[0]}</>", {"items": None}, "", id="index-none"),
+        pytest.param("<>{d?['k']}</>", {"d": {}}, "", id="index-missing-key"),
+        pytest.param(
+            "<>{items?[0]?.name}</>",
+            {"items": [{"name": "x"}]},
+            "x",
+            id="chain",
+        ),
+        pytest.param(
+            "<>{value ?? 'default'}</>", {"value": None}, "default", id="coalesce"
+        ),
+        pytest.param(
+            "<>{value ?? 'default'}</>", {"value": "v"}, "v", id="coalesce-value"
+        ),
+        pytest.param(
+            "<>{value ?? 'default'}</>", {"value": ""}, "", id="coalesce-falsy"
+        ),
+        pytest.param(
+            "<>{value ?? 'default'}</>", {"value": 0}, "0", id="coalesce-zero"
+        ),
+        pytest.param(
+            "<>{a ?? b ?? 'c'}</>", {"a": None, "b": None}, "c", id="coalesce-chain"
+        ),
+        pytest.param(
+            "<>{user?.profile?.avatar ?? 'default.png'}</>",
+            {"user": None},
+            "default.png",
+            id="coalesce-null-safe",
+        ),
+        pytest.param(
+            "<>{a ?? 1 + 2}</>", {"a": None}, "3", id="coalesce-precedence"
+        ),
+        pytest.param(
+            "<>{a or b ?? 'c'}</>",
+            {"a": None, "b": None},
+            "c",
+            id="coalesce-precedence-or",
+        ),
+        pytest.param(
+            "<>{a ?? undefined}</>",
+            {"a": "a"},
+            "a",
+            id="coalesce-short-circuit",
+        ),
+    ],
+)
+def test_null_safe(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,error",
+    [
+        pytest.param("<>{user.name}</>", {"user": {}}, KeyError, id="field"),
+        pytest.param("<>{user?.profile.avatar}</>", {"user": {}}, AttributeError, id="chain"),
+        pytest.param("<>{items[0]}</>", {"items": []}, IndexError, id="index"),
+    ],
+)
+def test_not_null_safe(
+    catalog: Catalog, template: str, params: dict[str, Any], error: type[Exception]
+):
+    with pytest.raises(error):
+        catalog.render(template, **params)
@@ -0,0 +1,149 @@
+from decimal import Decimal
+from typing import Any
+
+import pytest
+from xcomponent import Catalog
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param("<>{1.5}</>", {}, "1.5", id="float"),
+        pytest.param("<>{1_000.25}</>", {}, "1000.25", id="underscore"),
+        pytest.param("<>{1_000}</>", {}, "1000", id="underscore-int"),
+        pytest.param("<>{2e3}</>", {}, "2000.0", id="exponent"),
+        pytest.param("<>{1.5E-5}</>", {}, "1.5e-05", id="negative-exponent"),
+        pytest.param("<>{1e16}</>", {}, "1e+16", id="large"),
+        pytest.param("<>{x}</>", {"x": 0.1 + 0.2}, "0.30000000000000004", id="repr"),
+        pytest.param("<>{x}</>", {"x": float("nan")}, "nan", id="nan"),
+        pytest.param("<>{x}</>", {"x": float("-inf")}, "-inf", id="inf"),
+    ],
+)
+def test_float_literal(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template",
+    [
+        pytest.param("<>{1_}</>", id="trailing-underscore"),
+        pytest.param("<>{1__0}</>", id="double-underscore"),
+        pytest.param("<>{1_.5}</>", id="underscore-before-dot"),
+        pytest.param("<>{1.5_}</>", id="trailing-underscore-float"),
+        pytest.param("<>{1e3_}</>", id="trailing-underscore-exponent"),
+    ],
+)
+def test_invalid_number_literal(catalog: Catalog, template: str):
+    with pytest.raises(SyntaxError):
+        catalog.render(template)
+
+
+@pytest.mark.parametrize(
+    "template,expected",
+    [
+        pytest.param("<>{99999999999999999999}</>", "99999999999999999999", id="big"),
+        pytest.param(
+            "<>{2_0000000000_0000000000 + 1}</>", str(2 * 10**20 + 1), id="op"
+        ),
+        pytest.param("<>{-9223372036854775808}</>", "-9223372036854775808", id="min"),
+        pytest.param(
+            "<>{match 10**20 { 100000000000000000000 => 'big', _ => 'small' }}</>",
+            "big",
+            id="match",
+        ),
+        pytest.param(
+            "<>{match -10**20 { -100000000000000000000 => 'big', _ => 'small' }}</>",
+            "big",
+            id="match-negative",
+        ),
+    ],
+)
+def test_big_int_literal(catalog: Catalog, template: str, expected: str):
+    assert catalog.render(template) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param("<>{a + b}</>", {"a": 1, "b": 0.5}, "1.5", id="int-float"),
+        pytest.param("<>{a - b}</>", {"a": 0.5, "b": True}, "-0.5", id="float-bool"),
+        pytest.param("<>{a * 2.5}</>", {"a": 2}, "5.0", id="mul"),
+        pytest.param("<>{a / b}</>", {"a": 1, "b": 4}, "0.25", id="truediv"),
+        pytest.param(
+            "<>{a + b}</>",
+            {"a": Decimal("0.1"), "b": Decimal("0.2")},
+            "0.3",
+            id="decimal",
+        ),
+        pytest.param(
+            "<>{a * b}</>", {"a": Decimal("1.5"), "b": 2}, "3.0", id="decimal-int"
+        ),
+        pytest.param(
+            "<>{a / b}</>", {"a": Decimal(1), "b": Decimal(4)}, "0.25", id="decimal-div"
+        ),
+        pytest.param(
+            "<>{a * b}</>",
+            {"a": 2**62, "b": 4},
+            str(2**64),
+            id="overflow",
+        ),
+        pytest.param(
+            "<>{a + 1}</>", {"a": 2**70}, str(2**70 + 1), id="big-int"
+        ),
+        pytest.param(
+            "<>{a // 3}</>", {"a": 2**70}, str(2**70 // 3), id="big-int-floordiv"
+        ),
+        pytest.param(
+            "<>{a / b}</>", {"a": 2**60 + 1, "b": 2**60}, "1.0", id="big-int-div"
+        ),
+    ],
+)
+def test_arithmetic(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param("<>{a == 1}</>", {"a": 1.0}, "true", id="eq"),
+        pytest.param("<>{a > 1}</>", {"a": 1.5}, "true", id="gt"),
+        pytest.param("<>{a < b}</>", {"a": 1.5, "b": True}, "false", id="lt-bool"),
+        pytest.param(
+            "<>{a >= b}</>", {"a": Decimal("1.5"), "b": 1}, "true", id="decimal"
+        ),
+        pytest.param("<>{a > b}</>", {"a": 2**70, "b": 1.5}, "true", id="big-int"),
+        pytest.param("<>{a >= 1}</>", {"a": float("nan")}, "false", id="nan-gte"),
+        pytest.param("<>{a <= 1}</>", {"a": float("nan")}, "false", id="nan-lte"),
+        pytest.param("<>{1 >= a}</>", {"a": float("nan")}, "false", id="gte-nan"),
+        pytest.param("<>{1 <= a}</>", {"a": float("nan")}, "false", id="lte-nan"),
+        pytest.param("<>{a > 1}</>", {"a": float("nan")}, "false", id="nan-gt"),
+        pytest.param("<>{a < 1}</>", {"a": float("nan")}, "false", id="nan-lt"),
+        pytest.param("<>{a >= 1.5}</>", {"a": 1.5}, "true", id="gte-equal"),
+        pytest.param(
+            "<>{a <= b}</>", {"a": 2**70, "b": 2**70}, "true", id="lte-big-int"
+        ),
+        pytest.param("<>{not a}</>", {"a": 0.0}, "true", id="not-float"),
+        pytest.param("<>{not a}</>", {"a": Decimal(1)}, "false", id="not-decimal"),
+    ],
+)
+def test_compare(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param("<>{x}</>", {"x": Decimal("3.10")}, "3.10", id="decimal"),
+        pytest.param("<>{x}</>", {"x": 2**80}, str(2**80), id="big-int"),
+    ],
+)
+def test_render_number(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
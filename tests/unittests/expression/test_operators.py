@@ -0,0 +1,144 @@
+from typing import Any
+
+import pytest
+from xcomponent import Catalog
+
+
+class Bag:
+    def __init__(self, *items: Any) -> None:
+        self.items = items
+
+    def __contains__(self, item: Any) -> bool:
+        return item in self.items
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param("<>{a % b}</>", {"a": 7, "b": 3}, "1", id="mod"),
+        pytest.param("<>{a % b}</>", {"a": -7, "b": 3}, "2", id="mod-negative"),
+        pytest.param("<>{a % b}</>", {"a": 7, "b": -3}, "-2", id="mod-negative-divisor"),
+        pytest.param("<>{a % 2}</>", {"a": 7.5}, "1.5", id="mod-float"),
+        pytest.param("<>{a % 2 == 0}</>", {"a": 4}, "true", id="mod-eq"),
+        pytest.param("<>{1 + a % 3}</>", {"a": 5}, "3", id="mod-precedence"),
+        pytest.param("<>{2 ** 10}</>", {}, "1024", id="pow"),
+        pytest.param("<>{2 ** 3 ** 2}</>", {}, "512", id="pow-right-assoc"),
+        pytest.param("<>{2 * 3 ** 2}</>", {}, "18", id="pow-precedence"),
+        pytest.param("<>{2 ** -1}</>", {}, "0.5", id="pow-negative"),
+        pytest.param("<>{2 ** 0.5}</>", {}, "1.4142135623730951", id="pow-float"),
+        pytest.param("<>{2 ** 64}</>", {}, str(2**64), id="pow-overflow"),
+        pytest.param("<>{-a}</>", {"a": 3}, "-3", id="neg"),
+        pytest.param("<>{-1.5}</>", {}, "-1.5", id="neg-float"),
+        pytest.param("<>{- -a}</>", {"a": 3}, "3", id="neg-neg"),
+        pytest.param("<>{-a.b}</>", {"a": {"b": 3}}, "-3", id="neg-field"),
+        pytest.param("<>{-(a + 1)}</>", {"a": 3}, "-4", id="neg-parens"),
+        pytest.param("<>{-2 ** 2}</>", {}, "-4", id="neg-pow"),
+        pytest.param("<>{-a * 2}</>", {"a": 3}, "-6", id="neg-mul"),
+        pytest.param("<>{1 - -a}</>", {"a": 3}, "4", id="sub-neg"),
+        pytest.param("<>{7 // 2}</>", {}, "3", id="floordiv"),
+        pytest.param("<>{-7 // 2}</>", {}, "-4", id="floordiv-neg"),
+    ],
+)
+def test_arithmetic(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param("<>{a in b}</>", {"a": 2, "b": [1, 2]}, "true", id="list"),
+        pytest.param("<>{a in b}</>", {"a": 3, "b": [1, 2]}, "false", id="list-false"),
+        pytest.param("<>{a in b}</>", {"a": "x", "b": [1, 2]}, "false", id="list-type"),
+        pytest.param("<>{a not in b}</>", {"a": 3, "b": [1, 2]}, "true", id="not-in"),
+        pytest.param("<>{'a' in b}</>", {"b": {"a": 1}}, "true", id="dict"),
+        pytest.param("<>{'c' not in b}</>", {"b": {"a": 1}}, "true", id="dict-not-in"),
+        pytest.param("<>{'ell' in b}</>", {"b": "hello"}, "true", id="str"),
+        pytest.param("<>{'x' in b}</>", {"b": "hello"}, "false", id="str-false"),
+        pytest.param("<>{a in b}</>", {"a": 1, "b": Bag(1)}, "true", id="object"),
+        pytest.param("<>{a in b}</>", {"a": 2, "b": Bag(1)}, "false", id="object-false"),
+        pytest.param(
+            "<>{a in b and a + 1 not in b}</>",
+            {"a": 1, "b": [1]},
+            "true",
+            id="precedence",
+        ),
+        pytest.param(
+            "<>{not a in b}</>", {"a": 1, "b": [1]}, "false", id="not-precedence"
+        ),
+        pytest.param(
+            "<>{if a in b {<>yes</>} else {<>no</>}}</>",
+            {"a": 1, "b": [1]},
+            "yes",
+            id="if",
+        ),
+        pytest.param("<>{index in b}</>", {"index": 1, "b": [1]}, "true", id="ident-in"),
+    ],
+)
+def test_membership(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param("<>{a is None}</>", {"a": None}, "true", id="none"),
+        pytest.param("<>{a is None}</>", {"a": 0}, "false", id="zero"),
+        pytest.param("<>{a is not None}</>", {"a": ""}, "true", id="is-not"),
+        pytest.param("<>{a is not None}</>", {"a": None}, "false", id="is-not-none"),
+        pytest.param("<>{a is true}</>", {"a": True}, "true", id="bool"),
+        pytest.param("<>{a is b}</>", {"a": Bag(), "b": None}, "false", id="object"),
+        pytest.param("<>{a is a}</>", {"a": Bag()}, "true", id="same-object"),
+        pytest.param("<>{a.b is None}</>", {"a": {"b": None}}, "true", id="field"),
+        pytest.param(
+            "<>{if a is not None {<>{a}</>} else {<>-</>}}</>",
+            {"a": None},
+            "-",
+            id="if",
+        ),
+        pytest.param(
+            "<>{isbn is notes}</>", {"isbn": 1, "notes": 1}, "false", id="ident-is"
+        ),
+    ],
+)
+def test_identity(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,error,message",
+    [
+        pytest.param(
+            "<>{a % b}</>", {"a": 1, "b": 0}, ZeroDivisionError, "Modulo by zero", id="mod"
+        ),
+        pytest.param(
+            "<>{-a}</>",
+            {"a": "x"},
+            TypeError,
+            'Cannot negate Str("x"), type mismatch',
+            id="neg",
+        ),
+        pytest.param(
+            "<>{a in b}</>",
+            {"a": 1, "b": 2},
+            TypeError,
+            "Cannot check membership Int(1) in Int(2), type mismatch",
+            id="in",
+        ),
+    ],
+)
+def test_errors(
+    catalog: Catalog,
+    template: str,
+    params: dict[str, Any],
+    error: type[Exception],
+    message: str,
+):
+    with pytest.raises(error) as exc:
+        catalog.render(template, **params)
+    assert str(exc.value) == message
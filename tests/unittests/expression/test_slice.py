@@ -0,0 +1,103 @@
+from typing import Any
+
+import pytest
+from xcomponent import Catalog
+
+
+class QuerySet:
+    """Sequence without negative indexes, like a Django queryset."""
+
+    def __init__(self, *items: Any) -> None:
+        self.items = list(items)
+
+    def __len__(self) -> int:
+        return len(self.items)
+
+    def __getitem__(self, key: int | slice) -> Any:
+        if isinstance(key, int) and key < 0:
+            raise AssertionError("Negative indexing is not supported.")
+        return self.items[key]
+
+
+class Registry:
+    def __getitem__(self, key: str) -> str:
+        return key.upper()
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param("<>{l[0]}</>", {"l": ["a", "b"]}, "a", id="list-first"),
+        pytest.param("<>{l[-1]}</>", {"l": ["a", "b"]}, "b", id="list-negative"),
+        pytest.param("<>{l[-2]}</>", {"l": ["a", "b"]}, "a", id="list-negative-first"),
+        pytest.param("<>{l[1:3] | join(',')}</>", {"l": [0, 1, 2, 3]}, "1,2", id="slice"),
+        pytest.param("<>{l[:2] | join(',')}</>", {"l": [0, 1, 2]}, "0,1", id="slice-stop"),
+        pytest.param("<>{l[1:] | join(',')}</>", {"l": [0, 1, 2]}, "1,2", id="slice-start"),
+        pytest.param("<>{l[:] | join(',')}</>", {"l": [0, 1]}, "0,1", id="slice-copy"),
+        pytest.param(
+            "<>{l[::-1] | join(',')}</>", {"l": [0, 1, 2]}, "2,1,0", id="slice-reverse"
+        ),
+        pytest.param(
+            "<>{l[::2] | join(',')}</>", {"l": [0, 1, 2, 3, 4]}, "0,2,4", id="slice-step"
+        ),
+        pytest.param(
+            "<>{l[-2:] | join(',')}</>", {"l": [0, 1, 2]}, "1,2", id="slice-negative"
+        ),
+        pytest.param(
+            "<>{l[5:10] | length}</>", {"l": [0, 1, 2]}, "0", id="slice-out-of-range"
+        ),
+        pytest.param(
+            "<>{l[a:b] | join(',')}</>",
+            {"l": [0, 1, 2, 3], "a": 1, "b": 3},
+            "1,2",
+            id="slice-variables",
+        ),
+        pytest.param(
+            "<>{l[ 1 + 1 : ] | join(',')}</>",
+            {"l": [0, 1, 2, 3]},
+            "2,3",
+            id="slice-expressions",
+        ),
+        pytest.param("<>{name[0]}</>", {"name": "Bob"}, "B", id="str-index"),
+        pytest.param("<>{name[-1]}</>", {"name": "Bob"}, "b", id="str-negative"),
+        pytest.param("<>{name[0]}</>", {"name": "été"}, "é", id="str-unicode"),
+        pytest.param("<>{name[:2]}</>", {"name": "Bob"}, "Bo", id="str-slice"),
+        pytest.param("<>{name[::-1]}</>", {"name": "Bob"}, "boB", id="str-reverse"),
+        pytest.param("<>{t[-1]}</>", {"t": (1, 2)}, "2", id="tuple-negative"),
+        pytest.param("<>{t[1:] | join(',')}</>", {"t": (1, 2, 3)}, "2,3", id="tuple-slice"),
+        pytest.param(
+            "<>{qs[-1]}</>", {"qs": QuerySet("a", "b")}, "b", id="object-negative"
+        ),
+        pytest.param(
+            "<>{qs[0:1] | join(',')}</>",
+            {"qs": QuerySet("a", "b")},
+            "a",
+            id="object-slice",
+        ),
+        pytest.param(
+            "<>{registry['key']}</>", {"registry": Registry()}, "KEY", id="object-str-key"
+        ),
+        pytest.param("<>{l?[1:]}</>", {"l": None}, "", id="null-safe-slice"),
+    ],
+)
+def test_index(catalog: Catalog, template: str, params: dict[str, Any], expected: str):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,error",
+    [
+        pytest.param("<>{l[2]}</>", {"l": [0, 1]}, IndexError, id="list-out-of-range"),
+        pytest.param("<>{l[-3]}</>", {"l": [0, 1]}, IndexError, id="list-negative-out-of-range"),
+        pytest.param("<>{s[3]}</>", {"s": "abc"}, IndexError, id="str-out-of-range"),
+        pytest.param("<>{l['a']}</>", {"l": [0]}, TypeError, id="list-str-index"),
+        pytest.param("<>{l[::0]}</>", {"l": [0]}, ValueError, id="slice-step-zero"),
+        pytest.param("<>{l['a':]}</>", {"l": [0]}, TypeError, id="slice-str-bound"),
+        pytest.param("<>{n[1:]}</>", {"n": 1}, TypeError, id="slice-int"),
+    ],
+)
+def test_index_error(
+    catalog: Catalog, template: str, params: dict[str, Any], error: type[Exception]
+):
+    with pytest.raises(error):
+        catalog.render(template, **params)
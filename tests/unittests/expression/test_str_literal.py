@@ -0,0 +1,109 @@
+from typing import Any
+
+import pytest
+from xcomponent import Catalog, Markup
+
+
+class Obj:
+    def __repr__(self) -> str:
+        return "<Obj>"
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(r"<>{'a\nb'}</>", {}, "a\nb", id="newline"),
+        pytest.param(r'<>{"a\tb\rc"}</>', {}, "a\tb\rc", id="tab"),
+        pytest.param(r"<>{'it\'s'}</>", {}, "it's", id="single-quote"),
+        pytest.param(r'<>{"say \"hi\""}</>', {}, 'say "hi"', id="double-quote"),
+        pytest.param(r"<>{'a\\b'}</>", {}, "a\\b", id="backslash"),
+        pytest.param(r"<>{'\u{e9}t\u{E9}'}</>", {}, "été", id="unicode"),
+        pytest.param(r"<>{'\u{1F600}'}</>", {}, "\U0001f600", id="unicode-astral"),
+        pytest.param(r"<>{length('a\0b')}</>", {}, "3", id="nul"),
+        pytest.param(
+            "<>{'''\n    a\\n\n    b\n    '''}</>", {}, "a\n\nb\n", id="dedent-string"
+        ),
+        pytest.param(r"<>{'a\nb' == x}</>", {"x": "a\nb"}, "true", id="compare"),
+    ],
+)
+def test_escape_sequence(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+def test_invalid_unicode_escape(catalog: Catalog):
+    with pytest.raises(SyntaxError) as exc:
+        catalog.render(r"<>{'\u{D800}'}</>")
+    assert "Invalid unicode escape \\u{D800}" in str(exc.value)
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            '<>{f"Hello {user.name}!"}</>',
+            {"user": {"name": "Bob"}},
+            "Hello Bob!",
+            id="field",
+        ),
+        pytest.param("<>{f'{a} + {b} = {a + b}'}</>", {"a": 1, "b": 2}, "1 + 2 = 3", id="int"),
+        pytest.param("<>{f'{x}'}</>", {"x": 1.5}, "1.5", id="float"),
+        pytest.param("<>{f'{x}|'}</>", {"x": None}, "|", id="none"),
+        pytest.param("<>{f'{x}'}</>", {"x": True}, "true", id="bool"),
+        pytest.param("<>{f'{x}'}</>", {"x": [1, "a"]}, "1a", id="list"),
+        pytest.param("<>{f'{x}'}</>", {"x": [True, [2.5]]}, "true2.5", id="nested"),
+        pytest.param("<>{f'{x}'}</>", {"x": {"k": False}}, '"k"false', id="dict"),
+        pytest.param("<>{f'{x}'}</>", {"x": Obj()}, "&lt;Obj&gt;", id="object"),
+        pytest.param("<>{f''}</>", {}, "", id="empty"),
+        pytest.param("<>{f'plain'}</>", {}, "plain", id="text"),
+        pytest.param("<>{f'{{x}}'}</>", {"x": 1}, "{x}", id="braces"),
+        pytest.param(r"<>{f'a\n{x}'}</>", {"x": 1}, "a\n1", id="escape"),
+        pytest.param(
+            "<>{f\"{d['k']}\"}</>", {"d": {"k": "v"}}, "v", id="nested-string"
+        ),
+        pytest.param(
+            "<>{f'{name | upper}'}</>", {"name": "bob"}, "BOB", id="filter"
+        ),
+        pytest.param(
+            "<>{f'<b>{name}</b>'}</>",
+            {"name": "<i>"},
+            "&lt;b&gt;&lt;i&gt;&lt;/b&gt;",
+            id="escaped",
+        ),
+        pytest.param(
+            "<>{f'{name}'}</>",
+            {"name": Markup("<i>x</i>")},
+            "&lt;i&gt;x&lt;/i&gt;",
+            id="markup-is-str",
+        ),
+        pytest.param(
+            "<a href={f'/users/{id}'}>x</a>", {"id": 42}, '<a href="/users/42">x</a>', id="attr"
+        ),
+        pytest.param("<>{f'{x}' + '!'}</>", {"x": 1}, "1!", id="concat"),
+        pytest.param("<>{length(f'{x}')}</>", {"x": 100}, "3", id="call"),
+        pytest.param("<>{f}</>", {"f": "var"}, "var", id="f-variable"),
+    ],
+)
+def test_fstring(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "value",
+    [
+        pytest.param(None, id="none"),
+        pytest.param(True, id="bool"),
+        pytest.param(42, id="int"),
+        pytest.param(0.1, id="float"),
+        pytest.param("<i>", id="str"),
+        pytest.param([1, [False, "a"]], id="list"),
+        pytest.param(Obj(), id="object"),
+    ],
+)
+def test_fstring_renders_like_markup(catalog: Catalog, value: Any):
+    assert catalog.render("<p>{f'{x}'}</p>", x=value) == catalog.render(
+        "<p>{x}</p>", x=value
+    )
@@ -0,0 +1,55 @@
+import pytest
+from xcomponent import Catalog
+from xcomponent.service.catalog import CacheInfo
+
+
+@pytest.fixture(autouse=True)
+def components(catalog: Catalog):
+    @catalog.component
+    def Item(name: str) -> str:
+        return """<li>{name.upper()}</li>"""
+
+    @catalog.component
+    def List(names: list[str]) -> str:
+        return """<ul>{for name in names { <Item name={name} /> }}</ul>"""
+
+
+def test_render_cached(catalog: Catalog):
+    assert catalog.render("<List names={names} />", names=["a", "b"]) == (
+        "<ul><li>A</li><li>B</li></ul>"
+    )
+    assert catalog.render("<List names={names} />", names=["c"]) == (
+        "<ul><li>C</li></ul>"
+    )
+    assert catalog.render("<Item name='d' />") == "<li>D</li>"
+    assert catalog.cache_info() == CacheInfo(
+        hits=1, misses=2, maxsize=128, currsize=2
+    )
+
+
+def test_cache_eviction():
+    catalog = Catalog(cache_size=2)
+    for template in ["<p>1</p>", "<p>2</p>", "<p>1</p>", "<p>3</p>", "<p>2</p>"]:
+        catalog.render(template)
+    assert catalog.cache_info() == CacheInfo(hits=1, misses=4, maxsize=2, currsize=2)
+
+
+def test_cache_disabled():
+    catalog = Catalog(cache_size=0)
+    catalog.render("<p>{x}</p>", x=1)
+    assert catalog.render("<p>{x}</p>", x=2) == "<p>2</p>"
+    assert catalog.cache_info() == CacheInfo(hits=0, misses=2, maxsize=0, currsize=0)
+
+
+def test_cache_clear(catalog: Catalog):
+    catalog.render("<p>{x}</p>", x=1)
+    catalog.render("<p>{x}</p>", x=1)
+    catalog.cache_clear()
+    assert catalog.cache_info() == CacheInfo(hits=0, misses=0, maxsize=128, currsize=0)
+
+
+def test_cached_error(catalog: Catalog):
+    for _ in range(2):
+        with pytest.raises(UnboundLocalError):
+            catalog.render("<p>{missing}</p>")
+    assert catalog.render("<p>{missing}</p>", missing="ok") == "<p>ok</p>"
@@ -0,0 +1,212 @@
+from pathlib import Path
+from typing import Any, Literal, Optional
+
+import pytest
+
+from xcomponent import Catalog, XComponentRenderError, XNode
+
+TEMPLATES = {
+    "Card.xhtml": """
+        <!-- props: title: str, children: XNode, count: int = 0 -->
+        <div><h2>{title}</h2>{children}{count}</div>
+    """,
+    "Page.xcomponent": """
+        <!-- props: title: str -->
+        <ui.Layout title={title}><ui.Button label="ok" /></ui.Layout>
+    """,
+    "Hello.xhtml": "<p>Hello</p>",
+    "notes.txt": "not a template",
+    "ui/Button.xhtml": """
+        <!--
+          props:
+            label: str,
+            options: dict[str, int] = {"a": 1, "b": 2},
+            kind: Literal["primary", "secondary"] = "primary",
+        -->
+        <button class={kind}>{label}</button>
+    """,
+    "ui/Layout.xhtml": """
+        <!-- props: title: str, children: XNode -->
+        <main><h1>{title}</h1>{children}<forms.Input /></main>
+    """,
+    "ui/forms/Input.xhtml": "<!-- props: name: str = 'q' --><input name={name} />",
+    "__pycache__/cache.pyc": "",
+}
+
+
+def write_templates(path: Path, templates: dict[str, str]) -> None:
+    for name, template in templates.items():
+        file = path / name
+        file.parent.mkdir(parents=True, exist_ok=True)
+        file.write_text(template)
+
+
+@pytest.fixture
+def catalog(tmp_path: Path) -> Catalog:
+    write_templates(tmp_path, TEMPLATES)
+    catalog = Catalog(validation="strict")
+    catalog.load_directory(tmp_path)
+    return catalog
+
+
+@pytest.mark.parametrize(
+    "template,expected",
+    [
+        pytest.param("<Hello />", "<p>Hello</p>", id="no-props"),
+        pytest.param(
+            "<Card title='a'>b</Card>", "<div><h2>a</h2>b0</div>", id="default"
+        ),
+        pytest.param(
+            "<Card title='a' count={2}>b</Card>", "<div><h2>a</h2>b2</div>", id="props"
+        ),
+        pytest.param(
+            "<ui.Button label='ok' />",
+            '<button class="primary">ok</button>',
+            id="namespace",
+        ),
+        pytest.param(
+            "<Page title='t' />",
+            '<main><h1>t</h1><button class="primary">ok</button>'
+            '<input name="q"/></main>',
+            id="nested-namespace",
+        ),
+        pytest.param(
+            "<ui.forms.Input name='search' />",
+            '<input name="search"/>',
+            id="two-level-namespace",
+        ),
+    ],
+)
+def test_render_directory(catalog: Catalog, template: str, expected: str):
+    assert catalog.render(template) == expected
+
+
+@pytest.mark.parametrize(
+    "name,params,defaults",
+    [
+        pytest.param("Hello", {}, {}, id="no-props"),
+        pytest.param(
+            "Card",
+            {"title": str, "children": XNode, "count": int},
+            {"count": 0},
+            id="props",
+        ),
+    ],
+)
+def test_directory_props(
+    catalog: Catalog, name: str, params: dict[str, Any], defaults: dict[str, Any]
+):
+    template = catalog._catalog.get(name)
+    assert template.params == params
+    assert template.defaults == defaults
+
+
+@pytest.mark.parametrize(
+    "annotation,expected",
+    [
+        pytest.param("list[str] | None", list[str] | None, id="union"),
+        pytest.param("Optional[dict[str, int]]", Optional[dict[str, int]], id="nested"),
+        pytest.param("Literal['a', 1, None]", Literal["a", 1, None], id="literal"),
+    ],
+)
+def test_directory_annotations(tmp_path: Path, annotation: str, expected: Any):
+    templates = {"Card.xhtml": f"<!-- props: a: {annotation} --><p />"}
+    write_templates(tmp_path, templates)
+    catalog = Catalog()
+    catalog.load_directory(tmp_path)
+    assert catalog._catalog.get("Card").params == {"a": expected}
+
+
+def test_directory_unknown_files(catalog: Catalog):
+    assert catalog.render("<notes />") == "<notes/>"
+
+
+@pytest.mark.parametrize(
+    "template,error",
+    [
+        pytest.param("<ui.Unknown />", "Unknown component <ui.Unknown/>", id="name"),
+        pytest.param(
+            "<ui.unknown.Input />",
+            "Reference to unknown catalog ui.unknown",
+            id="namespace",
+        ),
+    ],
+)
+def test_directory_unknown_component(catalog: Catalog, template: str, error: str):
+    with pytest.raises(ValueError) as exc:
+        catalog.render(template)
+    assert error in str(exc.value)
+
+
+def test_directory_validation(catalog: Catalog):
+    with pytest.raises(XComponentRenderError) as exc:
+        catalog.render("<ui.Button label={1} />")
+    assert "attribute 'label' expects str, got int" in str(exc.value)
+
+
+@pytest.mark.parametrize(
+    "templates,error",
+    [
+        pytest.param(
+            {"Card.xhtml": "<!-- props: title: Unknown --><p />"},
+            "Invalid props of component <Card/>",
+            id="annotation",
+        ),
+        pytest.param(
+            {
+                "Card.xhtml": (
+                    "<!-- props:\n  title: str,\n"
+                    "  cls: ().__class__.__base__.__subclasses__()\n--><p />"
+                )
+            },
+            'line 3, invalid annotation "().__class__.__base__.__subclasses__()"',
+            id="attribute",
+        ),
+        pytest.param(
+            {"Card.xhtml": "<!-- props: title: str = open('x') --><p />"},
+            "a literal is expected",
+            id="default",
+        ),
+        pytest.param(
+            {"Card.xhtml": "<!-- props: title, title --><p />"},
+            'duplicate name "title"',
+            id="duplicate",
+        ),
+        pytest.param(
+            {"my-card.xhtml": "<p />"},
+            'Invalid component name "my-card"',
+            id="name",
+        ),
+        pytest.param(
+            {"Card.xhtml": "<p><p>"},
+            "Cannot parse component <Card/>",
+            id="markup",
+        ),
+    ],
+)
+def test_directory_errors(tmp_path: Path, templates: dict[str, str], error: str):
+    write_templates(tmp_path, templates)
+    with pytest.raises(ValueError) as exc:
+        Catalog().load_directory(tmp_path)
+    assert error in str(exc.value)
+
+
+def test_directory_error_location(tmp_path: Path):
+    write_templates(
+        tmp_path, {"Card.xhtml": "<!-- props: title: str -->\n<p>{name}</p>"}
+    )
+    catalog = Catalog()
+    catalog.load_directory(tmp_path)
+    with pytest.raises(UnboundLocalError) as exc:
+        catalog.render("<Card title='a' />")
+    assert exc.value.__notes__[0] == (
+        f"component `{tmp_path / 'Card.xhtml'}`, line 2, col 5\n"
+        "  |\n"
+        "2 | <p>{name}</p>\n"
+        "  |     ^^^^"
+    )
+
+
+def test_directory_not_found(tmp_path: Path):
+    with pytest.raises(FileNotFoundError):
+        Catalog().load_directory(tmp_path / "missing")
@@ -0,0 +1,114 @@
+from typing import Any
+
+import pytest
+from xcomponent import Catalog, XComponentRenderError
+
+
+@pytest.fixture(autouse=True)
+def components(catalog: Catalog):
+    @catalog.component
+    def Card(title: str, count: int) -> str:
+        return """
+            <div>
+                <h1>{title}</h1>
+                <p>{title + count}</p>
+            </div>
+        """
+
+    @catalog.component
+    def Greeting() -> str:
+        return """<p>Hello {name}</p>"""
+
+    @catalog.component
+    def Page(count: int) -> str:
+        return """<main><Card title="x" count={count} /></main>"""
+
+    @catalog.component
+    def BadPage(count: int) -> str:
+        return """<main><Card title={count + "x"} count={count} /></main>"""
+
+    @catalog.function
+    def lookup(key: str) -> str:
+        raise KeyError(key)
+
+
+def test_type_error_location(catalog: Catalog):
+    with pytest.raises(TypeError) as exc:
+        catalog.render("<Card title='x' count={1}/>")
+    assert exc.value.__notes__[0] == (
+        "component `Card`, line 4, col 27\n"
+        "  |\n"
+        "4 |                 <p>{title + count}</p>\n"
+        "  |                           ^"
+    )
+
+
+def test_undefined_variable_location(catalog: Catalog):
+    with pytest.raises(UnboundLocalError) as exc:
+        catalog.render("<Greeting/>")
+    assert str(exc.value) == '"name" is undefined'
+    assert exc.value.__notes__[0] == (
+        "component `Greeting`, line 1, col 11\n"
+        "  |\n"
+        "1 | <p>Hello {name}</p>\n"
+        "  |           ^^^^"
+    )
+
+
+def test_template_location(catalog: Catalog):
+    with pytest.raises(UnboundLocalError) as exc:
+        catalog.render("<div>\n  {missing}\n</div>")
+    assert exc.value.__notes__ == [
+        "template, line 2, col 4\n"
+        "  |\n"
+        "2 |   {missing}\n"
+        "  |    ^^^^^^^"
+    ]
+
+
+@pytest.mark.parametrize(
+    "template,error,stack",
+    [
+        pytest.param("<Greeting/>", UnboundLocalError, ["<Greeting>"], id="component"),
+        pytest.param(
+            "<Page count={1}/>", TypeError, ["<Page>", "<Card>"], id="nested"
+        ),
+        pytest.param(
+            "<BadPage count={1}/>",
+            TypeError,
+            ["<BadPage>", '<Card title={count + "x"}>'],
+            id="attribute",
+        ),
+        pytest.param("<p>{lookup('k')}</p>", KeyError, ["lookup()"], id="function"),
+    ],
+)
+def test_component_stack(
+    catalog: Catalog, template: str, error: type[Exception], stack: list[str]
+):
+    with pytest.raises(XComponentRenderError) as exc:
+        catalog.render(template)
+    assert isinstance(exc.value, error)
+    assert exc.value.component_stack == stack
+    assert "component stack: " + " > ".join(stack) in exc.value.__notes__
+
+
+def test_component_stack_namespace(catalog: Catalog):
+    ui = Catalog()
+
+    @ui.component
+    def Button(label: str) -> str:
+        return """<button>{label.upper()}</button>"""
+
+    @catalog.component(use={"ui": ui})
+    def Form(label: Any) -> str:
+        return """<form><ui.Button label={label} /></form>"""
+
+    with pytest.raises(AttributeError) as exc:
+        catalog.render("<Form label={label} />", label=None)
+    assert exc.value.component_stack == ["<Form>", "<ui.Button>"]
+
+
+def test_template_error_has_no_stack(catalog: Catalog):
+    with pytest.raises(UnboundLocalError) as exc:
+        catalog.render("<p>{missing}</p>")
+    assert not isinstance(exc.value, XComponentRenderError)
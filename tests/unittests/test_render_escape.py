@@ -0,0 +1,291 @@
+from typing import Any
+
+import pytest
+from xcomponent import Catalog, Markup, XNode
+
+
+class Html:
+    """An object implementing the __html__ protocol, like markupsafe.Markup."""
+
+    def __init__(self, value: str) -> None:
+        self.value = value
+
+    def __html__(self) -> str:
+        return self.value
+
+
+@pytest.fixture(autouse=True)
+def components(catalog: Catalog):
+    @catalog.component
+    def Title(title: str) -> str:
+        return """<h1>{title}</h1>"""
+
+    @catalog.component
+    def Card(title: str, children: XNode) -> str:
+        return """<div><Title title={title} />{children}</div>"""
+
+    @catalog.component
+    def Excerpt(text: str) -> str:
+        return """<p>{text}</p>"""
+
+    @catalog.function
+    def bold(text: str) -> Markup:
+        return Markup(f"<b>{text}</b>")
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            "<p>{name}</p>",
+            {"name": "<script>alert('xss')</script>"},
+            "<p>&lt;script&gt;alert('xss')&lt;/script&gt;</p>",
+            id="str",
+        ),
+        pytest.param(
+            "<p>{user.name}</p>",
+            {"user": {"name": "Tom & Jerry"}},
+            "<p>Tom &amp; Jerry</p>",
+            id="field",
+        ),
+        pytest.param(
+            "<p>{name}</p>",
+            {"name": Markup("<em>safe</em>")},
+            "<p><em>safe</em></p>",
+            id="markup",
+        ),
+        pytest.param(
+            "<p>{name}</p>",
+            {"name": Html("<em>safe</em>")},
+            "<p><em>safe</em></p>",
+            id="__html__",
+        ),
+        pytest.param(
+            "<p>{name + '<br/>'}</p>",
+            {"name": Markup("<em>safe</em>")},
+            "<p><em>safe</em>&lt;br/&gt;</p>",
+            id="markup-concat",
+        ),
+        pytest.param(
+            "<p>{bold(name)}</p>",
+            {"name": "x"},
+            "<p><b>x</b></p>",
+            id="function-markup",
+        ),
+        pytest.param(
+            "<ul>{for name in names {<li>{name}</li>}}</ul>",
+            {"names": ["<a>", "<b>"]},
+            "<ul><li>&lt;a&gt;</li><li>&lt;b&gt;</li></ul>",
+            id="for",
+        ),
+        pytest.param(
+            "<Title title={title} />",
+            {"title": "<i>x</i>"},
+            "<h1>&lt;i&gt;x&lt;/i&gt;</h1>",
+            id="component-attr",
+        ),
+        pytest.param(
+            "<Title title='a & b' />",
+            {},
+            "<h1>a &amp; b</h1>",
+            id="component-text-attr",
+        ),
+        pytest.param(
+            "<Card title='a & b'><p>{text}</p></Card>",
+            {"text": "<i>x</i>"},
+            "<div><h1>a &amp; b</h1><p>&lt;i&gt;x&lt;/i&gt;</p></div>",
+            id="children",
+        ),
+        pytest.param(
+            "<Excerpt text={<b>{'<i>'}</b>} />",
+            {},
+            "<p><b>&lt;i&gt;</b></p>",
+            id="xnode-attr",
+        ),
+    ],
+)
+def test_render_escape(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+def test_markup_type():
+    markup = Markup("<br/>")
+    assert isinstance(markup, str)
+    assert markup.__html__() == "<br/>"
+    assert repr(markup) == "Markup('<br/>')"
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            "<input value={value}/>",
+            {"value": '" onfocus="alert(1)'},
+            '<input value="&quot; onfocus=&quot;alert(1)"/>',
+            id="double-quote",
+        ),
+        pytest.param(
+            "<input value={value}/>",
+            {"value": "it's"},
+            '<input value="it\'s"/>',
+            id="single-quote",
+        ),
+        pytest.param(
+            "<input value='a \"b\"'/>",
+            {},
+            '<input value="a &quot;b&quot;"/>',
+            id="text",
+        ),
+        pytest.param(
+            "<input value={value}/>",
+            {"value": Markup("a &amp; &quot;b&quot;")},
+            '<input value="a &amp; &quot;b&quot;"/>',
+            id="markup",
+        ),
+        pytest.param(
+            "<a href={url}>x</a>",
+            {"url": "/search?q=a&page=2"},
+            '<a href="/search?q=a&amp;page=2">x</a>',
+            id="url",
+        ),
+        pytest.param(
+            "<a href={url}>x</a>",
+            {"url": "javascript:alert(1)"},
+            '<a href="about:invalid#unsafe-url">x</a>',
+            id="javascript-url",
+        ),
+        pytest.param(
+            "<a href={url}>x</a>",
+            {"url": " Java\tScript:alert(1)"},
+            '<a href="about:invalid#unsafe-url">x</a>',
+            id="obfuscated-javascript-url",
+        ),
+        pytest.param(
+            '<a href="javascript:void(0)">x</a>',
+            {},
+            '<a href="javascript:void(0)">x</a>',
+            id="literal-javascript-url",
+        ),
+        pytest.param(
+            '<a href="javascript:void(0)" {...attrs}>x</a>',
+            {"attrs": {"class": "btn"}},
+            '<a href="javascript:void(0)" class="btn">x</a>',
+            id="literal-javascript-url-spread",
+        ),
+        pytest.param(
+            '<a href="javascript:void(0)" {...attrs}>x</a>',
+            {"attrs": {"href": "javascript:alert(1)"}},
+            '<a href="about:invalid#unsafe-url">x</a>',
+            id="spread-javascript-url",
+        ),
+        pytest.param(
+            "<img src={url}/>",
+            {"url": "data:image/png;base64,iVBORw0KGgo="},
+            '<img src="data:image/png;base64,iVBORw0KGgo="/>',
+            id="data-image-url",
+        ),
+        pytest.param(
+            "<iframe src={url}/>",
+            {"url": "data:text/html,<script>alert(1)</script>"},
+            '<iframe src="about:invalid#unsafe-url"/>',
+            id="data-html-url",
+        ),
+        pytest.param(
+            "<img srcset={url}/>",
+            {"url": "javascript:alert(1)"},
+            '<img srcset="about:invalid#unsafe-url"/>',
+            id="srcset-javascript-url",
+        ),
+        pytest.param(
+            "<img srcset={urls}/>",
+            {"urls": "/a.png 1x, javascript:alert(1) 2x"},
+            '<img srcset="about:invalid#unsafe-url"/>',
+            id="srcset-javascript-candidate",
+        ),
+        pytest.param(
+            "<img srcset={urls}/>",
+            {"urls": "/a.png 1x,/b.png 2x, data:image/png;base64,iVBO= 3x"},
+            '<img srcset="/a.png 1x,/b.png 2x, data:image/png;base64,iVBO= 3x"/>',
+            id="srcset",
+        ),
+        pytest.param(
+            "<a href='/' ping={urls}>x</a>",
+            {"urls": "/track javascript:alert(1)"},
+            '<a href="/" ping="about:invalid#unsafe-url">x</a>',
+            id="ping-javascript-url",
+        ),
+        pytest.param(
+            "<a href='/' ping={urls}>x</a>",
+            {"urls": "/track /count"},
+            '<a href="/" ping="/track /count">x</a>',
+            id="ping",
+        ),
+        pytest.param(
+            "<object data={url}></object>",
+            {"url": "javascript:alert(1)"},
+            '<object data="about:invalid#unsafe-url"></object>',
+            id="data-javascript-url",
+        ),
+        pytest.param(
+            "<html manifest={url}></html>",
+            {"url": "javascript:alert(1)"},
+            '<html manifest="about:invalid#unsafe-url"></html>',
+            id="manifest-javascript-url",
+        ),
+        pytest.param(
+            "<button {...attrs}>x</button>",
+            {"attrs": {"formaction": "javascript:alert(1)"}},
+            '<button formaction="about:invalid#unsafe-url">x</button>',
+            id="spread-formaction-javascript-url",
+        ),
+        pytest.param(
+            "<img {...attrs}/>",
+            {"attrs": {"srcset": "/a.png 1x, javascript:alert(1) 2x"}},
+            '<img srcset="about:invalid#unsafe-url"/>',
+            id="spread-srcset-javascript-url",
+        ),
+        pytest.param(
+            "<script src={url}></script>",
+            {"url": "/static/app.js?v=1&t=2"},
+            '<script src="/static/app.js?v=1&amp;t=2"></script>',
+            id="script-attr",
+        ),
+    ],
+)
+def test_render_escape_attribute(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            '<script type="application/json">{data}</script>',
+            {"data": {"name": "</script><script>alert(1)</script>"}},
+            '<script type="application/json">'
+            '{"name":"\\u003c/script\\u003e\\u003cscript\\u003ealert(1)'
+            '\\u003c/script\\u003e"}</script>',
+            id="json",
+        ),
+        pytest.param(
+            '<script type="application/ld+json">{ items }</script>',
+            {"items": [1, "a\nb", None, True]},
+            '<script type="application/ld+json">[1,"a\\nb",null,true]</script>',
+            id="ld+json",
+        ),
+        pytest.param(
+            '<script type="application/ld+json">{"@type": "Person"}</script>',
+            {},
+            '<script type="application/ld+json">{"@type": "Person"}</script>',
+            id="literal-json",
+        ),
+    ],
+)
+def test_render_escape_script(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
@@ -0,0 +1,121 @@
+from typing import Any
+
+import pytest
+
+from xcomponent import Catalog, XNode
+
+
+@pytest.fixture(autouse=True)
+def components(catalog: Catalog, ui_catalog: Catalog):
+    @catalog.component
+    def Button(children: XNode) -> str:
+        return """<button class={inject("theme", "light")}>{children}</button>"""
+
+    @catalog.component
+    def Toolbar() -> str:
+        return """<nav><Button>a</Button><Button>b</Button></nav>"""
+
+    @catalog.component
+    def Dark(children: XNode) -> str:
+        return """<Provider theme="dark">{children}</Provider>"""
+
+    @catalog.component
+    def User() -> str:
+        return """<span>{inject("current_user").name}</span>"""
+
+    @catalog.component
+    def Provide() -> str:
+        return """<>{provide("theme", "blue")}<Button>a</Button></>"""
+
+    @catalog.component(use={"ui": ui_catalog})
+    def Badge() -> str:
+        return """<Provider color="red"><ui.Badge /></Provider>"""
+
+
+@pytest.fixture
+def ui_catalog() -> Catalog:
+    ui = Catalog()
+
+    @ui.component
+    def Badge() -> str:
+        return """<b>{inject("color")}</b>"""
+
+    return ui
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            "<Button>a</Button>",
+            {},
+            '<button class="light">a</button>',
+            id="default",
+        ),
+        pytest.param(
+            "<Provider theme='dark'><Toolbar /></Provider>",
+            {},
+            '<nav><button class="dark">a</button><button class="dark">b</button></nav>',
+            id="descendants",
+        ),
+        pytest.param(
+            "<><Provider theme='dark'><Button>a</Button></Provider>"
+            "<Button>b</Button></>",
+            {},
+            '<button class="dark">a</button><button class="light">b</button>',
+            id="scoped",
+        ),
+        pytest.param(
+            "<Provider theme='dark'><Provider theme='red'><Button>a</Button>"
+            "</Provider><Button>b</Button></Provider>",
+            {},
+            '<button class="red">a</button><button class="dark">b</button>',
+            id="nested",
+        ),
+        pytest.param(
+            "<Dark><Toolbar /></Dark>",
+            {},
+            '<nav><button class="dark">a</button><button class="dark">b</button></nav>',
+            id="component-provider",
+        ),
+        pytest.param(
+            "<Provider current-user={user}><User /></Provider>",
+            {"user": {"name": "Bob"}},
+            "<span>Bob</span>",
+            id="expression",
+        ),
+        pytest.param(
+            "<Provide />",
+            {},
+            '<button class="blue">a</button>',
+            id="provide",
+        ),
+        pytest.param(
+            "<><Provide /><Button>b</Button></>",
+            {},
+            '<button class="blue">a</button><button class="light">b</button>',
+            id="provide-scoped",
+        ),
+        pytest.param("<Badge />", {}, "<b>red</b>", id="namespace"),
+        pytest.param(
+            "<Provider theme='dark'>{inject('theme')}</Provider>",
+            {},
+            "dark",
+            id="template",
+        ),
+    ],
+)
+def test_render_provider(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+def test_inject_missing(catalog: Catalog):
+    with pytest.raises(KeyError):
+        catalog.render("<User />")
+
+
+def test_provide_missing_value(catalog: Catalog):
+    with pytest.raises(TypeError):
+        catalog.render("<>{provide('theme')}</>")
@@ -0,0 +1,98 @@
+import os
+import warnings
+from pathlib import Path
+
+import pytest
+
+from xcomponent import Catalog
+
+
+def write_template(path: Path, template: str, mtime: int) -> None:
+    path.write_text(template)
+    # the modification time of files written in a row may not change
+    os.utime(path, (mtime, mtime))
+
+
+@pytest.fixture
+def template_path(tmp_path: Path) -> Path:
+    path = tmp_path / "Card.xhtml"
+    write_template(path, "<!-- props: title: str --><p>{title}</p>", 1_000_000)
+    return path
+
+
+@pytest.fixture
+def catalog(template_path: Path) -> Catalog:
+    catalog = Catalog(reload=True)
+    catalog.load_directory(template_path.parent)
+    return catalog
+
+
+def test_origin(catalog: Catalog, template_path: Path):
+    assert catalog._catalog.get("Card").origin == template_path
+
+
+def test_reload(catalog: Catalog, template_path: Path):
+    assert catalog.render("<Card title='a' />") == "<p>a</p>"
+    write_template(
+        template_path,
+        "<!-- props: title: str, count: int = 1 --><h2>{title}{count}</h2>",
+        2_000_000,
+    )
+    assert catalog.render("<Card title='a' />") == "<h2>a1</h2>"
+    assert catalog._catalog.get("Card").defaults == {"count": 1}
+
+
+def test_reload_disabled(catalog: Catalog, template_path: Path):
+    catalog.reload = False
+    write_template(template_path, "<h2>changed</h2>", 2_000_000)
+    assert catalog.render("<Card title='a' />") == "<p>a</p>"
+
+
+@pytest.mark.parametrize(
+    "template",
+    [
+        pytest.param("<!-- props: title: str --><p>{title}", id="markup"),
+        pytest.param("<!-- props: title: Unknown --><p>{title}</p>", id="props"),
+    ],
+)
+def test_reload_error(catalog: Catalog, template_path: Path, template: str):
+    write_template(template_path, template, 2_000_000)
+    with warnings.catch_warnings(record=True) as caught:
+        warnings.simplefilter("always")
+        assert catalog.render("<Card title='a' />") == "<p>a</p>"
+        # the error is reported once
+        assert catalog.render("<Card title='a' />") == "<p>a</p>"
+    assert len(caught) == 1
+    assert str(caught[0].message).startswith(
+        f"Cannot reload component <Card/> from {template_path}, "
+        "the previous version is kept"
+    )
+
+    write_template(template_path, "<h2>{title}</h2>", 3_000_000)
+    assert catalog.render("<Card title='a' />") == "<h2>a</h2>"
+
+
+def test_reload_deleted(catalog: Catalog, template_path: Path):
+    template_path.unlink()
+    with pytest.warns(UserWarning):
+        assert catalog.render("<Card title='a' />") == "<p>a</p>"
+
+
+def test_reload_namespace(tmp_path: Path):
+    path = tmp_path / "ui" / "Button.xhtml"
+    path.parent.mkdir()
+    write_template(path, "<button>ok</button>", 1_000_000)
+    catalog = Catalog(reload=True)
+    catalog.load_directory(tmp_path)
+    assert catalog.render("<ui.Button />") == "<button>ok</button>"
+    write_template(path, "<button>cancel</button>", 2_000_000)
+    assert catalog.render("<ui.Button />") == "<button>cancel</button>"
+
+
+def test_reload_component(catalog: Catalog, template_path: Path):
+    @catalog.component
+    def Page() -> str:
+        return """<main><Card title="page" /></main>"""
+
+    write_template(template_path, "<h2>{title}</h2>", 2_000_000)
+    assert Page() == "<main><h2>page</h2></main>"
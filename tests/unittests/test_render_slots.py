@@ -0,0 +1,188 @@
+from typing import Any
+
+import pytest
+
+from xcomponent import Catalog, XNode
+
+
+@pytest.fixture(autouse=True)
+def components(catalog: Catalog, layout_catalog: Catalog):
+    @catalog.component
+    def Layout(children: XNode, header: XNode = None, footer: XNode = None) -> str:
+        return """
+            <div>
+                <header><slot:header>Default header</slot:header></header>
+                <main>{children}</main>
+                <footer><slot:footer /></footer>
+            </div>
+        """
+
+    @catalog.component
+    def Card(children: XNode, card_title: XNode = None) -> str:
+        return """<section><h2>{card_title}</h2>{children}</section>"""
+
+    @catalog.component
+    def Page(title: str) -> str:
+        return """
+            <Layout>
+                <slot:header><h1>{title}</h1></slot:header>
+                <p>content</p>
+                <slot:footer>by {title}</slot:footer>
+            </Layout>
+        """
+
+    @catalog.component
+    def Forward(children: XNode, title: XNode = None) -> str:
+        return """
+            <Layout>
+                <slot:header>
+                    <small><slot:title>untitled</slot:title></small>
+                </slot:header>
+                {children}
+            </Layout>
+        """
+
+    @catalog.component
+    def Counter(count: int, label: XNode = None) -> str:
+        return """<>{for i in range(count) { <slot:label /> }}</>"""
+
+    @catalog.function
+    def to_str(value: Any) -> str:
+        return str(value)
+
+    @catalog.component(use={"layout": layout_catalog})
+    def NSPage(title: str) -> str:
+        return """
+            <layout.Box><slot:title><b>{title}</b></slot:title>body</layout.Box>
+        """
+
+
+@pytest.fixture
+def layout_catalog() -> Catalog:
+    layout = Catalog()
+
+    @layout.component
+    def Box(children: XNode, title: XNode = None) -> str:
+        return """<div><slot:title>no title</slot:title>|{children}</div>"""
+
+    return layout
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            "<Page title='Hello' />",
+            {},
+            "<div><header><h1>Hello</h1></header><main><p>content</p></main>"
+            "<footer>by Hello</footer></div>",
+            id="named-slots",
+        ),
+        pytest.param(
+            "<Layout><p>content</p></Layout>",
+            {},
+            "<div><header>Default header</header><main><p>content</p></main>"
+            "<footer></footer></div>",
+            id="fallback",
+        ),
+        pytest.param(
+            "<Layout header={<h1>{title}</h1>}><p>content</p></Layout>",
+            {"title": "Hello"},
+            "<div><header><h1>Hello</h1></header><main><p>content</p></main>"
+            "<footer></footer></div>",
+            id="markup-prop",
+        ),
+        pytest.param(
+            "<Card><slot:card-title>{title}</slot:card-title>body</Card>",
+            {"title": "Hello"},
+            "<section><h2>Hello</h2>body</section>",
+            id="variable",
+        ),
+        pytest.param(
+            "<Card><slot:card_title>a</slot:card_title>"
+            "<slot:card_title>b</slot:card_title>c</Card>",
+            {},
+            "<section><h2>ab</h2>c</section>",
+            id="merged",
+        ),
+        pytest.param(
+            "<Forward><slot:title>{title}</slot:title>body</Forward>",
+            {"title": "Hello"},
+            "<div><header><small>Hello</small></header><main>body</main>"
+            "<footer></footer></div>",
+            id="forward",
+        ),
+        pytest.param(
+            "<Forward>body</Forward>",
+            {},
+            "<div><header><small>untitled</small></header><main>body</main>"
+            "<footer></footer></div>",
+            id="forward-fallback",
+        ),
+        pytest.param(
+            "<Counter count={2}><slot:label><i>{'<x>'}</i></slot:label></Counter>",
+            {},
+            "<i>&lt;x&gt;</i><i>&lt;x&gt;</i>",
+            id="rendered-each-use",
+        ),
+        pytest.param(
+            "<NSPage title='Hello' />",
+            {},
+            "<div><b>Hello</b>|body</div>",
+            id="namespace",
+        ),
+    ],
+)
+def test_render_slots(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+def test_slot_lazy(catalog: Catalog):
+    @catalog.component
+    def Hidden(show: bool, content: XNode = None) -> str:
+        return """<>{if show { <slot:content /> }}</>"""
+
+    # the slot is not rendered if the component does not use it
+    rendered = catalog.render(
+        "<Hidden show={false}><slot:content>{missing}</slot:content></Hidden>"
+    )
+    assert rendered == ""
+    with pytest.raises(UnboundLocalError):
+        catalog.render(
+            "<Hidden show={true}><slot:content>{missing}</slot:content></Hidden>"
+        )
+
+
+def test_slot_python(catalog: Catalog):
+    @catalog.component
+    def Upper(label: XNode = None) -> str:
+        return """<>{to_str(label).upper()}</>"""
+
+    rendered = catalog.render(
+        "<Upper><slot:label><b>{x}</b></slot:label></Upper>", x="a"
+    )
+    assert rendered == "&lt;B&gt;A&lt;/B&gt;"
+
+
+@pytest.mark.parametrize(
+    "template,expected",
+    [
+        pytest.param("<>{is_str(children)}</>", "true", id="function"),
+        pytest.param("<>{children | is_str}</>", "true", id="pipe"),
+        pytest.param("<>{children.upper()}</>", "<B>A</B>", id="method"),
+        pytest.param("<>{children | length}</>", "8", id="filter"),
+    ],
+)
+def test_slot_str(catalog: Catalog, template: str, expected: str):
+    # the children are given to the functions as a markup string
+    @catalog.function
+    def is_str(value: Any) -> bool:
+        return isinstance(value, str)
+
+    @catalog.component
+    def Wrap(children: XNode) -> str:
+        return template
+
+    assert catalog.render("<Wrap><b>{x}</b></Wrap>", x="a") == expected
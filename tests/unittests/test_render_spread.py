@@ -0,0 +1,132 @@
+from typing import Any
+
+import pytest
+
+from xcomponent import Catalog, XNode
+from xcomponent.xcore import parse_markup
+
+
+@pytest.fixture(autouse=True)
+def components(catalog: Catalog):
+    @catalog.component
+    def Button(children: XNode, attrs: dict[str, Any] | None = None) -> str:
+        return """<button {...attrs} class="btn" type="button">{children}</button>"""
+
+    @catalog.component
+    def Link(href: str, class_: str = "", target: str = "_self") -> str:
+        return """<a href={href} class={class_} target={target}>link</a>"""
+
+
+@pytest.mark.parametrize(
+    "template,params,expected",
+    [
+        pytest.param(
+            "<input {...attrs} />",
+            {"attrs": {"name": "q", "aria-label": "Search", "hx-get": "/search"}},
+            '<input name="q" aria-label="Search" hx-get="/search"/>',
+            id="element",
+        ),
+        pytest.param(
+            "<input {...attrs} name='a' />",
+            {"attrs": {"name": "q", "id": "i"}},
+            '<input name="a" id="i"/>',
+            id="last-wins",
+        ),
+        pytest.param(
+            "<input name='a' {...attrs} />",
+            {"attrs": {"name": "q"}},
+            '<input name="q"/>',
+            id="spread-wins",
+        ),
+        pytest.param(
+            "<input {...a} {...b} />",
+            {"a": {"name": "a", "id": "a"}, "b": {"name": "b"}},
+            '<input name="b" id="a"/>',
+            id="two-spreads",
+        ),
+        pytest.param(
+            "<div {...attrs} class='box'>a</div>",
+            {"attrs": {"class": "big"}},
+            '<div class="big box">a</div>',
+            id="class",
+        ),
+        pytest.param(
+            "<div class={cls} {...attrs}>a</div>",
+            {"cls": None, "attrs": {"class": "big"}},
+            '<div class="big">a</div>',
+            id="class-none",
+        ),
+        pytest.param(
+            "<div {...attrs} style='color: red; margin: 0'>a</div>",
+            {"attrs": {"style": "COLOR: blue;padding:1px;"}},
+            '<div style="color: red; padding: 1px; margin: 0">a</div>',
+            id="style",
+        ),
+        pytest.param(
+            "<input {...attrs} />",
+            {"attrs": {"disabled": True, "hidden": False, "title": None}},
+            "<input disabled/>",
+            id="bool",
+        ),
+        pytest.param(
+            "<input {...attrs} />",
+            {"attrs": {"title": "<b>"}},
+            '<input title="&lt;b&gt;"/>',
+            id="escaped",
+        ),
+        pytest.param(
+            "<input {...attrs} />", {"attrs": None}, "<input/>", id="none"
+        ),
+        pytest.param(
+            "<input {...{'name': 'q'}} />", {}, '<input name="q"/>', id="dict-literal"
+        ),
+        pytest.param(
+            "<Button attrs={attrs}>Go</Button>",
+            {"attrs": {"class": "primary", "hx-post": "/go", "type": "submit"}},
+            '<button class="primary btn" hx-post="/go" type="button">Go</button>',
+            id="forward",
+        ),
+        pytest.param(
+            "<Link {...props} target='_blank' />",
+            {"props": {"href": "/a", "class": "nav", "target": "_top"}},
+            '<a href="/a" class="nav" target="_blank">link</a>',
+            id="component",
+        ),
+        pytest.param(
+            "<>{for link in links { <Link {...link} /> }}</>",
+            {"links": [{"href": "/a"}, {"href": "/b"}]},
+            '<a href="/a" class="" target="_self">link</a>'
+            '<a href="/b" class="" target="_self">link</a>',
+            id="expression-markup",
+        ),
+    ],
+)
+def test_render_spread(
+    catalog: Catalog, template: str, params: dict[str, Any], expected: str
+):
+    assert catalog.render(template, **params) == expected
+
+
+@pytest.mark.parametrize(
+    "template,params,error",
+    [
+        pytest.param("<input {...attrs} />", {"attrs": [1]}, TypeError, id="list"),
+        pytest.param(
+            "<input {...attrs} />", {"attrs": {"a b": 1}}, ValueError, id="name"
+        ),
+        pytest.param(
+            "<input {...attrs} />", {"attrs": {'a"': 1}}, ValueError, id="quote"
+        ),
+    ],
+)
+def test_render_spread_error(
+    catalog: Catalog, template: str, params: dict[str, Any], error: type[Exception]
+):
+    with pytest.raises(error):
+        catalog.render(template, **params)
+
+
+def test_parse_spread():
+    node = parse_markup("<input { ...attrs } name='q' />")
+    assert repr(node) == '<input {...attrs} name="q"/>'
+    assert list(node.unwrap().attrs) == ["...attrs", "name"]
@@ -0,0 +1,103 @@
+from contextvars import ContextVar
+
+import pytest
+from xcomponent import Catalog, XNode
+
+current_user: ContextVar[str] = ContextVar("current_user", default="anonymous")
+
+
+@pytest.fixture(autouse=True)
+def components(catalog: Catalog):
+    @catalog.component
+    def Item(name: str) -> str:
+        return """<li>{name}</li>"""
+
+    @catalog.component
+    def Layout(title: str, children: XNode) -> str:
+        return """
+            <>
+                <!DOCTYPE html>
+                <html>
+                    <head><title>{title}</title></head>
+                    <body>{children}</body>
+                </html>
+            </>
+        """
+
+    @catalog.component
+    def Page(names: list[str]) -> str:
+        return """
+            <Layout title="Listing">
+                <ul>{for name in names { <Item name={name} /> }}</ul>
+            </Layout>
+        """
+
+
+def test_render_iter(catalog: Catalog):
+    chunks = list(catalog.render_iter("<Page names={names} />", names=["a", "<b>"]))
+    assert "".join(chunks) == (
+        "<!DOCTYPE html><html><head><title>Listing</title></head>"
+        "<body><ul><li>a</li><li>&lt;b&gt;</li></ul></body></html>"
+    )
+
+
+def test_render_iter_chunks(catalog: Catalog):
+    template = "<ul>{for name in names { <Item name={name} /> }}</ul>"
+    names = [f"name {i}" for i in range(5000)]
+    chunks = list(catalog.render_iter(template, names=names))
+    assert len(chunks) > 1
+    assert "".join(chunks) == catalog.render(template, names=names)
+
+
+def test_render_iter_error(catalog: Catalog):
+    chunks = catalog.render_iter("<Page names={names} />", names=None)
+    with pytest.raises(TypeError):
+        list(chunks)
+
+
+def test_render_iter_stopped(catalog: Catalog):
+    names = [f"name {i}" for i in range(5000)]
+    chunks = catalog.render_iter("<Page names={names} />", names=names)
+    assert next(chunks).startswith("<!DOCTYPE html><html>")
+    del chunks
+    assert catalog.render("<Item name='a' />") == "<li>a</li>"
+
+
+def test_render_iter_context(catalog: Catalog):
+    @catalog.function
+    def get_current_user() -> str:
+        return current_user.get()
+
+    token = current_user.set("alice")
+    try:
+        chunks = catalog.render_iter("<p>{get_current_user()}</p>")
+    finally:
+        current_user.reset(token)
+    assert "".join(chunks) == "<p>alice</p>"
+
+
+def test_render_iter_updated_catalog(catalog: Catalog):
+    names = [f"name {i}" for i in range(5000)]
+    chunks = catalog.render_iter("<Page names={names} />", names=names)
+    assert next(chunks).startswith("<!DOCTYPE html><html>")
+
+    @catalog.component
+    def Other() -> str:
+        return """<p>other</p>"""
+
+    catalog.validation = "warn"
+    assert catalog.render("<Other />") == "<p>other</p>"
+    assert "".join(chunks).endswith("<li>name 4999</li></ul></body></html>")
+
+
+def test_render_iter_namespace(catalog: Catalog):
+    app = Catalog()
+
+    @app.component(use={"ui": catalog})
+    def Home(names: list[str]) -> str:
+        return """<ui.Page names={names} />"""
+
+    names = [f"name {i}" for i in range(5000)]
+    chunks = list(app.render_iter("<Home names={names} />", names=names))
+    assert len(chunks) > 1
+    assert "".join(chunks) == app.render("<Home names={names} />", names=names)
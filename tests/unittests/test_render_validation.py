@@ -0,0 +1,173 @@
+import warnings
+from typing import Any, Optional
+
+import pytest
+
+from xcomponent import Catalog, XComponentRenderError, XNode
+
+
+@pytest.fixture
+def catalog() -> Catalog:
+    catalog = Catalog(validation="strict")
+
+    @catalog.component
+    def Card(title: str, children: XNode, count: int = 0) -> str:
+        return """<div><h2>{title}</h2>{children}{count}</div>"""
+
+    @catalog.component
+    def Typed(
+        flag: bool = False,
+        ratio: float = 1.0,
+        items: list[str] | None = None,
+        meta: dict[str, Any] | None = None,
+        label: Optional[str] = None,
+        head: XNode = None,
+        anything: Any = None,
+    ) -> str:
+        return """<p>typed</p>"""
+
+    @catalog.component
+    def Page(globals: Any) -> str:
+        return """<p>page</p>"""
+
+    return catalog
+
+
+@pytest.mark.parametrize(
+    "template,expected",
+    [
+        pytest.param(
+            "<Card title='a'>b</Card>", "<div><h2>a</h2>b0</div>", id="required"
+        ),
+        pytest.param(
+            "<Card title='a' count={2}>b</Card>", "<div><h2>a</h2>b2</div>", id="int"
+        ),
+        pytest.param(
+            "<Card title='a'><b>b</b></Card>",
+            "<div><h2>a</h2><b>b</b>0</div>",
+            id="children",
+        ),
+        pytest.param("<Card title='a' />", "<div><h2>a</h2>0</div>", id="no-children"),
+        pytest.param("<Page />", "<p>page</p>", id="globals"),
+        pytest.param(
+            "<Typed flag ratio={2} items={['a']} meta={{'a': 1}} label='l' />",
+            "<p>typed</p>",
+            id="types",
+        ),
+        pytest.param(
+            "<Typed ratio={2.5} items={None} label={None} />", "<p>typed</p>", id="none"
+        ),
+        pytest.param(
+            "<Typed head={<b>head</b>} anything={[1]} />", "<p>typed</p>", id="xnode"
+        ),
+        pytest.param(
+            "<Typed><slot:head>head</slot:head></Typed>", "<p>typed</p>", id="slot"
+        ),
+        pytest.param("<Typed {...attrs} />", "<p>typed</p>", id="spread"),
+    ],
+)
+def test_valid_props(catalog: Catalog, template: str, expected: str):
+    assert catalog.render(template, attrs={"flag": True}) == expected
+
+
+@pytest.mark.parametrize(
+    "template,error",
+    [
+        pytest.param(
+            "<Card titel='a'>b</Card>", "unknown attribute 'titel'", id="unknown"
+        ),
+        pytest.param("<Card>b</Card>", "missing attribute 'title'", id="missing"),
+        pytest.param(
+            "<Card title={1}>b</Card>",
+            "attribute 'title' expects str, got int",
+            id="str",
+        ),
+        pytest.param(
+            "<Card title='a' count='2'>b</Card>",
+            "attribute 'count' expects int, got str",
+            id="int",
+        ),
+        pytest.param(
+            "<Typed flag={1} />", "attribute 'flag' expects bool, got int", id="bool"
+        ),
+        pytest.param(
+            "<Typed items={'a'} />",
+            "attribute 'items' expects list[str] | None, got str",
+            id="list",
+        ),
+        pytest.param(
+            "<Typed meta={[]} />",
+            "attribute 'meta' expects dict[str, typing.Any] | None, got list",
+            id="dict",
+        ),
+        pytest.param(
+            "<Typed label={1} />",
+            "attribute 'label' expects typing.Optional[str], got int",
+            id="optional",
+        ),
+        pytest.param(
+            "<Typed head={1} />", "attribute 'head' expects XNode, got int", id="xnode"
+        ),
+        pytest.param(
+            "<Typed {...attrs} />", "unknown attribute 'hx_get'", id="spread"
+        ),
+    ],
+)
+def test_strict(catalog: Catalog, template: str, error: str):
+    with pytest.raises(TypeError) as exc:
+        catalog.render(template, attrs={"hx-get": "/"})
+    assert error in str(exc.value)
+    assert isinstance(exc.value, XComponentRenderError)
+
+
+def test_strict_reports_all_problems(catalog: Catalog):
+    with pytest.raises(TypeError) as exc:
+        catalog.render("<Card titel='a' count='1' />")
+    assert str(exc.value).splitlines()[0] == (
+        "<Card> unknown attribute 'titel', attribute 'count' expects int, got str, "
+        "missing attribute 'title'"
+    )
+
+
+def test_warn(catalog: Catalog):
+    catalog.validation = "warn"
+    with pytest.warns(UserWarning) as records:
+        rendered = catalog.render("<Card title='a' titel='x' count='2'>b</Card>")
+    assert rendered == "<div><h2>a</h2>b2</div>"
+    assert [str(r.message) for r in records] == [
+        "<Card> unknown attribute 'titel'",
+        "<Card> attribute 'count' expects int, got str",
+    ]
+
+
+def test_off(catalog: Catalog):
+    catalog.validation = "off"
+    with warnings.catch_warnings():
+        warnings.simplefilter("error")
+        rendered = catalog.render("<Card title='a' titel='x' count='2'>b</Card>")
+    assert rendered == "<div><h2>a</h2>b2</div>"
+
+
+def test_default_off():
+    assert Catalog().validation == "off"
+
+
+def test_invalid_mode():
+    with pytest.raises(ValueError):
+        Catalog(validation="loud")  # type: ignore
+
+
+def test_namespace(catalog: Catalog):
+    app = Catalog(validation="off")
+
+    @app.component(use={"ui": catalog})
+    def Home() -> str:
+        return """<ui.Card titel="a">b</ui.Card>"""
+
+    with pytest.raises(TypeError, match="<ui.Card> unknown attribute 'titel'"):
+        app.render("<Home />")
+
+
+def test_off_without_children(catalog: Catalog):
+    catalog.validation = "off"
+    assert catalog.render("<Card title='a' />") == "<div><h2>a</h2>0</div>"
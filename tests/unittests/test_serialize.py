@@ -0,0 +1,157 @@
+import pickle
+from pathlib import Path
+from typing import Any
+
+import pytest
+
+from xcomponent import Catalog, XNode
+from xcomponent.xcore import parse_markup
+
+
+def shout(text: str) -> str:
+    return text.upper()
+
+
+@pytest.fixture(autouse=True)
+def components(catalog: Catalog):
+    ui = Catalog()
+
+    @ui.component
+    def Button(label: str, kind: str = "primary") -> str:
+        return """<button class={kind}>{label}</button>"""
+
+    catalog.function(shout)
+
+    @catalog.component(use={"ui": ui})
+    def Card(title: str, items: list[Any], children: XNode = None) -> str:
+        return """
+            <>
+                <!DOCTYPE html>
+                <!-- card -->
+                <div class="card" {...{"data-title": title}}>
+                    <h2>{shout(title)}</h2>
+                    <ul>{for item in items { <li>{item}</li> }}</ul>
+                    <ui.Button label={title} />
+                    {children}
+                    <script>var x = {"a": 1};</script>
+                </div>
+            </>
+        """
+
+    @catalog.component
+    def Expressions(value: Any) -> str:
+        return """
+            <p>
+                {let double = (x) => x * 2}
+                {match value { 1 => "one", 2 | 3 => "few", n if n > 9 => "many",
+                               _ => "other" }}
+                {double(value)} {-value} {not value} {value ?? 0}
+                {f"v={value}"} {[1, 2, 3][1:]} {{"a": 1.5}?.a} {value is None}
+                {<b>{value}</b>} {if value > 2 { "big" } else { "small" }}
+                {value + 99999999999999999999}
+            </p>
+        """
+
+
+@pytest.mark.parametrize(
+    "template",
+    [
+        pytest.param("<Card title='t' items={[1, 2]}>child</Card>", id="card"),
+        pytest.param("<Expressions value={1} />", id="expressions"),
+        pytest.param("<Expressions value={10} />", id="match"),
+    ],
+)
+def test_pickle_catalog(catalog: Catalog, template: str):
+    rendered = catalog.render(template)
+    loaded = pickle.loads(pickle.dumps(catalog))
+    assert loaded.render(template) == rendered
+
+
+def test_pickle_catalog_state(catalog: Catalog):
+    catalog = Catalog(cache_size=4, validation="warn", reload=True)
+    loaded = pickle.loads(pickle.dumps(catalog))
+    assert loaded.validation == "warn"
+    assert loaded.reload is True
+    assert loaded.cache_info().maxsize == 4
+
+
+def test_pickle_template(catalog: Catalog):
+    template = pickle.loads(pickle.dumps(catalog._catalog.get("Card")))
+    assert template.node == catalog._catalog.get("Card").node
+    assert template.params == {"title": str, "items": list[Any], "children": XNode}
+    assert template.defaults == {"children": None}
+    assert template.origin is None
+
+
+@pytest.mark.parametrize(
+    "template",
+    [
+        pytest.param("<p>text</p>", id="element"),
+        pytest.param("<><!DOCTYPE html><!-- comment --></>", id="fragment"),
+        pytest.param("<ui.Button {...attrs} label='a' />", id="namespace"),
+        pytest.param("<style>p { color: red; }</style>", id="script"),
+        pytest.param(
+            "<p>{[x, {'a': y}][0] + 1 if z else None}</p>", id="expression"
+        ),
+    ],
+)
+def test_pickle_node(template: str):
+    node = parse_markup(template)
+    assert pickle.loads(pickle.dumps(node)) == node
+    assert XNode.from_state(node.__getstate__()) == node
+
+
+def test_pickle_compiled_expressions(catalog: Catalog):
+    # the expressions are compiled when the state is built, the rendering
+    # does not change it
+    node = catalog._catalog.get("Expressions").node
+    state = node.__getstate__()
+    catalog.render("<Expressions value={1} />")
+    assert node.__getstate__() == state
+    assert XNode.from_state(state).__getstate__() == state
+
+
+def test_pickle_error_location(catalog: Catalog):
+    loaded = pickle.loads(pickle.dumps(catalog))
+    with pytest.raises(TypeError) as exc:
+        loaded.render("<Expressions value='a' />")
+    with pytest.raises(TypeError) as expected:
+        catalog.render("<Expressions value='a' />")
+    assert exc.value.__notes__[0].startswith("component `Expressions`, line ")
+    assert exc.value.__notes__ == expected.value.__notes__
+
+
+@pytest.mark.parametrize(
+    "state,error",
+    [
+        pytest.param(b"", "not encoded by xcomponent", id="empty"),
+        pytest.param(
+            b"xcomponent\x050.0.0", "encoded by xcomponent 0.0.0", id="version"
+        ),
+        pytest.param(
+            parse_markup("<p>a</p>").__getstate__()[:-3],
+            "unexpected end",
+            id="truncated",
+        ),
+    ],
+)
+def test_invalid_state(state: bytes, error: str):
+    with pytest.raises(ValueError) as exc:
+        XNode.from_state(state)
+    assert error in str(exc.value)
+
+
+def test_save_load(catalog: Catalog, tmp_path: Path):
+    path = tmp_path / "catalog.bin"
+    catalog.save(path)
+    loaded = Catalog.load(path)
+    assert loaded.render("<Card title='t' items={[1]} />") == catalog.render(
+        "<Card title='t' items={[1]} />"
+    )
+
+
+def test_load_invalid(tmp_path: Path):
+    path = tmp_path / "catalog.bin"
+    path.write_bytes(pickle.dumps({"not": "a catalog"}))
+    with pytest.raises(TypeError):
+        Catalog.load(path)